@@ -0,0 +1,246 @@
+use chess::chess::chess_player::ChessPlayer;
+use chess::chess::game::{Game, GameStatus};
+use chess::chess::position::RandomOpening;
+use chess::chess::search::{SearchOptions, Searcher, TB_WIN_SCORE};
+use rand::distributions::Distribution;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+
+// Games longer than this are scored as draws
+const MAX_GAME_PLIES: usize = 400;
+// A side this far ahead for ADJUDICATION_PLIES plies in a row wins
+const ADJUDICATION_SCORE: i32 = 1500;
+const ADJUDICATION_PLIES: usize = 8;
+
+struct Config {
+    output: String,
+    games: usize,
+    threads: usize,
+    nodes: u64,
+    seed: u64,
+    opening_plies: usize,
+}
+
+fn parse_args() -> Result<Config, String> {
+    let args: Vec<String> = env::args().collect();
+    let mut config = Config {
+        output: String::new(),
+        games: 100,
+        threads: 1,
+        nodes: 5000,
+        seed: 0,
+        opening_plies: 8,
+    };
+    let mut k = 1;
+    while k < args.len() {
+        let flag = args[k].as_str();
+        if !flag.starts_with("--") {
+            config.output = args[k].clone();
+            k += 1;
+            continue;
+        }
+        let value = args
+            .get(k + 1)
+            .ok_or_else(|| format!("missing value of {}", flag))?;
+        let number: u64 = value
+            .parse()
+            .map_err(|_| format!("invalid value {} of {}", value, flag))?;
+        match flag {
+            "--games" => config.games = number as usize,
+            "--threads" => config.threads = (number as usize).max(1),
+            "--nodes" => config.nodes = number,
+            "--seed" => config.seed = number,
+            "--opening-plies" => config.opening_plies = number as usize,
+            _ => return Err(format!("unknown option {}", flag)),
+        }
+        k += 2;
+    }
+    if config.output.is_empty() {
+        return Err("missing output file".to_string());
+    }
+    Ok(config)
+}
+
+// Plays one self-play game, returns its records. Everything depends only on
+// the seed and the game index, so the output is the same with any number of
+// threads.
+fn play_game(config: &Config, searcher: &mut Searcher, index: usize) -> Vec<String> {
+    let mut rng = StdRng::seed_from_u64(
+        config
+            .seed
+            .wrapping_mul(0x9e37_79b9_7f4a_7c15)
+            .wrapping_add(index as u64),
+    );
+    let opening = RandomOpening {
+        plies: config.opening_plies,
+    };
+    let start = loop {
+        let pos = opening.sample(&mut rng);
+        if !pos.get_valid_moves().is_empty() {
+            break pos;
+        }
+    };
+
+    searcher.clear();
+    let mut game = Game::new(start);
+    // (fen, score from the point of view of white)
+    let mut records: Vec<(String, i32)> = Vec::new();
+    let mut winning_streak: (Option<ChessPlayer>, usize) = (None, 0);
+    let mut adjudicated = None;
+
+    while game.get_status() == GameStatus::Ongoing && game.get_moves().len() < MAX_GAME_PLIES {
+        let pos = *game.get_position();
+        let player = pos.get_player_on_move();
+        searcher.set_halfmove_clock(game.get_halfmove_clock());
+        let (m, score) = searcher.search_nodes(&pos, config.nodes);
+        let m = m.unwrap();
+        let white_score = match player {
+            ChessPlayer::White => score,
+            ChessPlayer::Black => -score,
+        };
+
+        // noisy positions teach an evaluation little
+        let quiet = !pos.is_in_check(player) && !pos.is_capture(m) && !pos.is_promotion(m);
+        if quiet && score.abs() < TB_WIN_SCORE {
+            records.push((game.get_fen(), white_score));
+        }
+
+        let leader = if white_score >= ADJUDICATION_SCORE {
+            Some(ChessPlayer::White)
+        } else if white_score <= -ADJUDICATION_SCORE {
+            Some(ChessPlayer::Black)
+        } else {
+            None
+        };
+        winning_streak = match (leader, winning_streak) {
+            (Some(p), (Some(q), count)) if p == q => (leader, count + 1),
+            (Some(_), _) => (leader, 1),
+            (None, _) => (None, 0),
+        };
+        if winning_streak.1 >= ADJUDICATION_PLIES {
+            adjudicated = leader;
+            break;
+        }
+        game.make_move(m);
+    }
+
+    let winner = adjudicated.or_else(|| game.get_status().get_winner());
+    let result = match winner {
+        Some(ChessPlayer::White) => "1.0",
+        Some(ChessPlayer::Black) => "0.0",
+        None => "0.5",
+    };
+    records
+        .into_iter()
+        .map(|(fen, score)| format!("{} | {} | {}", fen, score, result))
+        .collect()
+}
+
+// Usage: datagen <output> [--games N] [--threads N] [--nodes N] [--seed N]
+//                [--opening-plies N]
+// Writes one "FEN | score | result" line per position, score in centipawns
+// and result (1.0, 0.5, 0.0) both from the point of view of white.
+fn main() {
+    let config = match parse_args() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            eprintln!(
+                "usage: datagen <output> [--games N] [--threads N] [--nodes N] [--seed N] \
+                 [--opening-plies N]"
+            );
+            process::exit(1);
+        }
+    };
+    let file = match File::create(&config.output) {
+        Ok(file) => file,
+        Err(e) => {
+            eprintln!("{}: {}", config.output, e);
+            process::exit(1);
+        }
+    };
+    let mut writer = BufWriter::new(file);
+    generate(&config, &mut writer);
+    writer.flush().unwrap();
+}
+
+// Plays config.games games on config.threads threads and writes their
+// records to writer in the order of game indices
+fn generate(config: &Config, writer: &mut impl Write) {
+    let next_game = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel();
+    thread::scope(|scope| {
+        for _ in 0..config.threads {
+            let sender = sender.clone();
+            let next_game = &next_game;
+            scope.spawn(move || {
+                let mut searcher = Searcher::with_options(SearchOptions {
+                    hash_size_mb: 4,
+                    ..SearchOptions::default()
+                });
+                loop {
+                    let index = next_game.fetch_add(1, Ordering::Relaxed);
+                    if index >= config.games {
+                        break;
+                    }
+                    let records = play_game(config, &mut searcher, index);
+                    sender.send((index, records)).unwrap();
+                }
+            });
+        }
+        drop(sender);
+
+        // games finish out of order, they are written in the order of indices
+        let mut finished = BTreeMap::new();
+        let mut next_to_write = 0;
+        let mut positions = 0;
+        for (index, records) in receiver {
+            finished.insert(index, records);
+            while let Some(records) = finished.remove(&next_to_write) {
+                for record in records.iter() {
+                    writeln!(writer, "{}", record).unwrap();
+                }
+                positions += records.len();
+                next_to_write += 1;
+            }
+            eprintln!(
+                "{} / {} games, {} positions",
+                next_to_write, config.games, positions
+            );
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generate_with_threads(threads: usize) -> String {
+        let config = Config {
+            output: String::new(),
+            games: 3,
+            threads,
+            nodes: 500,
+            seed: 1,
+            opening_plies: 8,
+        };
+        let mut output = Vec::new();
+        generate(&config, &mut output);
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn output_does_not_depend_on_threads() {
+        let single = generate_with_threads(1);
+        assert!(!single.is_empty());
+        assert_eq!(single, generate_with_threads(3));
+    }
+}
@@ -0,0 +1,767 @@
+use chess::chess::chess_player::ChessPlayer;
+use chess::chess::game::Game;
+use chess::chess::pgn::{parse_pgn, write_pgn};
+use chess::chess::position::{Move, Position};
+use chess::chess::search::{SearchOptions, Searcher, MAX_DEPTH};
+use chess::chess::time_manager::{TimeControl, TimeManager};
+use chess::chess::uci_engine::{EngineScore, SearchLimit, UciEngine};
+use std::convert::TryInto;
+use std::env;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::process;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Games longer than this are drawn
+const MAX_GAME_PLIES: usize = 600;
+// time an engine may need on top of its clock before it is given up on
+const TIMEOUT_MARGIN: Duration = Duration::from_secs(5);
+// time an engine gets for a search limited by depth or nodes
+const UNTIMED_TIMEOUT: Duration = Duration::from_secs(600);
+// mate scores of UCI engines are converted to centipawns around this
+const MATE_CENTIPAWNS: i32 = 30000;
+
+#[derive(Debug, Clone)]
+enum EngineSpec {
+    Uci(String),
+    // name and options of a searcher of this crate
+    Builtin(String, SearchOptions),
+}
+
+#[derive(Debug, Copy, Clone)]
+enum Limit {
+    Clock { base: Duration, increment: Duration },
+    Nodes(u64),
+    Depth(u32),
+}
+
+struct Config {
+    engines: Vec<EngineSpec>,
+    games: usize,
+    concurrency: usize,
+    openings: Option<String>,
+    limit: Limit,
+    pgn: Option<String>,
+    // elo0, elo1, alpha, beta
+    sprt: Option<(f64, f64, f64, f64)>,
+    // a side with a score this bad for this many of its moves in a row loses
+    resign_score: i32,
+    resign_moves: usize,
+    // both sides with scores this close to zero for this many moves each
+    // after draw_move_number draw
+    draw_score: i32,
+    draw_moves: usize,
+    draw_move_number: u32,
+}
+
+// builtin[:option=value,...], options are the fields of SearchOptions
+fn parse_builtin(spec: &str) -> Result<SearchOptions, String> {
+    let mut options = SearchOptions::default();
+    let settings = match spec.split_once(':') {
+        Some((_, settings)) => settings,
+        None => return Ok(options),
+    };
+    for setting in settings.split(',').filter(|s| !s.is_empty()) {
+        let (name, value) = setting
+            .split_once('=')
+            .ok_or_else(|| format!("expected option=value, got {}", setting))?;
+        let invalid = || format!("invalid value {} of {}", value, name);
+        let flag = || value.parse::<bool>().map_err(|_| invalid());
+        let number = || value.parse::<f64>().map_err(|_| invalid());
+        match name {
+            "null_move" => options.null_move = flag()?,
+            "late_move_reductions" => options.late_move_reductions = flag()?,
+            "futility" => options.futility = flag()?,
+            "reverse_futility" => options.reverse_futility = flag()?,
+            "razoring" => options.razoring = flag()?,
+            "check_extensions" => options.check_extensions = flag()?,
+            "aspiration_windows" => options.aspiration_windows = flag()?,
+            "lmr_base" => options.lmr_base = number()?,
+            "lmr_divisor" => options.lmr_divisor = number()?,
+            "threads" => options.threads = (number()? as usize).max(1),
+            "hash" => options.hash_size_mb = (number()? as usize).max(1),
+            _ => return Err(format!("unknown builtin option {}", name)),
+        }
+    }
+    Ok(options)
+}
+
+fn parse_engine(spec: &str) -> Result<EngineSpec, String> {
+    if spec == "builtin" || spec.starts_with("builtin:") {
+        Ok(EngineSpec::Builtin(spec.to_string(), parse_builtin(spec)?))
+    } else {
+        Ok(EngineSpec::Uci(spec.to_string()))
+    }
+}
+
+// Time control as base+increment in seconds, e.g. 10+0.1
+fn parse_time_control(s: &str) -> Option<Limit> {
+    let (base, increment) = s.split_once('+').unwrap_or((s, "0"));
+    let seconds = |s: &str| {
+        s.parse::<f64>()
+            .ok()
+            .filter(|&seconds| seconds >= 0.0)
+            .map(Duration::from_secs_f64)
+    };
+    Some(Limit::Clock {
+        base: seconds(base)?,
+        increment: seconds(increment)?,
+    })
+}
+
+fn parse_args() -> Result<Config, String> {
+    let args: Vec<String> = env::args().collect();
+    let mut config = Config {
+        engines: Vec::new(),
+        games: 100,
+        concurrency: 1,
+        openings: None,
+        limit: Limit::Clock {
+            base: Duration::from_secs(10),
+            increment: Duration::from_millis(100),
+        },
+        pgn: None,
+        sprt: None,
+        resign_score: 1000,
+        resign_moves: 3,
+        draw_score: 10,
+        draw_moves: 8,
+        draw_move_number: 40,
+    };
+    let mut alpha_beta = (0.05, 0.05);
+    let mut k = 1;
+    while k < args.len() {
+        let flag = args[k].as_str();
+        // values of the flag, count depends on the flag
+        let value = |offset: usize| {
+            args.get(k + offset)
+                .map(|s| s.as_str())
+                .ok_or_else(|| format!("missing value of {}", flag))
+        };
+        let invalid = |s: &str| format!("invalid value {} of {}", s, flag);
+        let number = |offset: usize| -> Result<f64, String> {
+            let s = value(offset)?;
+            s.parse().map_err(|_| invalid(s))
+        };
+        match flag {
+            "--engine" => config.engines.push(parse_engine(value(1)?)?),
+            "--games" => config.games = number(1)? as usize,
+            "--concurrency" => config.concurrency = (number(1)? as usize).max(1),
+            "--openings" => config.openings = Some(value(1)?.to_string()),
+            "--tc" => {
+                let s = value(1)?;
+                config.limit = parse_time_control(s).ok_or_else(|| invalid(s))?;
+            }
+            "--nodes" => config.limit = Limit::Nodes(number(1)? as u64),
+            "--depth" => config.limit = Limit::Depth((number(1)? as u32).clamp(1, MAX_DEPTH)),
+            "--pgn" => config.pgn = Some(value(1)?.to_string()),
+            "--sprt" => config.sprt = Some((number(1)?, number(2)?, 0.0, 0.0)),
+            "--alpha" => alpha_beta.0 = number(1)?,
+            "--beta" => alpha_beta.1 = number(1)?,
+            "--resign" => {
+                config.resign_score = number(1)? as i32;
+                config.resign_moves = number(2)? as usize;
+            }
+            "--draw" => {
+                config.draw_score = number(1)? as i32;
+                config.draw_moves = number(2)? as usize;
+                config.draw_move_number = number(3)? as u32;
+            }
+            _ => return Err(format!("unknown option {}", flag)),
+        }
+        k += match flag {
+            "--sprt" | "--resign" => 3,
+            "--draw" => 4,
+            _ => 2,
+        };
+    }
+    if config.engines.len() != 2 {
+        return Err("expected two engines".to_string());
+    }
+    if let Some(sprt) = config.sprt.as_mut() {
+        sprt.2 = alpha_beta.0;
+        sprt.3 = alpha_beta.1;
+    }
+    Ok(config)
+}
+
+// Start positions with the moves leading to the position to play from, from
+// an EPD (one FEN per line) or a PGN file. PGN openings stop before their
+// first underpromotion, UCI moves sent to engines always promote to a queen.
+fn load_openings(path: &str) -> Result<Vec<(Position, Vec<Move>)>, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    let openings: Vec<(Position, Vec<Move>)> = if path.ends_with(".pgn") {
+        parse_pgn(&text)
+            .into_iter()
+            .map(|game| (game.start, game.get_moves_before_underpromotion().to_vec()))
+            .collect()
+    } else {
+        text.lines()
+            .filter_map(|line| Position::from_fen(line).ok())
+            .filter(|pos| !pos.get_valid_moves().is_empty())
+            .map(|pos| (pos, Vec::new()))
+            .collect()
+    };
+    if openings.is_empty() {
+        return Err(format!("{}: no openings found", path));
+    }
+    Ok(openings)
+}
+
+enum Player {
+    Uci(UciEngine),
+    Builtin(String, Box<Searcher>),
+}
+
+impl Player {
+    fn start(spec: &EngineSpec) -> Result<Self, String> {
+        match spec {
+            EngineSpec::Uci(path) => Ok(Player::Uci(UciEngine::start(path, &[])?)),
+            EngineSpec::Builtin(name, options) => Ok(Player::Builtin(
+                name.clone(),
+                Box::new(Searcher::with_options(*options)),
+            )),
+        }
+    }
+
+    fn get_name(&self) -> &str {
+        match self {
+            Player::Uci(engine) => engine.get_name(),
+            Player::Builtin(name, _) => name,
+        }
+    }
+
+    fn new_game(&mut self) -> Result<(), String> {
+        match self {
+            Player::Uci(engine) => engine.new_game(),
+            Player::Builtin(_, searcher) => {
+                searcher.clear();
+                Ok(())
+            }
+        }
+    }
+
+    // Best move in the current position of game and its score in
+    // centipawns from the point of view of the player on move, if known
+    fn play(
+        &mut self,
+        game: &Game,
+        limit: Limit,
+        clocks: &[Duration; 2],
+    ) -> Result<(Option<Move>, Option<i32>), String> {
+        let pos = game.get_position();
+        let own_clock = clocks[pos.get_player_on_move() as usize];
+        match self {
+            Player::Uci(engine) => {
+                engine.set_position(game.get_start_position(), game.get_moves())?;
+                let search_limit = match limit {
+                    Limit::Clock { increment, .. } => SearchLimit::Clock {
+                        white_time: clocks[ChessPlayer::White as usize],
+                        black_time: clocks[ChessPlayer::Black as usize],
+                        white_increment: increment,
+                        black_increment: increment,
+                        moves_to_go: None,
+                    },
+                    Limit::Nodes(nodes) => SearchLimit::Nodes(nodes),
+                    Limit::Depth(depth) => SearchLimit::Depth(depth),
+                };
+                let result = engine.go(search_limit, get_timeout(limit, own_clock))?;
+                let score = result
+                    .get_last_info()
+                    .and_then(|info| info.score)
+                    .map(|score| match score {
+                        EngineScore::Centipawns(cp) => cp,
+                        EngineScore::Mate(moves) if moves > 0 => MATE_CENTIPAWNS - moves,
+                        EngineScore::Mate(moves) => -MATE_CENTIPAWNS - moves,
+                    });
+                Ok((result.best_move, score))
+            }
+            Player::Builtin(_, searcher) => {
+                searcher.set_halfmove_clock(game.get_halfmove_clock());
+                let (best_move, score) = match limit {
+                    Limit::Clock { increment, .. } => {
+                        let time_control = TimeControl {
+                            remaining: own_clock,
+                            increment,
+                            moves_to_go: None,
+                            move_overhead: Duration::from_millis(10),
+                        };
+                        let time_manager = TimeManager::new(&time_control, pos);
+                        searcher.search_timed(pos, MAX_DEPTH, time_manager)
+                    }
+                    Limit::Nodes(nodes) => searcher.search_nodes(pos, nodes),
+                    Limit::Depth(depth) => searcher.search(pos, depth),
+                };
+                Ok((best_move, Some(score)))
+            }
+        }
+    }
+}
+
+// Time a UCI engine gets to answer go before it is given up on
+fn get_timeout(limit: Limit, own_clock: Duration) -> Duration {
+    match limit {
+        Limit::Clock { .. } => own_clock + TIMEOUT_MARGIN,
+        Limit::Nodes(_) | Limit::Depth(_) => UNTIMED_TIMEOUT,
+    }
+}
+
+// Termination of a game lost because the engine failed to play a move, an
+// engine that used up the whole timeout has run out of time
+fn get_failure_termination(limit: Limit, own_clock: Duration, elapsed: Duration) -> &'static str {
+    if elapsed >= get_timeout(limit, own_clock) {
+        "time forfeit"
+    } else {
+        "rules infraction"
+    }
+}
+
+struct GameRecord {
+    index: usize,
+    white: String,
+    black: String,
+    game: Game,
+    // from the point of view of white: 1.0, 0.5 or 0.0
+    white_score: f64,
+    result: String,
+    termination: &'static str,
+    reason: String,
+}
+
+// Plays one game, players[0] has white. Returns the record, and the side
+// whose engine failed and has to be restarted if any.
+fn play_game(
+    config: &Config,
+    players: &mut [Player; 2],
+    opening: &(Position, Vec<Move>),
+    index: usize,
+) -> (GameRecord, Option<ChessPlayer>) {
+    let mut game = Game::new(opening.0);
+    // the opening stops at an invalid move, or before a move ending the game
+    for &m in opening.1.iter() {
+        if !game.make_move(m) {
+            break;
+        }
+        if game.get_status().is_over() {
+            game.undo_move();
+            break;
+        }
+    }
+    let start_ply = game.get_moves().len();
+
+    let mut clocks = match config.limit {
+        Limit::Clock { base, .. } => [base; 2],
+        _ => [Duration::default(); 2],
+    };
+    // scores of the moves played by the engines, from the point of view of
+    // the mover
+    let mut scores: Vec<Option<i32>> = Vec::new();
+    let mut failed = None;
+
+    let mut outcome: Option<(Option<ChessPlayer>, &'static str, String)> = None;
+    for (k, player) in players.iter_mut().enumerate() {
+        if let Err(e) = player.new_game() {
+            let side = [ChessPlayer::White, ChessPlayer::Black][k];
+            failed = Some(side);
+            outcome = Some((Some(side.get_opponent()), "rules infraction", e));
+            break;
+        }
+    }
+
+    while outcome.is_none() {
+        let status = game.get_status();
+        if status.is_over() {
+            outcome = Some((status.get_winner(), "normal", status.to_string()));
+            break;
+        }
+        if game.get_moves().len() - start_ply >= MAX_GAME_PLIES {
+            outcome = Some((None, "adjudication", "game too long".to_string()));
+            break;
+        }
+
+        let side = game.get_position().get_player_on_move();
+        let player = &mut players[side as usize];
+        let start = Instant::now();
+        let played = player.play(&game, config.limit, &clocks);
+        let elapsed = start.elapsed();
+        let (m, score) = match played {
+            Ok((Some(m), score)) if game.get_position().get_valid_moves().contains(&m) => {
+                (m, score)
+            }
+            Ok(_) => {
+                let reason = format!("{} made no valid move", player.get_name());
+                outcome = Some((Some(side.get_opponent()), "rules infraction", reason));
+                break;
+            }
+            Err(e) => {
+                failed = Some(side);
+                let termination =
+                    get_failure_termination(config.limit, clocks[side as usize], elapsed);
+                outcome = Some((Some(side.get_opponent()), termination, e));
+                break;
+            }
+        };
+        if let Limit::Clock { increment, .. } = config.limit {
+            let clock = &mut clocks[side as usize];
+            if elapsed > *clock {
+                let reason = format!("{} loses on time", player.get_name());
+                outcome = Some((Some(side.get_opponent()), "time forfeit", reason));
+                break;
+            }
+            *clock = *clock - elapsed + increment;
+        }
+        game.make_move(m);
+        scores.push(score);
+
+        // the mover resigns after resign_moves of its own bad scores
+        let own_scores: Vec<Option<i32>> = scores
+            .iter()
+            .rev()
+            .step_by(2)
+            .take(config.resign_moves)
+            .copied()
+            .collect();
+        if config.resign_moves > 0
+            && own_scores.len() == config.resign_moves
+            && own_scores
+                .iter()
+                .all(|s| s.is_some_and(|s| s <= -config.resign_score))
+        {
+            let reason = format!("{} resigns", players[side as usize].get_name());
+            outcome = Some((Some(side.get_opponent()), "adjudication", reason));
+            break;
+        }
+        let recent: Vec<Option<i32>> = scores
+            .iter()
+            .rev()
+            .take(2 * config.draw_moves)
+            .copied()
+            .collect();
+        if config.draw_moves > 0
+            && game.get_fullmove_number() > config.draw_move_number
+            && recent.len() == 2 * config.draw_moves
+            && recent
+                .iter()
+                .all(|s| s.is_some_and(|s| s.abs() <= config.draw_score))
+        {
+            outcome = Some((None, "adjudication", "draw by adjudication".to_string()));
+        }
+    }
+
+    let (winner, termination, reason) = outcome.unwrap();
+    let (white_score, result) = match winner {
+        Some(ChessPlayer::White) => (1.0, "1-0"),
+        Some(ChessPlayer::Black) => (0.0, "0-1"),
+        None => (0.5, "1/2-1/2"),
+    };
+    let record = GameRecord {
+        index,
+        white: players[0].get_name().to_string(),
+        black: players[1].get_name().to_string(),
+        game,
+        white_score,
+        result: result.to_string(),
+        termination,
+        reason,
+    };
+    (record, failed)
+}
+
+// Elo difference of a score fraction
+fn get_elo(score: f64) -> f64 {
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+#[derive(Debug, Default)]
+struct Statistics {
+    // from the point of view of the first engine
+    wins: usize,
+    draws: usize,
+    losses: usize,
+}
+
+impl Statistics {
+    fn get_games(&self) -> usize {
+        self.wins + self.draws + self.losses
+    }
+
+    fn get_score(&self) -> f64 {
+        (self.wins as f64 + self.draws as f64 / 2.0) / self.get_games().max(1) as f64
+    }
+
+    // variance of the score of one game
+    fn get_variance(&self) -> f64 {
+        let n = self.get_games().max(1) as f64;
+        let s = self.get_score();
+        (self.wins as f64 * (1.0 - s).powi(2)
+            + self.draws as f64 * (0.5 - s).powi(2)
+            + self.losses as f64 * s.powi(2))
+            / n
+    }
+
+    // Elo difference and the half width of its 95% confidence interval, None
+    // while one side has all the points
+    fn get_elo(&self) -> Option<(f64, f64)> {
+        let s = self.get_score();
+        if self.get_games() == 0 || s <= 0.0 || s >= 1.0 {
+            return None;
+        }
+        let error = 1.96 * (self.get_variance() / self.get_games() as f64).sqrt();
+        let low = get_elo((s - error).max(1e-6));
+        let high = get_elo((s + error).min(1.0 - 1e-6));
+        Some((get_elo(s), (high - low) / 2.0))
+    }
+
+    // Log likelihood ratio of elo1 against elo0, the per game score taken
+    // as normally distributed
+    fn get_llr(&self, elo0: f64, elo1: f64) -> f64 {
+        let variance = self.get_variance();
+        if variance <= 0.0 {
+            return 0.0;
+        }
+        let expected = |elo: f64| 1.0 / (1.0 + 10f64.powf(-elo / 400.0));
+        let (s0, s1) = (expected(elo0), expected(elo1));
+        (s1 - s0) * (2.0 * self.get_score() - s0 - s1) * self.get_games() as f64 / (2.0 * variance)
+    }
+}
+
+// Usage: match --engine <path|builtin[:option=value,...]> --engine <...>
+//              [--games N] [--concurrency N] [--openings FILE.epd|FILE.pgn]
+//              [--tc BASE+INC | --nodes N | --depth N] [--pgn FILE]
+//              [--sprt ELO0 ELO1] [--alpha X] [--beta X]
+//              [--resign SCORE MOVES] [--draw SCORE MOVES MOVE_NUMBER]
+// Every opening is played twice with colours swapped. With --sprt the match
+// stops as soon as the test decides.
+fn main() {
+    let config = match parse_args() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            eprintln!(
+                "usage: match --engine <path|builtin[:option=value,...]> --engine <...> \
+                 [--games N] [--concurrency N] [--openings FILE] [--tc BASE+INC | --nodes N | \
+                 --depth N] [--pgn FILE] [--sprt ELO0 ELO1] [--alpha X] [--beta X] \
+                 [--resign SCORE MOVES] [--draw SCORE MOVES MOVE_NUMBER]"
+            );
+            process::exit(1);
+        }
+    };
+    let openings = match &config.openings {
+        Some(path) => load_openings(path).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(1);
+        }),
+        None => vec![(Position::default(), Vec::new())],
+    };
+    let mut pgn_writer = config.pgn.as_ref().map(|path| match File::create(path) {
+        Ok(file) => BufWriter::new(file),
+        Err(e) => {
+            eprintln!("{}: {}", path, e);
+            process::exit(1);
+        }
+    });
+
+    let next_game = AtomicUsize::new(0);
+    let finished = AtomicBool::new(false);
+    let (sender, receiver) = mpsc::channel();
+    thread::scope(|scope| {
+        for _ in 0..config.concurrency {
+            let sender = sender.clone();
+            let (config, openings, next_game, finished) =
+                (&config, &openings, &next_game, &finished);
+            scope.spawn(move || {
+                // players[k] is the k-th engine of the command line
+                let mut players = Vec::new();
+                for spec in config.engines.iter() {
+                    match Player::start(spec) {
+                        Ok(player) => players.push(player),
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            finished.store(true, Ordering::Relaxed);
+                            return;
+                        }
+                    }
+                }
+                let mut players: [Player; 2] = match players.try_into() {
+                    Ok(players) => players,
+                    Err(_) => return,
+                };
+                while !finished.load(Ordering::Relaxed) {
+                    let index = next_game.fetch_add(1, Ordering::Relaxed);
+                    if index >= config.games {
+                        break;
+                    }
+                    // the same opening twice, the first engine white in the first
+                    let opening = &openings[(index / 2) % openings.len()];
+                    let swapped = index % 2 == 1;
+                    if swapped {
+                        players.swap(0, 1);
+                    }
+                    let (record, failed) = play_game(config, &mut players, opening, index);
+                    if swapped {
+                        players.swap(0, 1);
+                    }
+                    // a failed engine is started again for the next game
+                    if let Some(side) = failed {
+                        let k = side as usize ^ swapped as usize;
+                        match Player::start(&config.engines[k]) {
+                            Ok(player) => players[k] = player,
+                            Err(e) => {
+                                eprintln!("{}", e);
+                                finished.store(true, Ordering::Relaxed);
+                            }
+                        }
+                    }
+                    sender.send((record, swapped)).unwrap();
+                }
+            });
+        }
+        drop(sender);
+
+        let mut statistics = Statistics::default();
+        for (record, swapped) in receiver {
+            let score = if swapped {
+                1.0 - record.white_score
+            } else {
+                record.white_score
+            };
+            match score {
+                s if s > 0.75 => statistics.wins += 1,
+                s if s < 0.25 => statistics.losses += 1,
+                _ => statistics.draws += 1,
+            }
+            println!(
+                "Game {} ({} vs {}): {} {{{}}}",
+                record.index + 1,
+                record.white,
+                record.black,
+                record.result,
+                record.reason
+            );
+            if let Some(writer) = pgn_writer.as_mut() {
+                let headers: Vec<(String, String)> = vec![
+                    ("Event".to_string(), "match".to_string()),
+                    ("Site".to_string(), "?".to_string()),
+                    ("Date".to_string(), "????.??.??".to_string()),
+                    ("Round".to_string(), (record.index + 1).to_string()),
+                    ("White".to_string(), record.white.clone()),
+                    ("Black".to_string(), record.black.clone()),
+                    ("Termination".to_string(), record.termination.to_string()),
+                ];
+                let pgn = write_pgn(&headers, &record.game, &record.result);
+                writer.write_all(pgn.as_bytes()).unwrap();
+                writer.flush().unwrap();
+            }
+
+            println!(
+                "Score: {} - {} - {} [{:.3}] {}",
+                statistics.wins,
+                statistics.losses,
+                statistics.draws,
+                statistics.get_score(),
+                statistics.get_games()
+            );
+            match statistics.get_elo() {
+                Some((elo, error)) => println!("Elo difference: {:.1} +/- {:.1}", elo, error),
+                None => println!("Elo difference: unknown"),
+            }
+            if let Some((elo0, elo1, alpha, beta)) = config.sprt {
+                let llr = statistics.get_llr(elo0, elo1);
+                let lower = (beta / (1.0 - alpha)).ln();
+                let upper = ((1.0 - beta) / alpha).ln();
+                let verdict = if llr >= upper {
+                    "H1 accepted"
+                } else if llr <= lower {
+                    "H0 accepted"
+                } else {
+                    "continue"
+                };
+                println!(
+                    "SPRT [{}, {}]: LLR {:.2} ({:.2}, {:.2}) {}",
+                    elo0, elo1, llr, lower, upper, verdict
+                );
+                if llr >= upper || llr <= lower {
+                    finished.store(true, Ordering::Relaxed);
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_statistics(wins: usize, draws: usize, losses: usize) -> Statistics {
+        Statistics {
+            wins,
+            draws,
+            losses,
+        }
+    }
+
+    fn assert_close(value: f64, expected: f64) {
+        assert!((value - expected).abs() < 1e-6, "{} != {}", value, expected);
+    }
+
+    #[test]
+    fn elo_and_error_of_known_results() {
+        let statistics = get_statistics(60, 20, 20);
+        assert_close(statistics.get_variance(), 0.16);
+        let (elo, error) = statistics.get_elo().unwrap();
+        assert_close(elo, 147.190714);
+        assert_close(error, 66.014639);
+
+        let (elo, error) = get_statistics(30, 40, 30).get_elo().unwrap();
+        assert_close(elo, 0.0);
+        assert_close(error, 53.158972);
+
+        let statistics = get_statistics(100, 300, 90);
+        assert_close(statistics.get_variance(), 0.096835);
+        let (elo, error) = statistics.get_elo().unwrap();
+        assert_close(elo, 7.091507);
+        assert_close(error, 19.173427);
+
+        assert!(get_statistics(5, 0, 0).get_elo().is_none());
+        assert!(get_statistics(0, 0, 0).get_elo().is_none());
+    }
+
+    #[test]
+    fn llr_of_known_results() {
+        assert_close(get_statistics(60, 20, 20).get_llr(0.0, 10.0), 1.733713);
+        assert_close(get_statistics(30, 40, 30).get_llr(0.0, 10.0), -0.068997);
+        assert_close(get_statistics(100, 300, 90).get_llr(0.0, 10.0), 0.219169);
+        // all draws, no spread to judge by
+        assert_close(get_statistics(0, 10, 0).get_llr(0.0, 10.0), 0.0);
+    }
+
+    #[test]
+    fn engine_timing_out_forfeits_on_time() {
+        let clock = Limit::Clock {
+            base: Duration::from_secs(10),
+            increment: Duration::default(),
+        };
+        let own_clock = Duration::from_secs(2);
+        let timeout = own_clock + TIMEOUT_MARGIN;
+        assert_eq!(
+            get_failure_termination(clock, own_clock, timeout),
+            "time forfeit"
+        );
+        assert_eq!(
+            get_failure_termination(clock, own_clock, Duration::from_millis(100)),
+            "rules infraction"
+        );
+        let depth = Limit::Depth(10);
+        assert_eq!(
+            get_failure_termination(depth, own_clock, UNTIMED_TIMEOUT),
+            "time forfeit"
+        );
+        assert_eq!(
+            get_failure_termination(depth, own_clock, timeout),
+            "rules infraction"
+        );
+    }
+}
@@ -0,0 +1,69 @@
+use chess::chess::position::Position;
+use chess::chess::uci::{move_to_string, parse_move};
+use std::io::{self, BufRead};
+use std::thread;
+use std::time::Duration;
+
+// Minimal UCI engine for exercising UciEngine: it plays the capture winning
+// the most material, otherwise the first valid move. The Delay option makes
+// it wait before answering go, to test timeouts.
+fn main() {
+    let stdin = io::stdin();
+    let mut pos = Position::default();
+    let mut delay = Duration::default();
+    for line in stdin.lock().lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.first() {
+            Some(&"uci") => {
+                println!("id name stub");
+                println!("id author Aj0SK");
+                println!("option name Delay type spin default 0 min 0 max 60000");
+                println!("uciok");
+            }
+            Some(&"isready") => println!("readyok"),
+            Some(&"setoption") => {
+                if let (Some(&"Delay"), Some(value)) = (tokens.get(2), tokens.get(4)) {
+                    delay = Duration::from_millis(value.parse().unwrap_or(0));
+                }
+            }
+            Some(&"position") => {
+                let moves_index = tokens.iter().position(|&t| t == "moves");
+                pos = match tokens.get(1) {
+                    Some(&"fen") => {
+                        let fen = tokens[2..moves_index.unwrap_or(tokens.len())].join(" ");
+                        Position::from_fen(&fen).unwrap_or_default()
+                    }
+                    _ => Position::default(),
+                };
+                for s in moves_index.map_or(&[][..], |ind| &tokens[ind + 1..]) {
+                    if let Some(m) = parse_move(&pos, s) {
+                        pos.make_move(m.0, m.1, m.2, m.3);
+                    }
+                }
+            }
+            Some(&"go") => {
+                thread::sleep(delay);
+                let moves = pos.get_valid_moves();
+                let best_move = moves
+                    .iter()
+                    .filter(|&&m| pos.is_capture(m))
+                    .max_by_key(|&&m| pos.see(m))
+                    .or_else(|| moves.first());
+                match best_move {
+                    Some(&m) => {
+                        let s = move_to_string(&pos, m);
+                        println!("info depth 1 score cp 0 nodes {} pv {}", moves.len(), s);
+                        println!("bestmove {}", s);
+                    }
+                    None => println!("bestmove 0000"),
+                }
+            }
+            Some(&"quit") => break,
+            _ => {}
+        }
+    }
+}
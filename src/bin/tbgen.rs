@@ -0,0 +1,75 @@
+use chess::chess::bitbase::generate_kpk;
+use chess::chess::retrograde::DtmTable;
+use chess::chess::syzygy::SyzygyTable;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process;
+
+fn print_statistics(signature: &str, statistics: (usize, usize, usize, u32), distance: &str) {
+    let (wins, losses, draws, longest) = statistics;
+    println!(
+        "{}: {} wins, {} losses, {} draws, longest {} {} plies",
+        signature, wins, losses, draws, distance, longest
+    );
+}
+
+// Usage: tbgen [--syzygy] <output dir> <material>... | tbgen --kpk <path>
+// Writes one <material>.rdtm file per material, e.g. tbgen tables KRvK KQvKR.
+// With --syzygy writes <material>.rtbw and <material>.rtbz for a single white
+// piece against the king, e.g. tbgen --syzygy tables KQvK KPvK. With --kpk
+// writes the KPK bitbase embedded in the engine, src/chess/kpk.bin.
+fn main() {
+    let mut args: Vec<String> = env::args().collect();
+    if args.len() == 3 && args[1] == "--kpk" {
+        if let Err(e) = fs::write(&args[2], generate_kpk()) {
+            eprintln!("{}: {}", args[2], e);
+            process::exit(1);
+        }
+        return;
+    }
+    let syzygy = args.get(1).is_some_and(|arg| arg == "--syzygy");
+    if syzygy {
+        args.remove(1);
+    }
+    if args.len() < 3 {
+        eprintln!(
+            "usage: {} [--syzygy] <output dir> <material>... | {} --kpk <path>",
+            args[0], args[0]
+        );
+        process::exit(1);
+    }
+
+    let dir = Path::new(&args[1]);
+    for material in args[2..].iter() {
+        if syzygy {
+            let table = match SyzygyTable::generate(material) {
+                Ok(table) => table,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    process::exit(1);
+                }
+            };
+            if let Err(e) = table.write(dir) {
+                eprintln!("{}: {}", dir.display(), e);
+                process::exit(1);
+            }
+            print_statistics(table.get_signature(), table.get_statistics(), "zeroing");
+            continue;
+        }
+
+        let table = match DtmTable::generate(material) {
+            Ok(table) => table,
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        };
+        let path = dir.join(format!("{}.rdtm", table.get_signature()));
+        if let Err(e) = table.write(&path) {
+            eprintln!("{}: {}", path.display(), e);
+            process::exit(1);
+        }
+        print_statistics(table.get_signature(), table.get_statistics(), "mate");
+    }
+}
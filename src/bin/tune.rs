@@ -0,0 +1,76 @@
+use chess::chess::tuner::{load_dataset, write_parameters_source, Tuner};
+use std::env;
+use std::fs;
+use std::process;
+
+const DEFAULT_OUTPUT: &str = "src/chess/eval_params.rs";
+
+// Usage: tune <dataset> [--epochs N] [--learning-rate X] [--output PATH]
+// Tunes the evaluation on a datagen dataset and writes the parameters as
+// Rust source, by default over the ones the engine is built with.
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let mut dataset = None;
+    let mut epochs = 1000;
+    let mut learning_rate = 1.0;
+    let mut output = DEFAULT_OUTPUT.to_string();
+    let mut k = 1;
+    while k < args.len() {
+        let value = args.get(k + 1);
+        let parsed = match (args[k].as_str(), value) {
+            ("--epochs", Some(v)) => v.parse().map(|v| epochs = v).is_ok(),
+            ("--learning-rate", Some(v)) => v.parse().map(|v| learning_rate = v).is_ok(),
+            ("--output", Some(v)) => {
+                output = v.clone();
+                true
+            }
+            (arg, _) if !arg.starts_with("--") => {
+                dataset = Some(arg.to_string());
+                k += 1;
+                continue;
+            }
+            _ => false,
+        };
+        if !parsed {
+            eprintln!("invalid option {}", args[k]);
+            process::exit(1);
+        }
+        k += 2;
+    }
+    let dataset = match dataset {
+        Some(dataset) => dataset,
+        None => {
+            eprintln!("usage: tune <dataset> [--epochs N] [--learning-rate X] [--output PATH]");
+            process::exit(1);
+        }
+    };
+
+    let text = match fs::read_to_string(&dataset) {
+        Ok(text) => text,
+        Err(e) => {
+            eprintln!("{}: {}", dataset, e);
+            process::exit(1);
+        }
+    };
+    let (samples, skipped) = load_dataset(&text);
+    println!("{} positions, {} skipped", samples.len(), skipped);
+    if samples.is_empty() {
+        process::exit(1);
+    }
+
+    let mut tuner = Tuner::new(samples);
+    let k_value = tuner.fit_k();
+    println!("K = {:.4}, error {:.6}", k_value, tuner.get_error(k_value));
+    for epoch in 1..=epochs {
+        let error = tuner.step(learning_rate);
+        if epoch % 50 == 0 || epoch == epochs {
+            println!("epoch {}: error {:.6}", epoch, error);
+        }
+    }
+
+    if let Err(e) = fs::write(&output, write_parameters_source(&tuner.get_parameters())) {
+        eprintln!("{}: {}", output, e);
+        process::exit(1);
+    }
+    println!("parameters written to {}", output);
+}
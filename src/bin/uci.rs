@@ -0,0 +1,18 @@
+use chess::chess::uci::Uci;
+use chess::chess::xboard::Xboard;
+use std::io::{self, BufRead, Cursor, Read};
+
+// The protocol is chosen by the first command, xboard starts with "xboard"
+fn main() {
+    let stdin = io::stdin();
+    let mut input = stdin.lock();
+    let mut first_line = String::new();
+    if input.read_line(&mut first_line).is_err() {
+        return;
+    }
+    if first_line.trim() == "xboard" {
+        Xboard::new().run(input);
+    } else {
+        Uci::new().run(Cursor::new(first_line).chain(input));
+    }
+}
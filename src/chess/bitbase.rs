@@ -0,0 +1,290 @@
+use super::chess_piece::ChessPiece;
+use super::chess_player::ChessPlayer;
+use super::position::Position;
+
+// side on move x strong king x weak king x pawn on files a-d, ranks 2-7
+const KPK_SIZE: usize = 2 * 64 * 64 * 24;
+
+// successors that are not KPK positions any more
+const DRAW_SUCCESSOR: u32 = u32::MAX;
+const WIN_SUCCESSOR: u32 = u32::MAX - 1;
+
+// one bit per KPK position, set when the pawn side wins, written by
+// tbgen --kpk
+static KPK_BITBASE: &[u8; KPK_SIZE / 8] = include_bytes!("kpk.bin");
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum KpkResult {
+    Invalid,
+    Unknown,
+    Draw,
+    Win,
+}
+
+// Strong side is white and its pawn is on files a-d
+fn get_index(strong_on_move: bool, strong_king: usize, weak_king: usize, pawn: usize) -> usize {
+    let side = if strong_on_move { 0 } else { 1 };
+    let pawn_index = (pawn / 8 - 1) * 4 + pawn % 8;
+    ((side * 64 + strong_king) * 64 + weak_king) * 24 + pawn_index
+}
+
+fn get_squares(index: usize) -> (bool, usize, usize, usize) {
+    let pawn_index = index % 24;
+    let weak_king = index / 24 % 64;
+    let strong_king = index / (24 * 64) % 64;
+    let strong_on_move = index / (24 * 64 * 64) == 0;
+    let pawn = (pawn_index / 4 + 1) * 8 + pawn_index % 4;
+    (strong_on_move, strong_king, weak_king, pawn)
+}
+
+fn get_square(pos: &Position, piece: ChessPiece, player: ChessPlayer) -> usize {
+    pos.get_piece_type_by_player(piece, player).trailing_zeros()
+}
+
+// Weak side is on move right after the pawn became a queen
+fn classify_promotion(pos: &Position) -> u32 {
+    let queen = get_square(pos, ChessPiece::Queen, ChessPlayer::White);
+    let moves = pos.get_valid_moves();
+    if moves.is_empty() {
+        return if pos.is_in_check(ChessPlayer::Black) {
+            WIN_SUCCESSOR
+        } else {
+            DRAW_SUCCESSOR
+        };
+    }
+    if moves.iter().any(|m| m.2 * 8 + m.3 == queen) {
+        DRAW_SUCCESSOR
+    } else {
+        WIN_SUCCESSOR
+    }
+}
+
+fn get_successor(pos: &Position) -> u32 {
+    if pos.get_piece_mask(ChessPiece::Queen).count_ones() > 0 {
+        return classify_promotion(pos);
+    }
+    if pos.get_piece_mask(ChessPiece::Pawn).count_ones() == 0 {
+        return DRAW_SUCCESSOR;
+    }
+    get_index(
+        pos.get_player_on_move() == ChessPlayer::White,
+        get_square(pos, ChessPiece::King, ChessPlayer::White),
+        get_square(pos, ChessPiece::King, ChessPlayer::Black),
+        get_square(pos, ChessPiece::Pawn, ChessPlayer::White),
+    ) as u32
+}
+
+// Retrograde analysis over all KPK positions. Terminal positions are
+// classified first, the rest is resolved by iterating until nothing changes:
+// the strong side needs one winning move, the weak side loses when every
+// move loses. Whatever stays unresolved is a draw.
+pub fn generate_kpk() -> Vec<u8> {
+    let mut results = vec![KpkResult::Invalid; KPK_SIZE];
+    let mut successors: Vec<u32> = Vec::new();
+    let mut offsets = vec![0; KPK_SIZE + 1];
+
+    for (index, result) in results.iter_mut().enumerate() {
+        offsets[index] = successors.len();
+        let (strong_on_move, strong_king, weak_king, pawn) = get_squares(index);
+        if strong_king == weak_king || strong_king == pawn || weak_king == pawn {
+            continue;
+        }
+
+        let on_move = if strong_on_move {
+            ChessPlayer::White
+        } else {
+            ChessPlayer::Black
+        };
+        let mut pos = Position::empty(on_move);
+        pos.put_piece(
+            ChessPlayer::White,
+            ChessPiece::King,
+            strong_king / 8,
+            strong_king % 8,
+        );
+        pos.put_piece(
+            ChessPlayer::Black,
+            ChessPiece::King,
+            weak_king / 8,
+            weak_king % 8,
+        );
+        pos.put_piece(ChessPlayer::White, ChessPiece::Pawn, pawn / 8, pawn % 8);
+        if pos.is_in_check(on_move.get_opponent()) {
+            continue;
+        }
+
+        let moves = pos.get_valid_moves();
+        if moves.is_empty() {
+            *result = if !strong_on_move && pos.is_in_check(on_move) {
+                KpkResult::Win
+            } else {
+                KpkResult::Draw
+            };
+            continue;
+        }
+
+        *result = KpkResult::Unknown;
+        for &(i, j, k, l) in moves.iter() {
+            let mut next = pos;
+            next.make_move(i, j, k, l);
+            successors.push(get_successor(&next));
+        }
+    }
+    offsets[KPK_SIZE] = successors.len();
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for index in 0..KPK_SIZE {
+            if results[index] != KpkResult::Unknown {
+                continue;
+            }
+            let strong_on_move = index < KPK_SIZE / 2;
+            let mut all_win = true;
+            let mut all_draw = true;
+            let mut any_win = false;
+            let mut any_draw = false;
+            for &successor in successors[offsets[index]..offsets[index + 1]].iter() {
+                let result = match successor {
+                    DRAW_SUCCESSOR => KpkResult::Draw,
+                    WIN_SUCCESSOR => KpkResult::Win,
+                    _ => results[successor as usize],
+                };
+                any_win |= result == KpkResult::Win;
+                any_draw |= result == KpkResult::Draw;
+                all_win &= result == KpkResult::Win;
+                all_draw &= result == KpkResult::Draw;
+            }
+
+            let result = if strong_on_move {
+                if any_win {
+                    KpkResult::Win
+                } else if all_draw {
+                    KpkResult::Draw
+                } else {
+                    KpkResult::Unknown
+                }
+            } else if any_draw {
+                KpkResult::Draw
+            } else if all_win {
+                KpkResult::Win
+            } else {
+                KpkResult::Unknown
+            };
+            if result != KpkResult::Unknown {
+                results[index] = result;
+                changed = true;
+            }
+        }
+    }
+
+    let mut bitbase = vec![0; KPK_SIZE / 8];
+    for (index, &result) in results.iter().enumerate() {
+        if result == KpkResult::Win {
+            bitbase[index / 8] |= 1 << (index % 8);
+        }
+    }
+    bitbase
+}
+
+// Squares as i * 8 + j. Returns true when the side with the pawn wins with
+// correct play, colors and files are normalized here.
+pub fn probe_kpk(
+    strong_player: ChessPlayer,
+    player_on_move: ChessPlayer,
+    strong_king: usize,
+    weak_king: usize,
+    pawn: usize,
+) -> bool {
+    let normalize = |square: usize| {
+        let i = match strong_player {
+            ChessPlayer::White => square / 8,
+            ChessPlayer::Black => 7 - square / 8,
+        };
+        let j = if pawn % 8 >= 4 {
+            7 - square % 8
+        } else {
+            square % 8
+        };
+        i * 8 + j
+    };
+    let index = get_index(
+        player_on_move == strong_player,
+        normalize(strong_king),
+        normalize(weak_king),
+        normalize(pawn),
+    );
+    KPK_BITBASE[index / 8] & (1 << (index % 8)) != 0
+}
+
+// Probes a position with a lone king against king and pawn
+pub fn probe_kpk_position(pos: &Position, strong_player: ChessPlayer) -> bool {
+    probe_kpk(
+        strong_player,
+        pos.get_player_on_move(),
+        get_square(pos, ChessPiece::King, strong_player),
+        get_square(pos, ChessPiece::King, strong_player.get_opponent()),
+        get_square(pos, ChessPiece::Pawn, strong_player),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_win(fen: &str, strong_player: ChessPlayer) -> bool {
+        probe_kpk_position(&Position::from_fen(fen).unwrap(), strong_player)
+    }
+
+    #[test]
+    fn king_on_sixth_in_front_of_pawn_wins() {
+        assert!(is_win(
+            "4k3/8/4K3/4P3/8/8/8/8 w - - 0 1",
+            ChessPlayer::White
+        ));
+        assert!(is_win(
+            "4k3/8/4K3/4P3/8/8/8/8 b - - 0 1",
+            ChessPlayer::White
+        ));
+        assert!(is_win(
+            "8/8/8/8/4p3/4k3/8/4K3 w - - 0 1",
+            ChessPlayer::Black
+        ));
+        assert!(is_win(
+            "8/8/8/8/3p4/3k4/8/3K4 b - - 0 1",
+            ChessPlayer::Black
+        ));
+    }
+
+    #[test]
+    fn pawn_outside_the_square_wins() {
+        assert!(is_win("8/8/8/8/8/7k/P7/K7 w - - 0 1", ChessPlayer::White));
+        assert!(is_win("k7/6p1/8/8/8/8/8/K7 b - - 0 1", ChessPlayer::Black));
+    }
+
+    #[test]
+    fn known_draws() {
+        // black keeps the opposition and ends up stalemated
+        assert!(!is_win(
+            "4k3/8/4P3/4K3/8/8/8/8 w - - 0 1",
+            ChessPlayer::White
+        ));
+        assert!(!is_win(
+            "4k3/4P3/4K3/8/8/8/8/8 b - - 0 1",
+            ChessPlayer::White
+        ));
+        // the defending king reaches the corner of a rook pawn
+        assert!(!is_win("k7/8/8/8/8/8/P7/K7 w - - 0 1", ChessPlayer::White));
+        assert!(!is_win("8/8/8/8/7k/7p/8/7K w - - 0 1", ChessPlayer::Black));
+        // the pawn is lost
+        assert!(!is_win(
+            "8/8/8/8/8/2k5/1P6/7K b - - 0 1",
+            ChessPlayer::White
+        ));
+    }
+
+    #[test]
+    fn embedded_bitbase_is_up_to_date() {
+        assert!(generate_kpk() == KPK_BITBASE);
+    }
+}
@@ -10,6 +10,9 @@ impl Bitboard {
     pub fn new(val: u64) -> Self {
         Self { val }
     }
+    pub fn get_value(&self) -> u64 {
+        self.val
+    }
     // is 1?
     pub fn is_set(&self, i: usize, j: usize) -> bool {
         self.val & (1 << (i * 8 + j)) != 0
@@ -77,6 +80,7 @@ impl_op_ex!(/ |a: Bitboard, b: Bitboard| -> Bitboard { Self::new(a.val / b.val)
 
 impl_op_ex!(&|a: Bitboard, b: Bitboard| -> Bitboard { Self::new(a.val & b.val) });
 impl_op_ex!(| |a: Bitboard, b: Bitboard| -> Bitboard { Self::new(a.val | b.val) });
+impl_op_ex!(^ |a: Bitboard, b: Bitboard| -> Bitboard { Self::new(a.val ^ b.val) });
 impl_op_ex!(!|a: Bitboard| -> Bitboard { Self::new(!a.val) });
 
 impl_op_ex!(<< |x: Bitboard, shift: usize| -> Bitboard { Self::new(x.val << shift) });
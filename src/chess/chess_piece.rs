@@ -10,6 +10,20 @@ pub enum ChessPiece {
     King = 5,
 }
 
+impl ChessPiece {
+    // Material value in centipawns, king is valued high enough to never be traded
+    pub fn get_value(&self) -> i32 {
+        match self {
+            ChessPiece::Pawn => 100,
+            ChessPiece::Rook => 500,
+            ChessPiece::Knight => 320,
+            ChessPiece::Bishop => 330,
+            ChessPiece::Queen => 900,
+            ChessPiece::King => 20000,
+        }
+    }
+}
+
 impl fmt::Display for ChessPiece {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{:?}", self)
@@ -0,0 +1,267 @@
+use super::bitbase::probe_kpk_position;
+use super::chess_piece::ChessPiece;
+use super::chess_player::ChessPlayer;
+use super::evaluation::get_material;
+use super::position::Position;
+
+// Score of a won endgame where no mate is in sight yet, stays far below mate
+// and tablebase scores
+pub const KNOWN_WIN: i32 = 10000;
+
+// Scale factors are applied as score * factor / SCALE_NORMAL
+pub const SCALE_NORMAL: i32 = 64;
+const SCALE_OPPOSITE_BISHOPS: i32 = 32;
+const SCALE_OPPOSITE_BISHOPS_FEW_PAWNS: i32 = 16;
+
+// Scores the endgame from the point of view of the strong side
+type EndgameEvaluator = fn(&Position, ChessPlayer) -> i32;
+
+fn get_square(pos: &Position, piece: ChessPiece, player: ChessPlayer) -> usize {
+    pos.get_piece_type_by_player(piece, player).trailing_zeros()
+}
+
+fn get_distance(a: usize, b: usize) -> i32 {
+    let di = (a / 8) as i32 - (b / 8) as i32;
+    let dj = (a % 8) as i32 - (b % 8) as i32;
+    std::cmp::max(di.abs(), dj.abs())
+}
+
+// Rank counted from the back rank of player
+fn get_relative_rank(square: usize, player: ChessPlayer) -> i32 {
+    match player {
+        ChessPlayer::White => (square / 8) as i32,
+        ChessPlayer::Black => 7 - (square / 8) as i32,
+    }
+}
+
+// Higher the closer the square is to the edge
+fn push_to_edge(square: usize) -> i32 {
+    let i = (square / 8) as i32;
+    let j = (square % 8) as i32;
+    let di = std::cmp::max(3 - i, i - 4);
+    let dj = std::cmp::max(3 - j, j - 4);
+    15 * (di + dj)
+}
+
+// Higher the closer the two squares are
+fn push_close(a: usize, b: usize) -> i32 {
+    140 - 20 * get_distance(a, b)
+}
+
+// Lone king against queen or rook: drive the king to the edge and bring the
+// strong king closer
+fn evaluate_kxk(pos: &Position, strong: ChessPlayer) -> i32 {
+    let strong_king = get_square(pos, ChessPiece::King, strong);
+    let weak_king = get_square(pos, ChessPiece::King, strong.get_opponent());
+    KNOWN_WIN
+        + get_material(pos, strong)
+        + push_to_edge(weak_king)
+        + push_close(strong_king, weak_king)
+}
+
+// Mate is only possible in a corner of the bishop's color
+fn evaluate_kbnk(pos: &Position, strong: ChessPlayer) -> i32 {
+    let strong_king = get_square(pos, ChessPiece::King, strong);
+    let weak_king = get_square(pos, ChessPiece::King, strong.get_opponent());
+    let bishop = get_square(pos, ChessPiece::Bishop, strong);
+    let corners: [usize; 2] = if (bishop / 8 + bishop % 8).is_multiple_of(2) {
+        [0, 63]
+    } else {
+        [7, 56]
+    };
+    let corner_distance = corners
+        .iter()
+        .map(|&corner| {
+            ((weak_king / 8) as i32 - (corner / 8) as i32).abs()
+                + ((weak_king % 8) as i32 - (corner % 8) as i32).abs()
+        })
+        .min()
+        .unwrap();
+    KNOWN_WIN
+        + get_material(pos, strong)
+        + 20 * (14 - corner_distance)
+        + push_close(strong_king, weak_king)
+}
+
+// Exact result from the bitbase, won positions prefer an advanced pawn
+fn evaluate_kpk(pos: &Position, strong: ChessPlayer) -> i32 {
+    if !probe_kpk_position(pos, strong) {
+        return 0;
+    }
+    let pawn = get_square(pos, ChessPiece::Pawn, strong);
+    KNOWN_WIN + ChessPiece::Pawn.get_value() + 10 * get_relative_rank(pawn, strong)
+}
+
+// Rook against pawn, usually won unless the pawn is far advanced and
+// supported by its king while the strong king is away
+fn evaluate_krkp(pos: &Position, strong: ChessPlayer) -> i32 {
+    let weak = strong.get_opponent();
+    // squares seen from the strong side, the pawn runs towards rank 0
+    let relative = |square: usize| match strong {
+        ChessPlayer::White => square,
+        ChessPlayer::Black => (7 - square / 8) * 8 + square % 8,
+    };
+    let strong_king = relative(get_square(pos, ChessPiece::King, strong));
+    let weak_king = relative(get_square(pos, ChessPiece::King, weak));
+    let rook = relative(get_square(pos, ChessPiece::Rook, strong));
+    let pawn = relative(get_square(pos, ChessPiece::Pawn, weak));
+    let queening = pawn % 8;
+    let strong_on_move = pos.get_player_on_move() == strong;
+    let rook_value = ChessPiece::Rook.get_value();
+
+    if strong_king % 8 == pawn % 8 && strong_king < pawn {
+        // strong king blocks the pawn
+        rook_value - get_distance(strong_king, pawn)
+    } else if get_distance(weak_king, pawn) >= 3 + if strong_on_move { 0 } else { 1 }
+        && get_distance(weak_king, rook) >= 3
+    {
+        // pawn is left alone
+        rook_value - get_distance(strong_king, pawn)
+    } else if weak_king / 8 <= 2
+        && get_distance(weak_king, queening) == 1
+        && strong_king / 8 >= 3
+        && get_distance(strong_king, pawn) > 2 + if strong_on_move { 1 } else { 0 }
+    {
+        // pawn is about to promote with its king next to it
+        80 - 8 * get_distance(strong_king, pawn)
+    } else {
+        200 - 8
+            * (get_distance(strong_king, pawn - 8)
+                - get_distance(weak_king, pawn - 8)
+                - get_distance(pawn, queening))
+    }
+}
+
+fn get_evaluator(pos: &Position) -> Option<(EndgameEvaluator, ChessPlayer)> {
+    for &strong in [ChessPlayer::White, ChessPlayer::Black].iter() {
+        let signature = format!(
+            "{}v{}",
+            pos.get_material_signature(strong),
+            pos.get_material_signature(strong.get_opponent())
+        );
+        let evaluator: EndgameEvaluator = match signature.as_str() {
+            "KQvK" | "KRvK" => evaluate_kxk,
+            "KBNvK" => evaluate_kbnk,
+            "KPvK" => evaluate_kpk,
+            "KRvKP" => evaluate_krkp,
+            _ => continue,
+        };
+        return Some((evaluator, strong));
+    }
+    None
+}
+
+// Score of a known endgame from the point of view of the player on move,
+// None when the material has no specialized evaluator
+pub fn probe_endgame(pos: &Position) -> Option<i32> {
+    let (evaluator, strong) = get_evaluator(pos)?;
+    let score = evaluator(pos, strong);
+    if pos.get_player_on_move() == strong {
+        Some(score)
+    } else {
+        Some(-score)
+    }
+}
+
+// Factor by which the regular evaluation is scaled towards a draw
+pub fn get_scale_factor(pos: &Position) -> i32 {
+    let white_bishops = pos.get_piece_type_by_player(ChessPiece::Bishop, ChessPlayer::White);
+    let black_bishops = pos.get_piece_type_by_player(ChessPiece::Bishop, ChessPlayer::Black);
+    let others = pos.get_piece_mask(ChessPiece::Rook)
+        | pos.get_piece_mask(ChessPiece::Knight)
+        | pos.get_piece_mask(ChessPiece::Queen);
+    if white_bishops.count_ones() != 1
+        || black_bishops.count_ones() != 1
+        || others.count_ones() != 0
+    {
+        return SCALE_NORMAL;
+    }
+
+    let white_bishop = white_bishops.trailing_zeros();
+    let black_bishop = black_bishops.trailing_zeros();
+    if (white_bishop / 8 + white_bishop % 8) % 2 == (black_bishop / 8 + black_bishop % 8) % 2 {
+        return SCALE_NORMAL;
+    }
+
+    // opposite colored bishops, an extra pawn or two rarely wins
+    let white_pawns = pos.get_piece_type_by_player(ChessPiece::Pawn, ChessPlayer::White);
+    let black_pawns = pos.get_piece_type_by_player(ChessPiece::Pawn, ChessPlayer::Black);
+    let difference = white_pawns.count_ones() as i32 - black_pawns.count_ones() as i32;
+    if difference.abs() <= 1 {
+        SCALE_OPPOSITE_BISHOPS_FEW_PAWNS
+    } else {
+        SCALE_OPPOSITE_BISHOPS
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chess::evaluation::evaluate;
+
+    fn probe(fen: &str) -> i32 {
+        probe_endgame(&Position::from_fen(fen).unwrap()).unwrap()
+    }
+
+    fn get_scale(fen: &str) -> i32 {
+        get_scale_factor(&Position::from_fen(fen).unwrap())
+    }
+
+    #[test]
+    fn kxk_drives_the_king_to_the_edge() {
+        let edge = probe("3k4/8/4K3/8/8/8/8/7Q w - - 0 1");
+        let center = probe("8/8/4K3/8/3k4/8/8/7Q w - - 0 1");
+        let far = probe("3k4/8/8/8/8/8/8/4K2Q w - - 0 1");
+        assert!(edge > center && center > KNOWN_WIN);
+        assert!(edge > far && far > KNOWN_WIN);
+        assert_eq!(probe("3k4/8/4K3/8/8/8/8/7Q b - - 0 1"), -edge);
+        // the same with colors reversed
+        assert_eq!(probe("7q/8/8/8/8/4k3/8/3K4 b - - 0 1"), edge);
+        assert!(probe("8/8/8/8/8/4k3/7r/3K4 b - - 0 1") > KNOWN_WIN);
+    }
+
+    #[test]
+    fn kbnk_drives_the_king_to_the_bishop_corner() {
+        // dark squared bishop mates on a1 and h8
+        let right = probe("8/8/8/8/8/2K5/8/k1B3N1 w - - 0 1");
+        let wrong = probe("8/8/8/8/8/5K2/8/2B3Nk w - - 0 1");
+        assert!(right > wrong && wrong > KNOWN_WIN);
+        // light squared bishop mates on h1 and a8
+        let right = probe("8/8/8/8/8/5K2/8/3B2Nk w - - 0 1");
+        let wrong = probe("8/8/8/8/8/2K5/8/k2B2N1 w - - 0 1");
+        assert!(right > wrong && wrong > KNOWN_WIN);
+    }
+
+    #[test]
+    fn krkp_depends_on_the_kings() {
+        // the strong king blocks the pawn
+        let blocked = probe("8/8/8/3k4/3p4/3K4/8/7R w - - 0 1");
+        // the pawn is about to promote next to its king
+        let running = probe("7K/8/8/8/8/8/2kp4/7R w - - 0 1");
+        assert!(blocked > 400);
+        assert!(running > 0 && running < 100);
+        assert_eq!(probe("7r/2KP4/8/8/8/8/8/7k b - - 0 1"), running);
+        assert!(probe("7K/8/8/8/8/8/2kp4/7R b - - 0 1") < 0);
+    }
+
+    #[test]
+    fn opposite_bishops_scale_towards_a_draw() {
+        // bishops on c1 and d5
+        let one_pawn = "4k3/8/8/3b4/8/8/3P4/2B1K3 w - - 0 1";
+        let two_pawns = "4k3/8/8/3b4/8/8/3PP3/2B1K3 w - - 0 1";
+        let same_color = "4k3/8/8/2b5/8/8/3PP3/2B1K3 w - - 0 1";
+        assert_eq!(get_scale(one_pawn), SCALE_OPPOSITE_BISHOPS_FEW_PAWNS);
+        assert_eq!(get_scale(two_pawns), SCALE_OPPOSITE_BISHOPS);
+        assert_eq!(get_scale(same_color), SCALE_NORMAL);
+        // another piece lifts the scaling
+        assert_eq!(
+            get_scale("4k3/8/8/3b4/8/8/3PP3/2B1K2R w - - 0 1"),
+            SCALE_NORMAL
+        );
+
+        let two_pawns = Position::from_fen(two_pawns).unwrap();
+        let same_color = Position::from_fen(same_color).unwrap();
+        assert!(evaluate(&two_pawns) > 0);
+        assert!(evaluate(&two_pawns) < evaluate(&same_color));
+    }
+}
@@ -0,0 +1,102 @@
+use super::chess_piece::ChessPiece;
+use super::chess_player::ChessPlayer;
+use super::endgame::{get_scale_factor, probe_endgame, SCALE_NORMAL};
+use super::eval_params::{PIECE_SQUARE_TABLES, PIECE_VALUES};
+use super::position::Position;
+
+const PIECES: [ChessPiece; 5] = [
+    ChessPiece::Pawn,
+    ChessPiece::Rook,
+    ChessPiece::Knight,
+    ChessPiece::Bishop,
+    ChessPiece::Queen,
+];
+
+const ALL_PIECES: [ChessPiece; 6] = [
+    ChessPiece::Pawn,
+    ChessPiece::Rook,
+    ChessPiece::Knight,
+    ChessPiece::Bishop,
+    ChessPiece::Queen,
+    ChessPiece::King,
+];
+
+// Parameter vector: piece values followed by the piece-square tables, in the
+// layout of eval_params
+pub const PIECE_SQUARE_OFFSET: usize = 5;
+pub const PARAMETER_COUNT: usize = PIECE_SQUARE_OFFSET + 6 * 64;
+
+// Square of a piece of player as seen from white
+fn get_relative_square(square: usize, player: ChessPlayer) -> usize {
+    match player {
+        ChessPlayer::White => square,
+        ChessPlayer::Black => square ^ 56,
+    }
+}
+
+// Returns material balance of player in centipawns
+pub fn get_material(pos: &Position, player: ChessPlayer) -> i32 {
+    PIECES
+        .iter()
+        .enumerate()
+        .map(|(k, &piece)| {
+            let count = pos.get_piece_type_by_player(piece, player).count_ones() as i32;
+            count * PIECE_VALUES[k]
+        })
+        .sum()
+}
+
+fn get_piece_squares(pos: &Position, player: ChessPlayer) -> i32 {
+    ALL_PIECES
+        .iter()
+        .enumerate()
+        .map(|(k, &piece)| {
+            pos.get_piece_type_by_player(piece, player)
+                .get_ones()
+                .iter()
+                .map(|&square| PIECE_SQUARE_TABLES[k][get_relative_square(square, player)])
+                .sum::<i32>()
+        })
+        .sum()
+}
+
+// Terms of the evaluation of player as (parameter index, count), the score of
+// player is the sum of parameter * count
+pub fn get_terms(pos: &Position, player: ChessPlayer) -> Vec<(usize, i32)> {
+    let mut terms = Vec::new();
+    for (k, &piece) in PIECES.iter().enumerate() {
+        let count = pos.get_piece_type_by_player(piece, player).count_ones() as i32;
+        if count > 0 {
+            terms.push((k, count));
+        }
+    }
+    for (k, &piece) in ALL_PIECES.iter().enumerate() {
+        for square in pos.get_piece_type_by_player(piece, player).get_ones() {
+            let index = PIECE_SQUARE_OFFSET + k * 64 + get_relative_square(square, player);
+            terms.push((index, 1));
+        }
+    }
+    terms
+}
+
+// Current parameters as a vector indexed like get_terms
+pub fn get_parameters() -> Vec<i32> {
+    let mut parameters = PIECE_VALUES.to_vec();
+    for table in PIECE_SQUARE_TABLES.iter() {
+        parameters.extend(table.iter());
+    }
+    parameters
+}
+
+// Static evaluation from the point of view of the player on move
+pub fn evaluate(pos: &Position) -> i32 {
+    if let Some(score) = probe_endgame(pos) {
+        return score;
+    }
+    let player = pos.get_player_on_move();
+    let opponent = player.get_opponent();
+    let score = get_material(pos, player) - get_material(pos, opponent)
+        + get_piece_squares(pos, player)
+        - get_piece_squares(pos, opponent);
+    score * get_scale_factor(pos) / SCALE_NORMAL
+}
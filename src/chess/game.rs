@@ -0,0 +1,229 @@
+use super::chess_piece::ChessPiece;
+use super::chess_player::ChessPlayer;
+use super::position::{Move, Position, PROMOTION_PIECES};
+use std::fmt;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GameStatus {
+    Ongoing,
+    // winner
+    Checkmate(ChessPlayer),
+    Stalemate,
+    ThreefoldRepetition,
+    FiftyMoveRule,
+    InsufficientMaterial,
+}
+
+impl GameStatus {
+    pub fn is_over(&self) -> bool {
+        *self != GameStatus::Ongoing
+    }
+
+    pub fn get_winner(&self) -> Option<ChessPlayer> {
+        match self {
+            GameStatus::Checkmate(winner) => Some(*winner),
+            _ => None,
+        }
+    }
+
+    // Result as written in PGN
+    pub fn get_result(&self) -> &'static str {
+        match self {
+            GameStatus::Ongoing => "*",
+            GameStatus::Checkmate(ChessPlayer::White) => "1-0",
+            GameStatus::Checkmate(ChessPlayer::Black) => "0-1",
+            _ => "1/2-1/2",
+        }
+    }
+}
+
+impl fmt::Display for GameStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GameStatus::Ongoing => write!(f, "Game in progress"),
+            GameStatus::Checkmate(winner) => write!(f, "{:?} wins by checkmate", winner),
+            GameStatus::Stalemate => write!(f, "Draw by stalemate"),
+            GameStatus::ThreefoldRepetition => write!(f, "Draw by threefold repetition"),
+            GameStatus::FiftyMoveRule => write!(f, "Draw by the fifty move rule"),
+            GameStatus::InsufficientMaterial => write!(f, "Draw by insufficient material"),
+        }
+    }
+}
+
+// Moves played from a start position together with what the rules need to
+// know about the history: repetitions and the halfmove clock
+#[derive(Debug, Clone)]
+pub struct Game {
+    // positions[0] is the start, positions[k] the one after k moves
+    positions: Vec<Position>,
+    moves: Vec<Move>,
+    // piece each move promotes to, a queen for moves that are not promotions
+    promotions: Vec<ChessPiece>,
+    keys: Vec<u64>,
+    halfmove_clocks: Vec<u32>,
+}
+
+impl Game {
+    pub fn new(start: Position) -> Self {
+        Self::from_position_with_clock(start, 0)
+    }
+
+    // Game from a position reached halfmove_clock plies after the last
+    // capture or pawn move
+    pub fn from_position_with_clock(start: Position, halfmove_clock: u32) -> Self {
+        Self {
+            positions: vec![start],
+            moves: Vec::new(),
+            promotions: Vec::new(),
+            keys: vec![start.get_zobrist_key()],
+            halfmove_clocks: vec![halfmove_clock],
+        }
+    }
+
+    // Game from a FEN keeping its halfmove clock, 0 when the field is missing
+    pub fn from_fen(fen: &str) -> Result<Self, String> {
+        let start = Position::from_fen(fen)?;
+        let halfmove_clock = match fen.split_whitespace().nth(4) {
+            Some(clock) => clock
+                .parse()
+                .map_err(|_| format!("invalid halfmove clock {}", clock))?,
+            None => 0,
+        };
+        Ok(Self::from_position_with_clock(start, halfmove_clock))
+    }
+
+    pub fn get_position(&self) -> &Position {
+        self.positions.last().unwrap()
+    }
+
+    pub fn get_start_position(&self) -> &Position {
+        &self.positions[0]
+    }
+
+    // Position after the first ply moves
+    pub fn get_position_at(&self, ply: usize) -> &Position {
+        &self.positions[ply]
+    }
+
+    pub fn get_moves(&self) -> &[Move] {
+        &self.moves
+    }
+
+    // Piece the move at ply promotes to, a queen for moves that are not
+    // promotions
+    pub fn get_promotion(&self, ply: usize) -> ChessPiece {
+        self.promotions[ply]
+    }
+
+    pub fn get_halfmove_clock(&self) -> u32 {
+        *self.halfmove_clocks.last().unwrap()
+    }
+
+    pub fn get_fullmove_number(&self) -> u32 {
+        let start_offset = match self.positions[0].get_player_on_move() {
+            ChessPlayer::White => 0,
+            ChessPlayer::Black => 1,
+        };
+        1 + (self.moves.len() + start_offset) as u32 / 2
+    }
+
+    pub fn get_fen(&self) -> String {
+        self.get_position()
+            .to_fen_with_clocks(self.get_halfmove_clock(), self.get_fullmove_number())
+    }
+
+    // Plays m if it is legal in the current position, promoting to a queen
+    pub fn make_move(&mut self, m: Move) -> bool {
+        self.make_move_promoting(m, ChessPiece::Queen)
+    }
+
+    // Plays m if it is legal in the current position, a pawn reaching the
+    // last rank becomes promotion
+    pub fn make_move_promoting(&mut self, m: Move, promotion: ChessPiece) -> bool {
+        let pos = *self.get_position();
+        if !pos.get_valid_moves().contains(&m) || !PROMOTION_PIECES.contains(&promotion) {
+            return false;
+        }
+        let promotion = if pos.is_promotion(m) {
+            promotion
+        } else {
+            ChessPiece::Queen
+        };
+        let resets_clock =
+            pos.is_capture(m) || pos.get_piece_on_position(m.0, m.1) == Some(ChessPiece::Pawn);
+        let mut next = pos;
+        next.make_move_promoting(m.0, m.1, m.2, m.3, promotion);
+
+        let clock = if resets_clock {
+            0
+        } else {
+            self.get_halfmove_clock() + 1
+        };
+        self.positions.push(next);
+        self.moves.push(m);
+        self.promotions.push(promotion);
+        self.keys.push(next.get_zobrist_key());
+        self.halfmove_clocks.push(clock);
+        true
+    }
+
+    pub fn undo_move(&mut self) -> Option<Move> {
+        let m = self.moves.pop()?;
+        self.promotions.pop();
+        self.positions.pop();
+        self.keys.pop();
+        self.halfmove_clocks.pop();
+        Some(m)
+    }
+
+    // How many times the current position occurred, counting this one. Only
+    // positions since the last capture or pawn move can repeat.
+    pub fn get_repetitions(&self) -> usize {
+        let key = *self.keys.last().unwrap();
+        let reversible = self.get_halfmove_clock() as usize;
+        self.keys
+            .iter()
+            .rev()
+            .take(reversible + 1)
+            .step_by(2)
+            .filter(|&&k| k == key)
+            .count()
+    }
+
+    pub fn get_status(&self) -> GameStatus {
+        let pos = self.get_position();
+        let player = pos.get_player_on_move();
+        if pos.get_valid_moves().is_empty() {
+            return if pos.is_in_check(player) {
+                GameStatus::Checkmate(player.get_opponent())
+            } else {
+                GameStatus::Stalemate
+            };
+        }
+        if is_insufficient_material(pos) {
+            return GameStatus::InsufficientMaterial;
+        }
+        if self.get_halfmove_clock() >= 100 {
+            return GameStatus::FiftyMoveRule;
+        }
+        if self.get_repetitions() >= 3 {
+            return GameStatus::ThreefoldRepetition;
+        }
+        GameStatus::Ongoing
+    }
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Self::new(Position::default())
+    }
+}
+
+// Neither side can mate: bare kings or a single minor piece left
+fn is_insufficient_material(pos: &Position) -> bool {
+    let heavy = pos.get_piece_mask(ChessPiece::Pawn)
+        | pos.get_piece_mask(ChessPiece::Rook)
+        | pos.get_piece_mask(ChessPiece::Queen);
+    let minors = pos.get_piece_mask(ChessPiece::Knight) | pos.get_piece_mask(ChessPiece::Bishop);
+    heavy.count_ones() == 0 && minors.count_ones() <= 1
+}
@@ -1,5 +1,25 @@
+pub mod bitbase;
 pub mod bitboard;
 pub mod chess_piece;
 pub mod chess_player;
+pub mod endgame;
+pub mod eval_params;
+pub mod evaluation;
+pub mod game;
 pub mod magic_bitboards;
+pub mod move_picker;
+pub mod nnue;
+pub mod pgn;
+pub mod polyglot;
 pub mod position;
+pub mod retrograde;
+pub mod search;
+pub mod syzygy;
+pub mod tablebase;
+pub mod time_manager;
+pub mod transposition_table;
+pub mod tuner;
+pub mod uci;
+pub mod uci_engine;
+pub mod xboard;
+pub mod zobrist;
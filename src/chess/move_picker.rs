@@ -0,0 +1,391 @@
+use super::chess_player::ChessPlayer;
+use super::position::{Move, Position};
+
+pub const MAX_PLY: usize = 128;
+
+const HISTORY_LIMIT: i32 = 1 << 20;
+
+fn from_square(m: Move) -> usize {
+    m.0 * 8 + m.1
+}
+
+fn to_square(m: Move) -> usize {
+    m.2 * 8 + m.3
+}
+
+// Heuristics collected during search that are used to order quiet moves
+pub struct MoveOrdering {
+    killers: [[Option<Move>; 2]; MAX_PLY],
+    // indexed by [player][from][to]
+    history: Box<[[[i32; 64]; 64]; 2]>,
+    // indexed by [from][to] of the move that is being answered
+    counter_moves: Box<[[Option<Move>; 64]; 64]>,
+}
+
+impl MoveOrdering {
+    pub fn new() -> Self {
+        Self {
+            killers: [[None; 2]; MAX_PLY],
+            history: Box::new([[[0; 64]; 64]; 2]),
+            counter_moves: Box::new([[None; 64]; 64]),
+        }
+    }
+
+    pub fn get_killers(&self, ply: usize) -> [Option<Move>; 2] {
+        if ply < MAX_PLY {
+            self.killers[ply]
+        } else {
+            [None; 2]
+        }
+    }
+
+    pub fn get_counter_move(&self, prev_move: Option<Move>) -> Option<Move> {
+        prev_move.and_then(|p| self.counter_moves[from_square(p)][to_square(p)])
+    }
+
+    pub fn get_history(&self, player: ChessPlayer, m: Move) -> i32 {
+        self.history[player as usize][from_square(m)][to_square(m)]
+    }
+
+    // Records quiet move m that caused a beta cutoff
+    pub fn update(
+        &mut self,
+        player: ChessPlayer,
+        m: Move,
+        prev_move: Option<Move>,
+        depth: u32,
+        ply: usize,
+    ) {
+        if ply < MAX_PLY && self.killers[ply][0] != Some(m) {
+            self.killers[ply][1] = self.killers[ply][0];
+            self.killers[ply][0] = Some(m);
+        }
+
+        if let Some(p) = prev_move {
+            self.counter_moves[from_square(p)][to_square(p)] = Some(m);
+        }
+
+        let table = &mut self.history[player as usize];
+        table[from_square(m)][to_square(m)] += (depth * depth) as i32;
+        if table[from_square(m)][to_square(m)] > HISTORY_LIMIT {
+            for row in table.iter_mut() {
+                for value in row.iter_mut() {
+                    *value /= 2;
+                }
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+}
+
+impl Default for MoveOrdering {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Stage {
+    TtMove,
+    GenerateCaptures,
+    GoodCaptures,
+    Killers,
+    CounterMove,
+    Quiets,
+    BadCaptures,
+    Done,
+}
+
+// Yields moves of a position one stage at a time: the transposition table
+// move, captures and promotions that don't lose material ordered by
+// MVV-LVA, killer moves, the counter move, quiet moves ordered by history
+// score and finally losing captures. Work for a stage is done only once the
+// stage is reached, so a cutoff on an early move skips sorting the rest.
+pub struct MovePicker {
+    pos: Position,
+    stage: Stage,
+    tactical_only: bool,
+    tt_move: Option<Move>,
+    killers: [Option<Move>; 2],
+    counter_move: Option<Move>,
+    // scored moves of the current stage, best one last
+    stage_moves: Vec<(i32, Move)>,
+    bad_captures: Vec<(i32, Move)>,
+    special_index: usize,
+}
+
+impl MovePicker {
+    pub fn new(
+        pos: &Position,
+        tt_move: Option<Move>,
+        killers: [Option<Move>; 2],
+        counter_move: Option<Move>,
+    ) -> Self {
+        Self {
+            pos: *pos,
+            stage: Stage::TtMove,
+            tactical_only: false,
+            tt_move,
+            killers,
+            counter_move,
+            stage_moves: Vec::new(),
+            bad_captures: Vec::new(),
+            special_index: 0,
+        }
+    }
+
+    // Picker for quiescence search, yields only captures and promotions that
+    // don't lose material according to static exchange evaluation
+    pub fn new_quiescence(pos: &Position, tt_move: Option<Move>) -> Self {
+        let mut picker = Self::new(pos, tt_move, [None; 2], None);
+        picker.tactical_only = true;
+        picker
+    }
+
+    fn is_tactical(&self, m: Move) -> bool {
+        self.pos.is_capture(m) || self.pos.is_promotion(m)
+    }
+
+    fn is_special(&self, m: Move) -> bool {
+        Some(m) == self.tt_move
+            || (!self.is_tactical(m)
+                && (Some(m) == self.killers[0]
+                    || Some(m) == self.killers[1]
+                    || Some(m) == self.counter_move))
+    }
+
+    // Most valuable victim, least valuable attacker
+    fn get_mvv_lva(&self, m: Move) -> i32 {
+        let victim = match self.pos.get_piece_on_position(m.2, m.3) {
+            Some(piece) => piece.get_value(),
+            None => 0,
+        };
+        let attacker = self.pos.get_piece_on_position(m.0, m.1).unwrap();
+        victim * 16 - attacker.get_value() / 100
+    }
+
+    fn generate_captures(&mut self) {
+        for m in self.pos.get_valid_tactical_moves() {
+            if Some(m) == self.tt_move {
+                continue;
+            }
+            let score = self.get_mvv_lva(m);
+            if self.pos.see(m) >= 0 {
+                self.stage_moves.push((score, m));
+            } else {
+                self.bad_captures.push((score, m));
+            }
+        }
+        self.stage_moves.sort_by_key(|&(score, _)| score);
+        self.bad_captures.sort_by_key(|&(score, _)| score);
+    }
+
+    fn generate_quiets(&mut self, ordering: &MoveOrdering) {
+        let player = self.pos.get_player_on_move();
+        self.stage_moves = self
+            .pos
+            .get_valid_quiet_moves()
+            .into_iter()
+            .filter(|&m| !self.is_special(m))
+            .map(|m| (ordering.get_history(player, m), m))
+            .collect();
+        self.stage_moves.sort_by_key(|&(score, _)| score);
+    }
+
+    // Quiet moves coming from heuristics have to be checked for legality
+    fn is_valid_special(&self, m: Option<Move>, yielded: &[Option<Move>]) -> bool {
+        match m {
+            Some(m) => {
+                !yielded.contains(&Some(m)) && !self.is_tactical(m) && self.pos.is_move_valid(m)
+            }
+            None => false,
+        }
+    }
+
+    pub fn next(&mut self, ordering: &MoveOrdering) -> Option<Move> {
+        loop {
+            match self.stage {
+                Stage::TtMove => {
+                    self.stage = Stage::GenerateCaptures;
+                    if let Some(m) = self.tt_move {
+                        let valid = (!self.tactical_only || self.is_tactical(m))
+                            && self.pos.is_move_valid(m);
+                        if valid {
+                            return Some(m);
+                        }
+                        self.tt_move = None;
+                    }
+                }
+                Stage::GenerateCaptures => {
+                    self.stage = Stage::GoodCaptures;
+                    self.generate_captures();
+                }
+                Stage::GoodCaptures => {
+                    if let Some((_, m)) = self.stage_moves.pop() {
+                        return Some(m);
+                    }
+                    self.stage = if self.tactical_only {
+                        Stage::Done
+                    } else {
+                        Stage::Killers
+                    };
+                }
+                Stage::Killers => {
+                    while self.special_index < 2 {
+                        let killer = self.killers[self.special_index];
+                        self.special_index += 1;
+                        if self.is_valid_special(killer, &[self.tt_move]) {
+                            return killer;
+                        }
+                        self.killers[self.special_index - 1] = None;
+                    }
+                    self.stage = Stage::CounterMove;
+                }
+                Stage::CounterMove => {
+                    self.stage = Stage::Quiets;
+                    let yielded = [self.tt_move, self.killers[0], self.killers[1]];
+                    if self.is_valid_special(self.counter_move, &yielded) {
+                        self.generate_quiets(ordering);
+                        return self.counter_move;
+                    }
+                    self.counter_move = None;
+                    self.generate_quiets(ordering);
+                }
+                Stage::Quiets => {
+                    if let Some((_, m)) = self.stage_moves.pop() {
+                        return Some(m);
+                    }
+                    self.stage = Stage::BadCaptures;
+                }
+                Stage::BadCaptures => {
+                    if let Some((_, m)) = self.bad_captures.pop() {
+                        return Some(m);
+                    }
+                    self.stage = Stage::Done;
+                }
+                Stage::Done => return None,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FENS: [&str; 4] = [
+        "rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+        "4k3/8/3p4/4p3/1r6/8/4Q3/R3K3 w - - 0 1",
+    ];
+
+    // Stage in which the picker should yield m
+    fn get_stage(
+        pos: &Position,
+        m: Move,
+        killers: [Option<Move>; 2],
+        counter_move: Option<Move>,
+    ) -> Stage {
+        if pos.is_capture(m) || pos.is_promotion(m) {
+            if pos.see(m) >= 0 {
+                Stage::GoodCaptures
+            } else {
+                Stage::BadCaptures
+            }
+        } else if killers.contains(&Some(m)) {
+            Stage::Killers
+        } else if Some(m) == counter_move {
+            Stage::CounterMove
+        } else {
+            Stage::Quiets
+        }
+    }
+
+    fn get_sorted(mut moves: Vec<Move>) -> Vec<Move> {
+        moves.sort();
+        moves
+    }
+
+    #[test]
+    fn every_valid_move_is_yielded_once_in_stage_order() {
+        for fen in FENS {
+            let pos = Position::from_fen(fen).unwrap();
+            let player = pos.get_player_on_move();
+            let valid_moves = pos.get_valid_moves();
+            let quiets = pos.get_valid_quiet_moves();
+            let tactical = pos.get_valid_tactical_moves();
+            assert!(quiets.len() >= 5 && !tactical.is_empty(), "{}", fen);
+
+            // the TT move is a capture, the first killer moves a piece onto
+            // its own square and is skipped
+            let tt_move = tactical[0];
+            let invalid = (quiets[0].0, quiets[0].1, quiets[0].0, quiets[0].1);
+            let killers = [Some(invalid), Some(quiets[0])];
+            let counter_move = Some(quiets[1]);
+            let mut ordering = MoveOrdering::new();
+            ordering.update(player, quiets[2], None, 3, 0);
+            ordering.update(player, quiets[3], None, 5, 0);
+
+            let mut picker = MovePicker::new(&pos, Some(tt_move), killers, counter_move);
+            let mut moves = Vec::new();
+            while let Some(m) = picker.next(&ordering) {
+                moves.push(m);
+            }
+            assert_eq!(
+                get_sorted(moves.clone()),
+                get_sorted(valid_moves),
+                "{}",
+                fen
+            );
+            assert_eq!(moves[0], tt_move, "{}", fen);
+            // the valid killer and the counter move come right after the
+            // good captures
+            let killer = moves.iter().position(|&m| m == quiets[0]).unwrap();
+            assert_eq!(moves[killer + 1], quiets[1], "{}", fen);
+
+            let picker = MovePicker::new(&pos, None, [None; 2], None);
+            for pair in moves[1..].windows(2) {
+                let first = get_stage(&pos, pair[0], killers, counter_move);
+                let second = get_stage(&pos, pair[1], killers, counter_move);
+                assert!(first as u8 <= second as u8, "{} {:?}", fen, pair);
+                if first != second {
+                    continue;
+                }
+                match first {
+                    Stage::GoodCaptures | Stage::BadCaptures => {
+                        assert!(picker.get_mvv_lva(pair[0]) >= picker.get_mvv_lva(pair[1]))
+                    }
+                    Stage::Quiets => assert!(
+                        ordering.get_history(player, pair[0])
+                            >= ordering.get_history(player, pair[1])
+                    ),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn quiescence_yields_only_captures_that_do_not_lose_material() {
+        for fen in FENS {
+            let pos = Position::from_fen(fen).unwrap();
+            let ordering = MoveOrdering::new();
+            let mut picker =
+                MovePicker::new_quiescence(&pos, pos.get_valid_quiet_moves().first().copied());
+            let mut moves = Vec::new();
+            while let Some(m) = picker.next(&ordering) {
+                moves.push(m);
+            }
+            let expected = pos
+                .get_valid_tactical_moves()
+                .into_iter()
+                .filter(|&m| pos.see(m) >= 0)
+                .collect();
+            assert_eq!(get_sorted(moves), get_sorted(expected), "{}", fen);
+        }
+    }
+}
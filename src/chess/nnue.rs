@@ -0,0 +1,615 @@
+// Efficiently updatable neural network evaluation.
+//
+// The network has one hidden layer per perspective (the accumulator) and a
+// single output. Network file layout, little endian:
+//   4 bytes              magic "NNUE"
+//   1 byte               format version (1)
+//   1 byte               feature set, 0 for HalfKP, 1 for HalfKA
+//   2 bytes              hidden size N
+//   N x i16              feature transformer biases
+//   F x N x i16          feature transformer weights, feature major
+//   2N x i8              output weights, side on move perspective first
+//   i32                  output bias
+//
+// Features are relative to the perspective: squares are flipped vertically
+// for black, and the king square of the perspective selects one of 64 sets of
+// piece features. Index = king * kinds * 64 + kind * 64 + square, where kind is
+// 2 * piece + (piece belongs to the opponent), pieces ordered pawn, knight,
+// bishop, rook, queen, king. HalfKP leaves the kings out (10 kinds, F = 40960),
+// HalfKA includes them (12 kinds, F = 49152).
+//
+// Output = (bias + sum(clamp(acc, 0, 127) * weight)) * 400 / (127 * 64)
+// in centipawns from the point of view of the player on move.
+
+use super::chess_piece::ChessPiece;
+use super::chess_player::ChessPlayer;
+use super::position::{Move, Position};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+const MAGIC: &[u8; 4] = b"NNUE";
+const VERSION: u8 = 1;
+const ACTIVATION_MAX: i16 = 127;
+const WEIGHT_SCALE: i64 = 64;
+const OUTPUT_SCALE: i64 = 400;
+// outputs are cut off here, far below mate scores
+const MAX_OUTPUT: i64 = 20000;
+
+const FEATURE_PIECES: [ChessPiece; 6] = [
+    ChessPiece::Pawn,
+    ChessPiece::Knight,
+    ChessPiece::Bishop,
+    ChessPiece::Rook,
+    ChessPiece::Queen,
+    ChessPiece::King,
+];
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FeatureSet {
+    HalfKp,
+    HalfKa,
+}
+
+impl FeatureSet {
+    fn get_kinds(&self) -> usize {
+        match self {
+            FeatureSet::HalfKp => 10,
+            FeatureSet::HalfKa => 12,
+        }
+    }
+
+    pub fn get_feature_count(&self) -> usize {
+        64 * self.get_kinds() * 64
+    }
+}
+
+pub struct Network {
+    feature_set: FeatureSet,
+    hidden_size: usize,
+    feature_biases: Vec<i16>,
+    feature_weights: Vec<i16>,
+    output_weights: Vec<i8>,
+    output_bias: i32,
+}
+
+impl Network {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message);
+        if bytes.len() < 8 || &bytes[0..4] != MAGIC {
+            return Err(invalid("not an NNUE network"));
+        }
+        if bytes[4] != VERSION {
+            return Err(invalid("unsupported NNUE network version"));
+        }
+        let feature_set = match bytes[5] {
+            0 => FeatureSet::HalfKp,
+            1 => FeatureSet::HalfKa,
+            _ => return Err(invalid("unknown NNUE feature set")),
+        };
+        let hidden_size = u16::from_le_bytes([bytes[6], bytes[7]]) as usize;
+        let features = feature_set.get_feature_count();
+        let expected = 8 + 2 * hidden_size + 2 * features * hidden_size + 2 * hidden_size + 4;
+        if hidden_size == 0 || bytes.len() != expected {
+            return Err(invalid("wrong NNUE network size"));
+        }
+
+        let read_i16 = |start: usize, count: usize| -> Vec<i16> {
+            bytes[start..start + 2 * count]
+                .chunks_exact(2)
+                .map(|c| i16::from_le_bytes([c[0], c[1]]))
+                .collect()
+        };
+        let mut offset = 8;
+        let feature_biases = read_i16(offset, hidden_size);
+        offset += 2 * hidden_size;
+        let feature_weights = read_i16(offset, features * hidden_size);
+        offset += 2 * features * hidden_size;
+        let output_weights = bytes[offset..offset + 2 * hidden_size]
+            .iter()
+            .map(|&b| b as i8)
+            .collect();
+        offset += 2 * hidden_size;
+        let output_bias = i32::from_le_bytes([
+            bytes[offset],
+            bytes[offset + 1],
+            bytes[offset + 2],
+            bytes[offset + 3],
+        ]);
+
+        Ok(Self {
+            feature_set,
+            hidden_size,
+            feature_biases,
+            feature_weights,
+            output_weights,
+            output_bias,
+        })
+    }
+
+    pub fn get_feature_set(&self) -> FeatureSet {
+        self.feature_set
+    }
+
+    pub fn get_hidden_size(&self) -> usize {
+        self.hidden_size
+    }
+
+    fn get_feature_index(
+        &self,
+        perspective: ChessPlayer,
+        king: usize,
+        piece: ChessPiece,
+        player: ChessPlayer,
+        square: usize,
+    ) -> Option<usize> {
+        if piece == ChessPiece::King && self.feature_set == FeatureSet::HalfKp {
+            return None;
+        }
+        let orient = |square: usize| match perspective {
+            ChessPlayer::White => square,
+            ChessPlayer::Black => square ^ 56,
+        };
+        let piece_index = FEATURE_PIECES.iter().position(|&p| p == piece).unwrap();
+        let kind = 2 * piece_index + if player == perspective { 0 } else { 1 };
+        Some((orient(king) * self.feature_set.get_kinds() + kind) * 64 + orient(square))
+    }
+
+    fn get_weights(&self, feature: usize) -> &[i16] {
+        &self.feature_weights[feature * self.hidden_size..(feature + 1) * self.hidden_size]
+    }
+
+    // Accumulator of perspective computed from scratch
+    fn refresh(&self, pos: &Position, perspective: ChessPlayer, accumulator: &mut [i16]) {
+        accumulator.copy_from_slice(&self.feature_biases);
+        let king = pos
+            .get_piece_type_by_player(ChessPiece::King, perspective)
+            .trailing_zeros();
+        for &player in [ChessPlayer::White, ChessPlayer::Black].iter() {
+            for &piece in FEATURE_PIECES.iter() {
+                for square in pos.get_piece_type_by_player(piece, player).get_ones() {
+                    if let Some(feature) =
+                        self.get_feature_index(perspective, king, piece, player, square)
+                    {
+                        simd::add_weights(accumulator, self.get_weights(feature));
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Accumulators of the current line, one entry per ply, indexed by player
+#[derive(Clone)]
+struct Accumulator {
+    values: [Vec<i16>; 2],
+}
+
+// Incrementally updated evaluation state of one search thread
+pub struct NnueState {
+    network: Arc<Network>,
+    stack: Vec<Accumulator>,
+    // number of used entries in stack, the top one describes the position
+    len: usize,
+}
+
+impl NnueState {
+    pub fn new(network: Arc<Network>) -> Self {
+        let empty = Accumulator {
+            values: [vec![0; network.hidden_size], vec![0; network.hidden_size]],
+        };
+        Self {
+            network,
+            stack: vec![empty],
+            len: 0,
+        }
+    }
+
+    pub fn get_network(&self) -> Arc<Network> {
+        self.network.clone()
+    }
+
+    // Next free entry, initialized as a copy of the top one
+    fn push(&mut self) -> &mut Accumulator {
+        if self.len == self.stack.len() {
+            let top = self.stack[self.len - 1].clone();
+            self.stack.push(top);
+        } else if self.len > 0 {
+            let (done, free) = self.stack.split_at_mut(self.len);
+            for (to, from) in free[0]
+                .values
+                .iter_mut()
+                .zip(done[self.len - 1].values.iter())
+            {
+                to.copy_from_slice(from);
+            }
+        }
+        self.len += 1;
+        &mut self.stack[self.len - 1]
+    }
+
+    // Starts a new line from pos
+    pub fn refresh(&mut self, pos: &Position) {
+        self.len = 0;
+        let network = self.network.clone();
+        let top = self.push();
+        for &player in [ChessPlayer::White, ChessPlayer::Black].iter() {
+            network.refresh(pos, player, &mut top.values[player as usize]);
+        }
+    }
+
+    // pos is the position before m was played, next the one after it. A king
+    // move changes every feature of its own perspective, which is then
+    // refreshed, everything else only touches the moved and captured pieces.
+    pub fn make_move(&mut self, pos: &Position, m: Move, next: &Position) {
+        let network = self.network.clone();
+        let (from, to) = (m.0 * 8 + m.1, m.2 * 8 + m.3);
+        let player = pos.get_player_on_move();
+        let moved = pos.get_piece_on_position(m.0, m.1).unwrap();
+        let placed = next.get_piece_on_position(m.2, m.3).unwrap();
+        let captured = pos.get_piece_on_position(m.2, m.3);
+
+        let top = self.push();
+        for &perspective in [ChessPlayer::White, ChessPlayer::Black].iter() {
+            let accumulator = &mut top.values[perspective as usize];
+            if moved == ChessPiece::King && player == perspective {
+                network.refresh(next, perspective, accumulator);
+                continue;
+            }
+            let king = next
+                .get_piece_type_by_player(ChessPiece::King, perspective)
+                .trailing_zeros();
+            let feature = |piece, owner, square| {
+                network.get_feature_index(perspective, king, piece, owner, square)
+            };
+            if let Some(f) = feature(moved, player, from) {
+                simd::sub_weights(accumulator, network.get_weights(f));
+            }
+            if let Some(f) = captured.and_then(|piece| feature(piece, player.get_opponent(), to)) {
+                simd::sub_weights(accumulator, network.get_weights(f));
+            }
+            if let Some(f) = feature(placed, player, to) {
+                simd::add_weights(accumulator, network.get_weights(f));
+            }
+        }
+    }
+
+    pub fn make_null_move(&mut self) {
+        self.push();
+    }
+
+    pub fn unmake_move(&mut self) {
+        assert!(self.len > 1);
+        self.len -= 1;
+    }
+
+    // Evaluation of the position on top of the stack from the point of view
+    // of the player on move
+    pub fn evaluate(&self, pos: &Position) -> i32 {
+        let top = &self.stack[self.len - 1];
+        let player = pos.get_player_on_move();
+        let network = &self.network;
+        let (us, them) = network.output_weights.split_at(network.hidden_size);
+        // a large output bias would overflow in i32
+        let sum = network.output_bias as i64
+            + simd::dot_activated(&top.values[player as usize], us) as i64
+            + simd::dot_activated(&top.values[player.get_opponent() as usize], them) as i64;
+        let output = sum * OUTPUT_SCALE / (ACTIVATION_MAX as i64 * WEIGHT_SCALE);
+        output.clamp(-MAX_OUTPUT, MAX_OUTPUT) as i32
+    }
+}
+
+// Vector kernels, AVX2 when the CPU has it and a scalar fallback. Both wrap
+// around on overflow in the same way.
+mod simd {
+    use super::ACTIVATION_MAX;
+
+    pub fn add_weights(accumulator: &mut [i16], weights: &[i16]) {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if is_x86_feature_detected!("avx2") {
+                // safe, the CPU supports the instructions
+                unsafe { avx2::add_weights(accumulator, weights) };
+                return;
+            }
+        }
+        for (a, &w) in accumulator.iter_mut().zip(weights.iter()) {
+            *a = a.wrapping_add(w);
+        }
+    }
+
+    pub fn sub_weights(accumulator: &mut [i16], weights: &[i16]) {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if is_x86_feature_detected!("avx2") {
+                unsafe { avx2::sub_weights(accumulator, weights) };
+                return;
+            }
+        }
+        for (a, &w) in accumulator.iter_mut().zip(weights.iter()) {
+            *a = a.wrapping_sub(w);
+        }
+    }
+
+    // Sum of clamp(value, 0, ACTIVATION_MAX) * weight
+    pub fn dot_activated(values: &[i16], weights: &[i8]) -> i32 {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if is_x86_feature_detected!("avx2") {
+                return unsafe { avx2::dot_activated(values, weights) };
+            }
+        }
+        dot_activated_scalar(values, weights)
+    }
+
+    pub(super) fn dot_activated_scalar(values: &[i16], weights: &[i8]) -> i32 {
+        values
+            .iter()
+            .zip(weights.iter())
+            .map(|(&v, &w)| v.clamp(0, ACTIVATION_MAX) as i32 * w as i32)
+            .sum()
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    pub(super) mod avx2 {
+        use super::ACTIVATION_MAX;
+        use std::arch::x86_64::*;
+
+        const LANES: usize = 16;
+
+        #[target_feature(enable = "avx2")]
+        pub unsafe fn add_weights(accumulator: &mut [i16], weights: &[i16]) {
+            let chunks = accumulator.len() / LANES;
+            for k in 0..chunks {
+                let a = accumulator.as_mut_ptr().add(k * LANES) as *mut __m256i;
+                let w = weights.as_ptr().add(k * LANES) as *const __m256i;
+                _mm256_storeu_si256(
+                    a,
+                    _mm256_add_epi16(_mm256_loadu_si256(a), _mm256_loadu_si256(w)),
+                );
+            }
+            for k in chunks * LANES..accumulator.len() {
+                accumulator[k] = accumulator[k].wrapping_add(weights[k]);
+            }
+        }
+
+        #[target_feature(enable = "avx2")]
+        pub unsafe fn sub_weights(accumulator: &mut [i16], weights: &[i16]) {
+            let chunks = accumulator.len() / LANES;
+            for k in 0..chunks {
+                let a = accumulator.as_mut_ptr().add(k * LANES) as *mut __m256i;
+                let w = weights.as_ptr().add(k * LANES) as *const __m256i;
+                _mm256_storeu_si256(
+                    a,
+                    _mm256_sub_epi16(_mm256_loadu_si256(a), _mm256_loadu_si256(w)),
+                );
+            }
+            for k in chunks * LANES..accumulator.len() {
+                accumulator[k] = accumulator[k].wrapping_sub(weights[k]);
+            }
+        }
+
+        #[target_feature(enable = "avx2")]
+        pub unsafe fn dot_activated(values: &[i16], weights: &[i8]) -> i32 {
+            let chunks = values.len() / LANES;
+            let zero = _mm256_setzero_si256();
+            let max = _mm256_set1_epi16(ACTIVATION_MAX);
+            let mut sum = _mm256_setzero_si256();
+            for k in 0..chunks {
+                let v = _mm256_loadu_si256(values.as_ptr().add(k * LANES) as *const __m256i);
+                let v = _mm256_min_epi16(_mm256_max_epi16(v, zero), max);
+                let w = _mm_loadu_si128(weights.as_ptr().add(k * LANES) as *const __m128i);
+                let w = _mm256_cvtepi8_epi16(w);
+                sum = _mm256_add_epi32(sum, _mm256_madd_epi16(v, w));
+            }
+            let mut lanes = [0i32; 8];
+            _mm256_storeu_si256(lanes.as_mut_ptr() as *mut __m256i, sum);
+            let rest = chunks * LANES;
+            lanes.iter().sum::<i32>()
+                + super::dot_activated_scalar(&values[rest..], &weights[rest..])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    // Small random weights, the hidden size is not a multiple of the vector
+    // width so the scalar tail of the kernels is used too
+    fn get_random_network(feature_set: FeatureSet) -> Network {
+        let hidden_size = 24;
+        let mut rng = StdRng::seed_from_u64(7);
+        let features = feature_set.get_feature_count();
+        Network {
+            feature_set,
+            hidden_size,
+            feature_biases: (0..hidden_size).map(|_| rng.gen_range(-64..64)).collect(),
+            feature_weights: (0..features * hidden_size)
+                .map(|_| rng.gen_range(-64..64))
+                .collect(),
+            output_weights: (0..2 * hidden_size).map(|_| rng.gen()).collect(),
+            output_bias: rng.gen_range(-1000..1000),
+        }
+    }
+
+    fn to_bytes(network: &Network) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(VERSION);
+        bytes.push(match network.feature_set {
+            FeatureSet::HalfKp => 0,
+            FeatureSet::HalfKa => 1,
+        });
+        bytes.extend_from_slice(&(network.hidden_size as u16).to_le_bytes());
+        for &value in network
+            .feature_biases
+            .iter()
+            .chain(network.feature_weights.iter())
+        {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        bytes.extend(network.output_weights.iter().map(|&w| w as u8));
+        bytes.extend_from_slice(&network.output_bias.to_le_bytes());
+        bytes
+    }
+
+    // Plays moves from fen and checks every incrementally updated
+    // accumulator against one computed from scratch, then takes them back
+    fn check_incremental_updates(feature_set: FeatureSet, fen: &str, moves: &[Move]) {
+        let network = Arc::new(get_random_network(feature_set));
+        let refreshed = |pos: &Position| {
+            let mut state = NnueState::new(network.clone());
+            state.refresh(pos);
+            (state.stack[0].values.clone(), state.evaluate(pos))
+        };
+
+        let mut state = NnueState::new(network.clone());
+        let start = Position::from_fen(fen).unwrap();
+        let mut pos = start;
+        state.refresh(&pos);
+        for &m in moves.iter() {
+            let mut next = pos;
+            assert!(next.make_move(m.0, m.1, m.2, m.3), "{:?} is not valid", m);
+            state.make_move(&pos, m, &next);
+            let top = state.stack[state.len - 1].values.clone();
+            assert_eq!(
+                (top, state.evaluate(&next)),
+                refreshed(&next),
+                "after {:?}",
+                m
+            );
+            pos = next;
+        }
+        for _ in moves.iter() {
+            state.unmake_move();
+        }
+        assert_eq!(state.len, 1);
+        assert_eq!(
+            (state.stack[0].values.clone(), state.evaluate(&start)),
+            refreshed(&start)
+        );
+    }
+
+    #[test]
+    fn incremental_updates_match_refresh() {
+        // exd5, Kd7, bxa8=Q, Kd6, Kf2, Kc5, Rh5 and Kb4
+        let fen = "r3k3/1P6/8/3p4/4P3/8/8/4K2R w - - 0 1";
+        let moves = [
+            (3, 4, 4, 3),
+            (7, 4, 6, 3),
+            (6, 1, 7, 0),
+            (6, 3, 5, 3),
+            (0, 4, 1, 5),
+            (5, 3, 4, 2),
+            (0, 7, 4, 7),
+            (4, 2, 3, 1),
+        ];
+        // black promotes with bxa1=Q+, then Kd2, Qxa2 and Kxd3
+        let black_fen = "4k3/8/8/8/8/3p4/Pp6/N3K3 b - - 0 1";
+        let black_moves = [(1, 1, 0, 0), (0, 4, 1, 3), (0, 0, 1, 0), (1, 3, 2, 3)];
+        for &feature_set in [FeatureSet::HalfKp, FeatureSet::HalfKa].iter() {
+            check_incremental_updates(feature_set, fen, &moves);
+            check_incremental_updates(feature_set, black_fen, &black_moves);
+        }
+    }
+
+    #[test]
+    fn network_is_read_from_bytes() {
+        let network = get_random_network(FeatureSet::HalfKa);
+        let read = Network::from_bytes(&to_bytes(&network)).unwrap();
+        assert_eq!(read.get_feature_set(), FeatureSet::HalfKa);
+        assert_eq!(read.get_hidden_size(), network.hidden_size);
+        assert_eq!(read.feature_biases, network.feature_biases);
+        assert_eq!(read.feature_weights, network.feature_weights);
+        assert_eq!(read.output_weights, network.output_weights);
+        assert_eq!(read.output_bias, network.output_bias);
+    }
+
+    #[test]
+    fn broken_networks_are_rejected() {
+        let bytes = to_bytes(&get_random_network(FeatureSet::HalfKp));
+        let is_rejected = |bytes: &[u8]| {
+            Network::from_bytes(bytes).err().map(|e| e.kind()) == Some(io::ErrorKind::InvalidData)
+        };
+        assert!(is_rejected(&bytes[..bytes.len() - 1]));
+        let mut longer = bytes.clone();
+        longer.push(0);
+        assert!(is_rejected(&longer));
+        assert!(is_rejected(&bytes[..6]));
+
+        let header = |position: usize, value: u8| {
+            let mut broken = bytes.clone();
+            broken[position] = value;
+            is_rejected(&broken)
+        };
+        // magic, version, feature set and a hidden size that does not fit
+        assert!(header(0, b'X'));
+        assert!(header(4, VERSION + 1));
+        assert!(header(5, 2));
+        assert!(header(6, 25));
+    }
+
+    // Values around the clamping bounds and the i16 limits, with a length
+    // that is not a multiple of the vector width
+    fn get_test_vectors() -> (Vec<i16>, Vec<i16>, Vec<i8>) {
+        let values = (0..37)
+            .map(|k| [-32768, -5, 0, 63, 127, 128, 32767][k % 7])
+            .collect();
+        let weights = (0..37).map(|k| (k as i16 - 18) * 1800).collect();
+        let output_weights = (0..37).map(|k| [-128, -1, 0, 1, 127][k % 5]).collect();
+        (values, weights, output_weights)
+    }
+
+    #[test]
+    #[cfg(target_arch = "x86_64")]
+    fn avx2_kernels_match_scalar() {
+        if !is_x86_feature_detected!("avx2") {
+            return;
+        }
+        let (values, weights, output_weights) = get_test_vectors();
+
+        let mut scalar = values.clone();
+        for (a, &w) in scalar.iter_mut().zip(weights.iter()) {
+            *a = a.wrapping_add(w);
+        }
+        let mut vector = values.clone();
+        unsafe { simd::avx2::add_weights(&mut vector, &weights) };
+        assert_eq!(vector, scalar);
+
+        for (a, &w) in scalar.iter_mut().zip(weights.iter()) {
+            *a = a.wrapping_sub(w);
+        }
+        unsafe { simd::avx2::sub_weights(&mut vector, &weights) };
+        assert_eq!(vector, scalar);
+
+        assert_eq!(
+            unsafe { simd::avx2::dot_activated(&values, &output_weights) },
+            simd::dot_activated_scalar(&values, &output_weights)
+        );
+    }
+
+    #[test]
+    fn large_output_is_clamped() {
+        let hidden_size = 16;
+        let feature_set = FeatureSet::HalfKp;
+        let network = Network {
+            feature_set,
+            hidden_size,
+            feature_biases: vec![ACTIVATION_MAX; hidden_size],
+            feature_weights: vec![0; feature_set.get_feature_count() * hidden_size],
+            output_weights: vec![127; 2 * hidden_size],
+            output_bias: i32::MAX / 2,
+        };
+        let mut state = NnueState::new(Arc::new(network));
+        let pos = Position::default();
+        state.refresh(&pos);
+        assert_eq!(state.evaluate(&pos), MAX_OUTPUT as i32);
+    }
+}
@@ -0,0 +1,411 @@
+use super::chess_piece::ChessPiece;
+use super::chess_player::ChessPlayer;
+use super::game::Game;
+use super::position::{Move, Position, PROMOTION_PIECES};
+
+const FILES: &str = "abcdefgh";
+const RESULTS: [&str; 4] = ["1-0", "0-1", "1/2-1/2", "*"];
+
+#[derive(Debug, Clone, Default)]
+pub struct PgnGame {
+    pub headers: Vec<(String, String)>,
+    // the initial position unless the FEN tag gives another one
+    pub start: Position,
+    // moves from the starting position, up to the first one that could not
+    // be replayed
+    pub moves: Vec<Move>,
+    // piece each move promotes to, a queen for moves that are not promotions
+    pub promotions: Vec<ChessPiece>,
+    pub result: String,
+    // movetext contained moves that are not supported or not valid
+    pub truncated: bool,
+}
+
+impl PgnGame {
+    pub fn get_header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    // Moves up to the first underpromotion, the ones that can be replayed
+    // where pawns always promote to a queen, e.g. in books or UCI engines
+    pub fn get_moves_before_underpromotion(&self) -> &[Move] {
+        let end = self
+            .promotions
+            .iter()
+            .position(|&piece| piece != ChessPiece::Queen)
+            .unwrap_or(self.moves.len());
+        &self.moves[..end]
+    }
+}
+
+fn get_piece_from_letter(letter: char) -> Option<ChessPiece> {
+    match letter {
+        'N' => Some(ChessPiece::Knight),
+        'B' => Some(ChessPiece::Bishop),
+        'R' => Some(ChessPiece::Rook),
+        'Q' => Some(ChessPiece::Queen),
+        'K' => Some(ChessPiece::King),
+        _ => None,
+    }
+}
+
+// Parses a move in standard algebraic notation, e.g. Nbd7, exd5 or e8=N,
+// with the piece it promotes to, a queen for moves that are not promotions.
+// Returns None unless it describes exactly one valid move of pos. Castling
+// is not part of the rules and is never parsed.
+pub fn parse_san(pos: &Position, san: &str) -> Option<(Move, ChessPiece)> {
+    let san = san.trim_end_matches(|c| "+#!?".contains(c));
+    if san.starts_with("O-O") || san.starts_with("0-0") {
+        return None;
+    }
+
+    let (san, promotion) = match san.find('=') {
+        Some(ind) => (&san[..ind], Some(&san[ind + 1..])),
+        None => match san.chars().last() {
+            Some(c) if get_piece_from_letter(c).is_some() => {
+                (&san[..san.len() - 1], Some(&san[san.len() - 1..]))
+            }
+            _ => (san, None),
+        },
+    };
+    let promotion = match promotion {
+        Some(letter) if letter.len() == 1 => {
+            let piece = get_piece_from_letter(letter.chars().next()?)?;
+            if !PROMOTION_PIECES.contains(&piece) {
+                return None;
+            }
+            Some(piece)
+        }
+        Some(_) => return None,
+        None => None,
+    };
+
+    let mut chars: Vec<char> = san.chars().filter(|&c| c != 'x' && c != '-').collect();
+    let piece = match chars.first().and_then(|&c| get_piece_from_letter(c)) {
+        Some(piece) => {
+            chars.remove(0);
+            piece
+        }
+        None => ChessPiece::Pawn,
+    };
+    if chars.len() < 2 {
+        return None;
+    }
+
+    let target: Vec<char> = chars.split_off(chars.len() - 2);
+    let l = FILES.find(target[0])?;
+    let k = target[1].to_digit(10)?.checked_sub(1)? as usize;
+    let mut from_file = None;
+    let mut from_rank = None;
+    for c in chars {
+        if let Some(j) = FILES.find(c) {
+            from_file = Some(j);
+        } else {
+            from_rank = Some(c.to_digit(10)?.checked_sub(1)? as usize);
+        }
+    }
+
+    let candidates: Vec<Move> = pos
+        .get_valid_moves()
+        .into_iter()
+        .filter(|&(i, j, to_i, to_j)| {
+            (to_i, to_j) == (k, l)
+                && pos.get_piece_on_position(i, j) == Some(piece)
+                && from_file.is_none_or(|f| f == j)
+                && from_rank.is_none_or(|r| r == i)
+        })
+        .collect();
+    if candidates.len() != 1 || pos.is_promotion(candidates[0]) != promotion.is_some() {
+        return None;
+    }
+    Some((candidates[0], promotion.unwrap_or(ChessPiece::Queen)))
+}
+
+fn get_letter_of_piece(piece: ChessPiece) -> char {
+    match piece {
+        ChessPiece::Pawn => 'P',
+        ChessPiece::Knight => 'N',
+        ChessPiece::Bishop => 'B',
+        ChessPiece::Rook => 'R',
+        ChessPiece::Queen => 'Q',
+        ChessPiece::King => 'K',
+    }
+}
+
+// Formats a valid move of pos promoting to promotion in standard algebraic
+// notation, with the origin disambiguated only as much as needed and + or #
+// for checks
+pub fn move_to_san(pos: &Position, m: Move, promotion: ChessPiece) -> String {
+    let (i, j, k, l) = m;
+    let files: Vec<char> = FILES.chars().collect();
+    let piece = pos.get_piece_on_position(i, j).unwrap();
+    let mut san = String::new();
+    if piece == ChessPiece::Pawn {
+        if pos.is_capture(m) {
+            san.push(files[j]);
+        }
+    } else {
+        san.push(get_letter_of_piece(piece));
+        let others: Vec<Move> = pos
+            .get_valid_moves()
+            .into_iter()
+            .filter(|&(from_i, from_j, to_i, to_j)| {
+                (to_i, to_j) == (k, l)
+                    && (from_i, from_j) != (i, j)
+                    && pos.get_piece_on_position(from_i, from_j) == Some(piece)
+            })
+            .collect();
+        if !others.is_empty() {
+            if others.iter().all(|other| other.1 != j) {
+                san.push(files[j]);
+            } else if others.iter().all(|other| other.0 != i) {
+                san.push_str(&(i + 1).to_string());
+            } else {
+                san.push(files[j]);
+                san.push_str(&(i + 1).to_string());
+            }
+        }
+    }
+    if pos.is_capture(m) {
+        san.push('x');
+    }
+    san.push(files[l]);
+    san.push_str(&(k + 1).to_string());
+    if pos.is_promotion(m) {
+        san.push('=');
+        san.push(get_letter_of_piece(promotion));
+    }
+
+    let mut next = *pos;
+    next.make_move_promoting(i, j, k, l, promotion);
+    if next.is_in_check(next.get_player_on_move()) {
+        san.push(if next.get_valid_moves().is_empty() {
+            '#'
+        } else {
+            '+'
+        });
+    }
+    san
+}
+
+// Writes one game as PGN, the Result tag follows headers. A start position
+// other than the initial one is recorded in the SetUp and FEN tags. These
+// tags come from the arguments, the same ones in headers are skipped.
+pub fn write_pgn(headers: &[(String, String)], game: &Game, result: &str) -> String {
+    let start = game.get_start_position();
+    let mut pgn = String::new();
+    for (name, value) in headers.iter() {
+        if ["Result", "SetUp", "FEN"].contains(&name.as_str()) {
+            continue;
+        }
+        pgn.push_str(&format!("[{} \"{}\"]\n", name, value.replace('"', "\\\"")));
+    }
+    pgn.push_str(&format!("[Result \"{}\"]\n", result));
+    if start.to_fen() != Position::default().to_fen() {
+        pgn.push_str(&format!("[SetUp \"1\"]\n[FEN \"{}\"]\n", start.to_fen()));
+    }
+    pgn.push('\n');
+
+    // movetext is wrapped to lines of at most 80 characters
+    let moves = game.get_moves();
+    let mut tokens = Vec::with_capacity(moves.len() * 3 / 2 + 1);
+    let offset = start.get_player_on_move() as usize;
+    for (ply, &m) in moves.iter().enumerate() {
+        let pos = game.get_position_at(ply);
+        let number = (ply + offset) / 2 + 1;
+        match pos.get_player_on_move() {
+            ChessPlayer::White => tokens.push(format!("{}.", number)),
+            ChessPlayer::Black if ply == 0 => tokens.push(format!("{}...", number)),
+            ChessPlayer::Black => {}
+        }
+        tokens.push(move_to_san(pos, m, game.get_promotion(ply)));
+    }
+    tokens.push(result.to_string());
+    let mut line = String::new();
+    for token in tokens {
+        if !line.is_empty() && line.len() + 1 + token.len() > 80 {
+            pgn.push_str(&line);
+            pgn.push('\n');
+            line.clear();
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(&token);
+    }
+    pgn.push_str(&line);
+    pgn.push_str("\n\n");
+    pgn
+}
+
+fn parse_tag(tag: &str) -> Option<(String, String)> {
+    let tag = tag.trim();
+    let space = tag.find(char::is_whitespace)?;
+    let name = tag[..space].to_string();
+    let value = tag[space..].trim().trim_matches('"').replace("\\\"", "\"");
+    Some((name, value))
+}
+
+struct GameBuilder {
+    game: PgnGame,
+    pos: Position,
+    has_movetext: bool,
+}
+
+impl GameBuilder {
+    fn new() -> Self {
+        Self {
+            game: PgnGame::default(),
+            pos: Position::default(),
+            has_movetext: false,
+        }
+    }
+
+    fn add_header(&mut self, name: String, value: String) {
+        // moves can't be replayed from a start that is not valid
+        if name == "FEN" {
+            match Position::from_fen(&value) {
+                Ok(pos) => {
+                    self.pos = pos;
+                    self.game.start = pos;
+                }
+                Err(_) => self.game.truncated = true,
+            }
+        }
+        self.game.headers.push((name, value));
+    }
+
+    fn add_token(&mut self, token: &str) {
+        self.has_movetext = true;
+        if RESULTS.contains(&token) {
+            self.game.result = token.to_string();
+            return;
+        }
+        // move numbers, possibly glued to the move as in 1.e4 or 3...Nf6
+        let token = token.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+        if token.is_empty() || self.game.truncated {
+            return;
+        }
+        match parse_san(&self.pos, token) {
+            Some((m, promotion)) => {
+                self.pos.make_move_promoting(m.0, m.1, m.2, m.3, promotion);
+                self.game.moves.push(m);
+                self.game.promotions.push(promotion);
+            }
+            None => self.game.truncated = true,
+        }
+    }
+}
+
+// Parses all games of a PGN file. Comments, variations and annotations are
+// skipped, games start from the initial position unless they have a FEN tag.
+pub fn parse_pgn(text: &str) -> Vec<PgnGame> {
+    let mut games = Vec::new();
+    let mut builder = GameBuilder::new();
+    let mut chars = text.chars().peekable();
+    let mut token = String::new();
+
+    while let Some(c) = chars.next() {
+        let ends_token = c.is_whitespace() || "[{(;$".contains(c);
+        if ends_token && !token.is_empty() {
+            builder.add_token(&token);
+            token.clear();
+        }
+        match c {
+            '[' => {
+                if builder.has_movetext {
+                    games.push(builder.game);
+                    builder = GameBuilder::new();
+                }
+                let tag: String = chars.by_ref().take_while(|&c| c != ']').collect();
+                if let Some((name, value)) = parse_tag(&tag) {
+                    builder.add_header(name, value);
+                }
+            }
+            '{' => chars.by_ref().take_while(|&c| c != '}').for_each(drop),
+            ';' => chars.by_ref().take_while(|&c| c != '\n').for_each(drop),
+            '(' => {
+                let mut depth = 1;
+                for c in chars.by_ref() {
+                    match c {
+                        '(' => depth += 1,
+                        ')' => depth -= 1,
+                        _ => {}
+                    }
+                    if depth == 0 {
+                        break;
+                    }
+                }
+            }
+            '$' => {
+                while chars.peek().is_some_and(|c| c.is_ascii_digit()) {
+                    chars.next();
+                }
+            }
+            c if c.is_whitespace() => {}
+            c => token.push(c),
+        }
+    }
+    if !token.is_empty() {
+        builder.add_token(&token);
+    }
+    if builder.has_movetext || !builder.game.headers.is_empty() {
+        games.push(builder.game);
+    }
+    games
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn written_game_reads_back() {
+        let start = Position::from_fen("4k3/8/8/8/8/8/4P3/4K3 b - - 0 1").unwrap();
+        let moves = [(7, 4, 6, 4), (1, 4, 3, 4)];
+        let headers = vec![
+            ("White".to_string(), "A \"quoted\" name".to_string()),
+            ("Result".to_string(), "1-0".to_string()),
+        ];
+        let mut game = Game::new(start);
+        for &m in moves.iter() {
+            assert!(game.make_move(m));
+        }
+        let pgn = write_pgn(&headers, &game, "*");
+        assert_eq!(pgn.matches("[Result ").count(), 1);
+
+        let games = parse_pgn(&pgn);
+        assert_eq!(games.len(), 1);
+        assert_eq!(games[0].get_header("White"), Some("A \"quoted\" name"));
+        assert_eq!(games[0].get_header("Result"), Some("*"));
+        assert_eq!(games[0].start.to_fen(), start.to_fen());
+        assert_eq!(games[0].moves, moves);
+    }
+
+    #[test]
+    fn underpromotions_round_trip() {
+        let pos = Position::from_fen("8/4P1k1/8/8/8/8/8/K7 w - - 0 1").unwrap();
+        let m = (6, 4, 7, 4);
+        assert_eq!(parse_san(&pos, "e8=N"), Some((m, ChessPiece::Knight)));
+        assert_eq!(parse_san(&pos, "e8R"), Some((m, ChessPiece::Rook)));
+        assert_eq!(parse_san(&pos, "e8=Q+"), Some((m, ChessPiece::Queen)));
+        assert_eq!(parse_san(&pos, "e8=K"), None);
+        assert_eq!(parse_san(&pos, "e8"), None);
+        assert_eq!(move_to_san(&pos, m, ChessPiece::Bishop), "e8=B");
+        // only the knight attacks the king on g7
+        assert_eq!(move_to_san(&pos, m, ChessPiece::Rook), "e8=R");
+        assert_eq!(move_to_san(&pos, m, ChessPiece::Knight), "e8=N+");
+
+        let mut game = Game::new(pos);
+        assert!(game.make_move_promoting(m, ChessPiece::Knight));
+        assert!(game.make_move((6, 6, 6, 5)));
+        let games = parse_pgn(&write_pgn(&[], &game, "*"));
+        assert_eq!(games[0].moves, game.get_moves());
+        assert_eq!(games[0].promotions, [ChessPiece::Knight, ChessPiece::Queen]);
+        assert!(games[0].get_moves_before_underpromotion().is_empty());
+        assert!(!games[0].truncated);
+    }
+}
@@ -9,11 +9,37 @@ use super::magic_bitboards::{
     BISHOP_BLOCKER_MASKS, BISHOP_MAP, KING_POSSIBLE_MOVES, KNIGHT_POSSIBLE_MOVES,
     ROOK_BLOCKER_MASKS, ROOK_MAP,
 };
+use super::zobrist::{ZOBRIST_BLACK_ON_MOVE, ZOBRIST_PIECES};
 use std::fmt;
 
 const WHITE_PIECES: [&str; 6] = ["♙", "♖", "♘", "♗", "♕", "♔"];
 const BLACK_PIECES: [&str; 6] = ["♟︎", "♜", "♞", "♝", "♛", "♚"];
 
+// Order in which pieces are tried as attackers during static exchange evaluation
+const SEE_ATTACKER_ORDER: [ChessPiece; 6] = [
+    ChessPiece::Pawn,
+    ChessPiece::Knight,
+    ChessPiece::Bishop,
+    ChessPiece::Rook,
+    ChessPiece::Queen,
+    ChessPiece::King,
+];
+
+// Pieces a pawn can promote to, the first one is what make_move picks
+pub const PROMOTION_PIECES: [ChessPiece; 4] = [
+    ChessPiece::Queen,
+    ChessPiece::Rook,
+    ChessPiece::Bishop,
+    ChessPiece::Knight,
+];
+
+// Pawns on these files would wrap around the board when capturing sideways
+const FILE_A: u64 = 0x0101_0101_0101_0101;
+const FILE_H: u64 = FILE_A << 7;
+
+// (from_i, from_j, to_i, to_j)
+pub type Move = (usize, usize, usize, usize);
+
 #[derive(Debug, Copy, Clone, Hash)]
 pub struct Position {
     white: Bitboard,
@@ -27,7 +53,7 @@ pub struct Position {
 }
 
 impl Position {
-    fn get_taken_bitboard(&self) -> Bitboard {
+    pub fn get_taken_bitboard(&self) -> Bitboard {
         self.pawn | self.rook | self.knight | self.bishop | self.queen | self.king
     }
 
@@ -43,7 +69,7 @@ impl Position {
         self.get_taken_bitboard() - self.white
     }
 
-    fn get_pieces_of_player(&self, player: ChessPlayer) -> Bitboard {
+    pub fn get_pieces_of_player(&self, player: ChessPlayer) -> Bitboard {
         match player {
             ChessPlayer::White => self.get_white_pieces(),
             ChessPlayer::Black => self.get_black_pieces(),
@@ -68,7 +94,7 @@ impl Position {
         }
     }
 
-    fn get_piece_mask(&self, piece: ChessPiece) -> Bitboard {
+    pub fn get_piece_mask(&self, piece: ChessPiece) -> Bitboard {
         match piece {
             ChessPiece::Pawn => self.pawn,
             ChessPiece::Rook => self.rook,
@@ -79,7 +105,7 @@ impl Position {
         }
     }
 
-    fn get_piece_type_by_player(&self, piece: ChessPiece, player: ChessPlayer) -> Bitboard {
+    pub fn get_piece_type_by_player(&self, piece: ChessPiece, player: ChessPlayer) -> Bitboard {
         let player_mask = match player {
             ChessPlayer::White => self.get_white_pieces(),
             ChessPlayer::Black => self.get_black_pieces(),
@@ -136,14 +162,17 @@ impl Position {
             ChessPlayer::Black => (my_pawn >> 8) & self.get_free_bitboard(),
         };
 
-        let diagonal_move = match player {
-            ChessPlayer::White => ((my_pawn << 7) | (my_pawn << 9)) & self.get_black_pieces(),
-            ChessPlayer::Black => ((my_pawn >> 7) | (my_pawn >> 9)) & self.get_white_pieces(),
-        };
+        let diagonal_move = Self::get_pawn_attacks(my_pawn, player)
+            & self.get_pieces_of_player(player.get_opponent());
 
+        // the square in between has to be free as well
+        let starting_move = match player {
+            ChessPlayer::White => ((my_pawn & (0xff << 8)) << 8) & self.get_free_bitboard(),
+            ChessPlayer::Black => ((my_pawn & (0xff << 48)) >> 8) & self.get_free_bitboard(),
+        };
         let starting_move = match player {
-            ChessPlayer::White => (((my_pawn & (0xff << 8)) << 16) & self.get_free_bitboard()),
-            ChessPlayer::Black => (((my_pawn & (0xff << 48)) >> 16) & self.get_free_bitboard()),
+            ChessPlayer::White => (starting_move << 8) & self.get_free_bitboard(),
+            ChessPlayer::Black => (starting_move >> 8) & self.get_free_bitboard(),
         };
 
         let all_moves = classic_move | diagonal_move | starting_move;
@@ -192,6 +221,19 @@ impl Position {
         KING_POSSIBLE_MOVES[i * 8 + j] & (!self.get_pieces_of_player(player))
     }
 
+    // Squares the piece standing on [i, j] can move to, checks are ignored
+    pub fn get_piece_moves(&self, i: usize, j: usize) -> Bitboard {
+        let player = self.get_player_on_position(i, j).unwrap();
+        match self.get_piece_on_position(i, j).unwrap() {
+            ChessPiece::Pawn => self.get_valid_pawn_moves(i, j, player),
+            ChessPiece::Rook => self.get_valid_rook_moves(i, j, player),
+            ChessPiece::Knight => self.get_valid_knight_moves(i, j, player),
+            ChessPiece::Bishop => self.get_valid_bishop_moves(i, j, player),
+            ChessPiece::Queen => self.get_valid_queen_moves(i, j, player),
+            ChessPiece::King => self.get_valid_king_moves(i, j, player),
+        }
+    }
+
     fn is_valid_move(
         &self,
         chess_piece: ChessPiece,
@@ -244,7 +286,22 @@ impl Position {
         true
     }
 
+    // Plays the move, a pawn reaching the last rank becomes a queen
     pub fn make_move(&mut self, i: usize, j: usize, k: usize, l: usize) -> bool {
+        self.make_move_promoting(i, j, k, l, ChessPiece::Queen)
+    }
+
+    // Same as make_move, a pawn reaching the last rank becomes promotion,
+    // one of PROMOTION_PIECES. Ignored for other moves.
+    pub fn make_move_promoting(
+        &mut self,
+        i: usize,
+        j: usize,
+        k: usize,
+        l: usize,
+        promotion: ChessPiece,
+    ) -> bool {
+        assert!(PROMOTION_PIECES.contains(&promotion));
         assert!(i < 8 && j < 8 && (i * 8 + j < 64));
         assert_ne!((i, j), (k, l));
         assert_eq!(self.get_taken_bitboard().is_set(i, j), true);
@@ -292,17 +349,18 @@ impl Position {
         helper[ind].set(k, l);
 
         if chess_piece == ChessPiece::Pawn {
+            let promoted = num::ToPrimitive::to_usize(&promotion).unwrap();
             match player_on_move {
                 ChessPlayer::White => {
                     if k == 7 {
                         helper[ind].clear(k, l);
-                        helper[4].set(k, l);
+                        helper[promoted].set(k, l);
                     }
                 }
                 ChessPlayer::Black => {
                     if k == 0 {
                         helper[ind].clear(k, l);
-                        helper[4].set(k, l);
+                        helper[promoted].set(k, l);
                     }
                 }
             };
@@ -320,14 +378,17 @@ impl Position {
         true
     }
 
+    fn get_pawn_attacks(pawns: Bitboard, player: ChessPlayer) -> Bitboard {
+        match player {
+            ChessPlayer::White => ((pawns & !FILE_A) << 7) | ((pawns & !FILE_H) << 9),
+            ChessPlayer::Black => ((pawns & !FILE_H) >> 7) | ((pawns & !FILE_A) >> 9),
+        }
+    }
+
     fn get_attacked_by_pawns(&self, by_player: ChessPlayer) -> Bitboard {
         let my_pawns = self.get_piece_type_by_player(ChessPiece::Pawn, by_player);
-        let diagonal_move = match by_player {
-            ChessPlayer::White => ((my_pawns << 7) | (my_pawns << 9)) & self.get_black_pieces(),
-            ChessPlayer::Black => ((my_pawns >> 7) | (my_pawns >> 9)) & self.get_white_pieces(),
-        };
-        let all_moves = diagonal_move;
-        all_moves & !self.get_pieces_of_player(by_player)
+        Self::get_pawn_attacks(my_pawns, by_player)
+            & self.get_pieces_of_player(by_player.get_opponent())
     }
 
     fn get_attacked_by_other(&self, by_player: ChessPlayer, piece: ChessPiece) -> Bitboard {
@@ -378,6 +439,240 @@ impl Position {
             | self.get_attacked_by_other(by_player, ChessPiece::Queen)
     }
 
+    pub fn get_zobrist_key(&self) -> u64 {
+        let mut key = match self.get_player_on_move() {
+            ChessPlayer::White => 0,
+            ChessPlayer::Black => *ZOBRIST_BLACK_ON_MOVE,
+        };
+        for &player in [ChessPlayer::White, ChessPlayer::Black].iter() {
+            for (ind, &piece_bitfield) in [
+                self.pawn,
+                self.rook,
+                self.knight,
+                self.bishop,
+                self.queen,
+                self.king,
+            ]
+            .iter()
+            .enumerate()
+            {
+                let pieces = piece_bitfield & self.get_pieces_of_player(player);
+                for square in pieces.get_ones() {
+                    key ^= ZOBRIST_PIECES[player as usize][ind][square];
+                }
+            }
+        }
+        key
+    }
+
+    // Passes the turn to the opponent, used by null-move pruning
+    pub fn make_null_move(&mut self) {
+        self.change_player_on_move();
+    }
+
+    pub fn has_non_pawn_material(&self, player: ChessPlayer) -> bool {
+        let pieces = self.get_pieces_of_player(player) & !(self.pawn | self.king);
+        pieces != Bitboard::new(0)
+    }
+
+    // Material of player written as in endgame names, e.g. "KRP"
+    pub fn get_material_signature(&self, player: ChessPlayer) -> String {
+        let pieces = [
+            (ChessPiece::King, 'K'),
+            (ChessPiece::Queen, 'Q'),
+            (ChessPiece::Rook, 'R'),
+            (ChessPiece::Bishop, 'B'),
+            (ChessPiece::Knight, 'N'),
+            (ChessPiece::Pawn, 'P'),
+        ];
+        let mut res = String::new();
+        for &(piece, letter) in pieces.iter() {
+            let count = self.get_piece_type_by_player(piece, player).count_ones();
+            res.extend(std::iter::repeat_n(letter, count));
+        }
+        res
+    }
+
+    // Castling and en passant are not supported, so their fields are always
+    // empty
+    pub fn to_fen_with_clocks(&self, halfmove_clock: u32, fullmove_number: u32) -> String {
+        let mut fen = String::new();
+        for i in (0..8).rev() {
+            let mut empty = 0;
+            for j in 0..8 {
+                let piece = match self.get_piece_on_position(i, j) {
+                    Some(piece) => piece,
+                    None => {
+                        empty += 1;
+                        continue;
+                    }
+                };
+                if empty > 0 {
+                    fen.push_str(&empty.to_string());
+                    empty = 0;
+                }
+                let letter = match piece {
+                    ChessPiece::Pawn => 'p',
+                    ChessPiece::Rook => 'r',
+                    ChessPiece::Knight => 'n',
+                    ChessPiece::Bishop => 'b',
+                    ChessPiece::Queen => 'q',
+                    ChessPiece::King => 'k',
+                };
+                if self.white.is_set(i, j) {
+                    fen.push(letter.to_ascii_uppercase());
+                } else {
+                    fen.push(letter);
+                }
+            }
+            if empty > 0 {
+                fen.push_str(&empty.to_string());
+            }
+            if i > 0 {
+                fen.push('/');
+            }
+        }
+        let player = match self.get_player_on_move() {
+            ChessPlayer::White => 'w',
+            ChessPlayer::Black => 'b',
+        };
+        format!(
+            "{} {} - - {} {}",
+            fen, player, halfmove_clock, fullmove_number
+        )
+    }
+
+    pub fn to_fen(&self) -> String {
+        self.to_fen_with_clocks(0, 1)
+    }
+
+    // Reads piece placement and side to move, castling and en passant fields
+    // are ignored and the clocks are left to the caller
+    pub fn from_fen(fen: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        if fields.len() < 2 {
+            return Err(format!("{}: missing side to move", fen));
+        }
+        let player_on_move = match fields[1] {
+            "w" => ChessPlayer::White,
+            "b" => ChessPlayer::Black,
+            other => return Err(format!("invalid side to move {}", other)),
+        };
+
+        let mut pos = Position::empty(player_on_move);
+        let ranks: Vec<&str> = fields[0].split('/').collect();
+        if ranks.len() != 8 {
+            return Err(format!("{}: expected 8 ranks", fields[0]));
+        }
+        for (rank, i) in ranks.iter().zip((0..8).rev()) {
+            let mut j = 0;
+            for c in rank.chars() {
+                if let Some(empty) = c.to_digit(10) {
+                    j += empty as usize;
+                    continue;
+                }
+                let piece = match c.to_ascii_lowercase() {
+                    'p' => ChessPiece::Pawn,
+                    'r' => ChessPiece::Rook,
+                    'n' => ChessPiece::Knight,
+                    'b' => ChessPiece::Bishop,
+                    'q' => ChessPiece::Queen,
+                    'k' => ChessPiece::King,
+                    _ => return Err(format!("invalid piece {}", c)),
+                };
+                let player = if c.is_ascii_uppercase() {
+                    ChessPlayer::White
+                } else {
+                    ChessPlayer::Black
+                };
+                if j >= 8 {
+                    return Err(format!("{}: expected 8 files", rank));
+                }
+                pos.put_piece(player, piece, i, j);
+                j += 1;
+            }
+            if j != 8 {
+                return Err(format!("{}: expected 8 files", rank));
+            }
+        }
+        pos.validate()?;
+        Ok(pos)
+    }
+
+    // Checks what the rest of the code relies on: one king per side, no pawns
+    // on the back ranks and the player not on move not being in check
+    pub fn validate(&self) -> Result<(), String> {
+        for &player in [ChessPlayer::White, ChessPlayer::Black].iter() {
+            if self
+                .get_piece_type_by_player(ChessPiece::King, player)
+                .count_ones()
+                != 1
+            {
+                return Err(format!("{:?} must have exactly one king", player));
+            }
+        }
+        if self.pawn.get_value() & 0xff000000000000ff != 0 {
+            return Err("pawns can't stand on the first or the last rank".to_string());
+        }
+        if self.is_in_check(self.get_player_on_move().get_opponent()) {
+            return Err("player not on move is in check".to_string());
+        }
+        Ok(())
+    }
+
+    // Board without any pieces, fill it with put_piece
+    pub fn empty(player_on_move: ChessPlayer) -> Self {
+        let mut pos = Self {
+            white: Bitboard::new(0),
+            pawn: Bitboard::new(0),
+            rook: Bitboard::new(0),
+            knight: Bitboard::new(0),
+            bishop: Bitboard::new(0),
+            queen: Bitboard::new(0),
+            king: Bitboard::new(0),
+            other: 0,
+        };
+        pos.set_player_on_move(player_on_move);
+        pos
+    }
+
+    pub fn set_player_on_move(&mut self, player: ChessPlayer) {
+        if self.get_player_on_move() != player {
+            self.change_player_on_move();
+        }
+    }
+
+    // Replaces whatever stands on [i, j]
+    pub fn put_piece(&mut self, player: ChessPlayer, piece: ChessPiece, i: usize, j: usize) {
+        self.remove_piece(i, j);
+        if player == ChessPlayer::White {
+            self.white.set(i, j);
+        }
+        let helper = &mut [
+            &mut self.pawn,
+            &mut self.rook,
+            &mut self.knight,
+            &mut self.bishop,
+            &mut self.queen,
+            &mut self.king,
+        ];
+        helper[num::ToPrimitive::to_usize(&piece).unwrap()].set(i, j);
+    }
+
+    pub fn remove_piece(&mut self, i: usize, j: usize) {
+        for bitboard in [
+            &mut self.white,
+            &mut self.pawn,
+            &mut self.rook,
+            &mut self.knight,
+            &mut self.bishop,
+            &mut self.queen,
+            &mut self.king,
+        ] {
+            bitboard.clear(i, j);
+        }
+    }
+
     pub fn is_in_check(&self, player: ChessPlayer) -> bool {
         let attacked_by_opponent = self.get_attacked_positions(player.get_opponent());
         let resulting =
@@ -391,7 +686,37 @@ impl Position {
             && (self.get_valid_moves().len() == 0)
     }
 
-    pub fn get_valid_moves(&self) -> Vec<(usize, usize, usize, usize)> {
+    pub fn get_valid_moves(&self) -> Vec<Move> {
+        self.get_valid_moves_where(|_| true)
+    }
+
+    // Valid captures and promotions
+    pub fn get_valid_tactical_moves(&self) -> Vec<Move> {
+        self.get_valid_moves_where(|m| self.is_capture(m) || self.is_promotion(m))
+    }
+
+    // Valid moves that neither capture nor promote
+    pub fn get_valid_quiet_moves(&self) -> Vec<Move> {
+        self.get_valid_moves_where(|m| !self.is_capture(m) && !self.is_promotion(m))
+    }
+
+    // Same as get_valid_moves().contains(&m) without generating the moves
+    pub fn is_move_valid(&self, m: Move) -> bool {
+        let (i, j, k, l) = m;
+        if i >= 8 || j >= 8 || k >= 8 || l >= 8 {
+            return false;
+        }
+        if self.get_player_on_position(i, j) != Some(self.get_player_on_move()) {
+            return false;
+        }
+        let piece_type = self.get_piece_on_position(i, j).unwrap();
+        let mut next = *self;
+        self.is_valid_move(piece_type, i, j, k, l) && next.make_move(i, j, k, l)
+    }
+
+    // Valid moves for which keep holds, checked before the costlier test
+    // whether the move leaves the king in check
+    fn get_valid_moves_where(&self, keep: impl Fn(Move) -> bool) -> Vec<Move> {
         let mut valid_moves = Vec::new();
         let player_on_move = self.get_player_on_move();
         let curr_player_pieces = self.get_pieces_of_player(player_on_move);
@@ -415,7 +740,9 @@ impl Position {
 
             for semi_valid_move in valid_moves_mask.get_ones().iter() {
                 let (k, l) = (semi_valid_move / 8, semi_valid_move % 8);
-                if self.is_valid_move(piece_type, i, j, k, l) && self.clone().make_move(i, j, k, l)
+                if keep((i, j, k, l))
+                    && self.is_valid_move(piece_type, i, j, k, l)
+                    && self.clone().make_move(i, j, k, l)
                 {
                     valid_moves.push((i, j, k, l));
                 }
@@ -423,6 +750,103 @@ impl Position {
         }
         valid_moves
     }
+
+    pub fn is_capture(&self, m: Move) -> bool {
+        self.get_taken_bitboard().is_set(m.2, m.3)
+    }
+
+    pub fn is_promotion(&self, m: Move) -> bool {
+        self.pawn.is_set(m.0, m.1) && (m.2 == 0 || m.2 == 7)
+    }
+
+    // Returns pieces of both players standing on occupied squares that attack
+    // square, sliding pieces are blocked by pieces in occupied
+    fn get_attackers_to(&self, square: usize, occupied: Bitboard) -> Bitboard {
+        let (i, j) = (square / 8, square % 8);
+
+        let mut white_pawn_sources = Bitboard::default();
+        let mut black_pawn_sources = Bitboard::default();
+        for nj in [j.wrapping_sub(1), j + 1].iter().filter(|&&nj| nj < 8) {
+            if i > 0 {
+                white_pawn_sources.set(i - 1, *nj);
+            }
+            if i < 7 {
+                black_pawn_sources.set(i + 1, *nj);
+            }
+        }
+
+        let rook_attacks = *ROOK_MAP
+            .get(&(square, occupied & ROOK_BLOCKER_MASKS[square]))
+            .unwrap();
+        let bishop_attacks = *BISHOP_MAP
+            .get(&(square, occupied & BISHOP_BLOCKER_MASKS[square]))
+            .unwrap();
+
+        let attackers = (white_pawn_sources & self.pawn & self.get_white_pieces())
+            | (black_pawn_sources & self.pawn & self.get_black_pieces())
+            | (KNIGHT_POSSIBLE_MOVES[square] & self.knight)
+            | (KING_POSSIBLE_MOVES[square] & self.king)
+            | (rook_attacks & (self.rook | self.queen))
+            | (bishop_attacks & (self.bishop | self.queen));
+        attackers & occupied
+    }
+
+    // Static exchange evaluation, returns material balance in centipawns for
+    // the player on move after all profitable captures on the target square of
+    // m are played out, including x-ray attackers behind the first ones
+    pub fn see(&self, m: Move) -> i32 {
+        let (i, j, k, l) = m;
+        let target = k * 8 + l;
+        let mut gain = [0i32; 32];
+        let mut depth = 0;
+
+        let mut attacker = self.get_piece_on_position(i, j).unwrap();
+        gain[0] = match self.get_piece_on_position(k, l) {
+            Some(piece) => piece.get_value(),
+            None => 0,
+        };
+        if self.is_promotion(m) {
+            gain[0] += ChessPiece::Queen.get_value() - ChessPiece::Pawn.get_value();
+            attacker = ChessPiece::Queen;
+        }
+
+        let mut side = self.get_player_on_move();
+        let mut occupied = self.get_taken_bitboard();
+        let mut from = Bitboard::new(1 << (i * 8 + j));
+
+        loop {
+            depth += 1;
+            gain[depth] = attacker.get_value() - gain[depth - 1];
+            if std::cmp::max(-gain[depth - 1], gain[depth]) < 0 || depth == gain.len() - 1 {
+                break;
+            }
+
+            // removing the attacker may uncover sliding pieces behind it
+            occupied = occupied ^ from;
+            side = side.get_opponent();
+
+            let own_attackers =
+                self.get_attackers_to(target, occupied) & self.get_pieces_of_player(side);
+            let next = SEE_ATTACKER_ORDER
+                .iter()
+                .map(|&piece| (piece, own_attackers & self.get_piece_mask(piece)))
+                .find(|(_, mask)| *mask != Bitboard::new(0));
+
+            match next {
+                Some((piece, mask)) => {
+                    attacker = piece;
+                    from = Bitboard::new(1 << mask.trailing_zeros());
+                }
+                None => break,
+            }
+        }
+
+        while depth > 1 {
+            depth -= 1;
+            gain[depth - 1] = -std::cmp::max(-gain[depth - 1], gain[depth]);
+        }
+        gain[0]
+    }
 }
 
 impl Default for Position {
@@ -521,3 +945,108 @@ impl Distribution<Position> for Standard {
         pos
     }
 }
+
+// Position after a given number of random moves from the start, or fewer if
+// the game ends on the way
+pub struct RandomOpening {
+    pub plies: usize,
+}
+
+impl Distribution<Position> for RandomOpening {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Position {
+        let mut pos = Position::default();
+        for _ in 0..self.plies {
+            let valid_moves = pos.get_valid_moves();
+            if valid_moves.is_empty() {
+                break;
+            }
+            let (i, j, k, l) = valid_moves[rng.gen_range(0..valid_moves.len())];
+            pos.make_move(i, j, k, l);
+        }
+        pos
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn see_of(fen: &str, m: Move) -> i32 {
+        let pos = Position::from_fen(fen).unwrap();
+        assert!(pos.get_valid_moves().contains(&m));
+        pos.see(m)
+    }
+
+    #[test]
+    fn see_undefended_pawn() {
+        // Re1xe5
+        let fen = "1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1";
+        assert_eq!(see_of(fen, (0, 4, 4, 4)), 100);
+    }
+
+    #[test]
+    fn see_losing_knight_for_pawn() {
+        // Nd3xe5, after Nd7xe5 white is better off stopping
+        let fen = "1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1";
+        assert_eq!(see_of(fen, (2, 3, 4, 4)), 100 - 320);
+    }
+
+    #[test]
+    fn see_pawn_takes_defended_knight() {
+        // d4xe5
+        let fen = "4k3/3p4/8/4n3/3P4/8/8/4K3 w - - 0 1";
+        assert_eq!(see_of(fen, (3, 3, 4, 4)), 320);
+    }
+
+    #[test]
+    fn see_queen_takes_defended_pawn() {
+        // Qd1xd6 is answered by c7xd6
+        let fen = "4k3/2p5/3p4/8/8/8/8/3QK3 w - - 0 1";
+        assert_eq!(see_of(fen, (0, 3, 5, 3)), 100 - 900);
+    }
+
+    #[test]
+    fn see_quiet_move() {
+        assert_eq!(see_of(&Position::default().to_fen(), (1, 4, 3, 4)), 0);
+    }
+
+    #[test]
+    fn see_xray_rook_behind_rook() {
+        // Re2xe7 Re8xe7 Re1xe7, the second rook only attacks through the first
+        let fen = "k3r3/4r3/8/8/8/8/4R3/K3R3 w - - 0 1";
+        assert_eq!(see_of(fen, (1, 4, 6, 4)), 500);
+    }
+
+    #[test]
+    fn pawn_promotes_to_the_chosen_piece() {
+        let pos = Position::from_fen("8/4P1k1/8/8/8/8/8/K7 w - - 0 1").unwrap();
+        for &piece in PROMOTION_PIECES.iter() {
+            let mut next = pos;
+            assert!(next.make_move_promoting(6, 4, 7, 4, piece));
+            assert_eq!(next.get_piece_on_position(7, 4), Some(piece));
+            assert_eq!(next.get_player_on_position(7, 4), Some(ChessPlayer::White));
+            assert_eq!(next.get_piece_mask(ChessPiece::Pawn).count_ones(), 0);
+        }
+        let mut next = pos;
+        next.make_move(6, 4, 7, 4);
+        assert_eq!(next.get_piece_on_position(7, 4), Some(ChessPiece::Queen));
+    }
+
+    #[test]
+    fn pawns_on_the_edge_do_not_capture_across_the_board() {
+        // a pawn on h2 must not take the knight on a4, a pawn on a7 the one on h5
+        let pos = Position::from_fen("4k3/p7/8/7n/n7/8/7P/4K3 w - - 0 1").unwrap();
+        assert!(!pos.get_valid_moves().contains(&(1, 7, 3, 0)));
+        assert!(!pos.is_in_check(ChessPlayer::White));
+        let pos = Position::from_fen("4k3/p7/8/7N/N7/8/7P/4K3 b - - 0 1").unwrap();
+        assert!(!pos.get_valid_moves().contains(&(6, 0, 4, 7)));
+    }
+
+    #[test]
+    fn blocked_pawn_does_not_jump() {
+        let pos = Position::from_fen("4k3/8/8/8/8/4n3/4P3/4K3 w - - 0 1").unwrap();
+        assert!(!pos.get_valid_moves().contains(&(1, 4, 3, 4)));
+        let pos = Position::from_fen("4k3/4p3/4N3/8/8/8/8/4K3 b - - 0 1").unwrap();
+        assert!(!pos.get_valid_moves().contains(&(6, 4, 4, 4)));
+    }
+}
@@ -0,0 +1,537 @@
+// Distance to mate tablebases for small pawnless endgames, generated by
+// retrograde analysis.
+//
+// Table file layout:
+//   4 bytes   magic "RDTM"
+//   1 byte    format version (1)
+//   1 byte    length of the material signature
+//   n bytes   material signature, e.g. "KQvKR" (white before v)
+//   rest      one byte per position index, 0 for draws and unused indices,
+//             otherwise plies to mate + 1. Even plies mean the side on move
+//             gets mated, odd plies mean it mates.
+//
+// Position index, most significant first: side on move (white 0, black 1),
+// white king on one of the 10 squares of the a1-d1-d4 triangle, then the
+// squares of the black king, the other white pieces and the other black
+// pieces. Board symmetries bring the white king into the triangle.
+
+use super::chess_piece::ChessPiece;
+use super::chess_player::ChessPlayer;
+use super::position::Position;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+const MAGIC: &[u8; 4] = b"RDTM";
+const VERSION: u8 = 1;
+const MAX_PIECES: usize = 4;
+const MAX_PLIES: usize = 253;
+
+// counter value of positions that cannot occur
+const ILLEGAL: u8 = u8::MAX;
+
+const TRIANGLE: [usize; 10] = [0, 1, 2, 3, 9, 10, 11, 18, 19, 27];
+
+const SIGNATURE_PIECES: [(char, ChessPiece); 5] = [
+    ('K', ChessPiece::King),
+    ('Q', ChessPiece::Queen),
+    ('R', ChessPiece::Rook),
+    ('B', ChessPiece::Bishop),
+    ('N', ChessPiece::Knight),
+];
+
+// Result for the player on move, distances are in plies
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Dtm {
+    Win(u32),
+    Loss(u32),
+    Draw,
+}
+
+impl Dtm {
+    fn from_value(value: u8) -> Self {
+        match value {
+            0 => Dtm::Draw,
+            v if (v - 1) % 2 == 0 => Dtm::Loss(v as u32 - 1),
+            v => Dtm::Win(v as u32 - 1),
+        }
+    }
+}
+
+type PieceList = Vec<(ChessPlayer, ChessPiece)>;
+
+// Pieces in index order: white king, black king, other white pieces, other
+// black pieces
+fn parse_signature(signature: &str) -> Result<PieceList, String> {
+    let sides: Vec<&str> = signature.split('v').collect();
+    if sides.len() != 2 {
+        return Err(format!("{}: expected material like KRvK", signature));
+    }
+
+    let mut kings = Vec::new();
+    let mut others = Vec::new();
+    for (&side, &player) in sides
+        .iter()
+        .zip([ChessPlayer::White, ChessPlayer::Black].iter())
+    {
+        let mut pieces = Vec::new();
+        for c in side.chars() {
+            match SIGNATURE_PIECES.iter().find(|&&(letter, _)| letter == c) {
+                Some(&(_, piece)) => pieces.push(piece),
+                None if c == 'P' => return Err(format!("{}: pawns are not supported", signature)),
+                None => return Err(format!("{}: unknown piece {}", signature, c)),
+            }
+        }
+        if pieces
+            .iter()
+            .filter(|&&piece| piece == ChessPiece::King)
+            .count()
+            != 1
+        {
+            return Err(format!("{}: each side needs exactly one king", signature));
+        }
+        pieces.retain(|&piece| piece != ChessPiece::King);
+        pieces.sort_by_key(|&piece| {
+            SIGNATURE_PIECES
+                .iter()
+                .position(|&(_, p)| p == piece)
+                .unwrap()
+        });
+        kings.push((player, ChessPiece::King));
+        others.extend(pieces.into_iter().map(|piece| (player, piece)));
+    }
+
+    kings.extend(others);
+    if kings.len() > MAX_PIECES {
+        return Err(format!("{}: at most {} pieces", signature, MAX_PIECES));
+    }
+    Ok(kings)
+}
+
+fn get_signature(pieces: &[(ChessPlayer, ChessPiece)]) -> String {
+    let mut res = String::new();
+    for &player in [ChessPlayer::White, ChessPlayer::Black].iter() {
+        if player == ChessPlayer::Black {
+            res.push('v');
+        }
+        for &(letter, piece) in SIGNATURE_PIECES.iter() {
+            let count = pieces.iter().filter(|&&p| p == (player, piece)).count();
+            res.extend(std::iter::repeat_n(letter, count));
+        }
+    }
+    res
+}
+
+fn get_table_size(piece_count: usize) -> usize {
+    2 * TRIANGLE.len() * 64usize.pow(piece_count as u32 - 1)
+}
+
+fn transform(square: usize, flip_file: bool, flip_rank: bool, swap: bool) -> usize {
+    let mut i = square / 8;
+    let mut j = square % 8;
+    if flip_file {
+        j = 7 - j;
+    }
+    if flip_rank {
+        i = 7 - i;
+    }
+    if swap {
+        std::mem::swap(&mut i, &mut j);
+    }
+    i * 8 + j
+}
+
+// Same pieces are interchangeable, keep their squares sorted
+fn sort_same_pieces(pieces: &[(ChessPlayer, ChessPiece)], squares: &mut [usize]) {
+    let mut start = 0;
+    while start < pieces.len() {
+        let mut end = start + 1;
+        while end < pieces.len() && pieces[end] == pieces[start] {
+            end += 1;
+        }
+        squares[start..end].sort_unstable();
+        start = end;
+    }
+}
+
+// Index of the position class, symmetric positions share one index
+fn get_index(
+    pieces: &[(ChessPlayer, ChessPiece)],
+    player_on_move: ChessPlayer,
+    squares: &[usize],
+) -> usize {
+    let king = squares[0];
+    let flip_file = king % 8 > 3;
+    let flip_rank = king / 8 > 3;
+    let king = transform(king, flip_file, flip_rank, false);
+    let swap = king / 8 > king % 8;
+
+    let mut canonical: Vec<usize> = squares
+        .iter()
+        .map(|&square| transform(square, flip_file, flip_rank, swap))
+        .collect();
+    sort_same_pieces(pieces, &mut canonical);
+    if canonical[0] / 8 == canonical[0] % 8 {
+        // king on the diagonal, mirroring along it keeps it in the triangle
+        let mut mirrored: Vec<usize> = canonical
+            .iter()
+            .map(|&square| transform(square, false, false, true))
+            .collect();
+        sort_same_pieces(pieces, &mut mirrored);
+        if mirrored < canonical {
+            canonical = mirrored;
+        }
+    }
+
+    let side = match player_on_move {
+        ChessPlayer::White => 0,
+        ChessPlayer::Black => 1,
+    };
+    let king_index = TRIANGLE.iter().position(|&s| s == canonical[0]).unwrap();
+    canonical[1..]
+        .iter()
+        .fold(side * TRIANGLE.len() + king_index, |index, &square| {
+            index * 64 + square
+        })
+}
+
+fn get_squares_from_index(mut index: usize, piece_count: usize) -> (ChessPlayer, Vec<usize>) {
+    let mut squares = vec![0; piece_count];
+    for square in squares[1..].iter_mut().rev() {
+        *square = index % 64;
+        index /= 64;
+    }
+    squares[0] = TRIANGLE[index % TRIANGLE.len()];
+    let player = match index / TRIANGLE.len() {
+        0 => ChessPlayer::White,
+        _ => ChessPlayer::Black,
+    };
+    (player, squares)
+}
+
+fn get_squares_from_position(pos: &Position, pieces: &[(ChessPlayer, ChessPiece)]) -> Vec<usize> {
+    let mut squares = Vec::with_capacity(pieces.len());
+    let mut start = 0;
+    while start < pieces.len() {
+        let (player, piece) = pieces[start];
+        let ones = pos.get_piece_type_by_player(piece, player).get_ones();
+        squares.extend(ones.iter());
+        start += ones.len();
+    }
+    squares
+}
+
+fn get_position(
+    pieces: &[(ChessPlayer, ChessPiece)],
+    player_on_move: ChessPlayer,
+    squares: &[usize],
+) -> Position {
+    let mut pos = Position::empty(player_on_move);
+    for (&(player, piece), &square) in pieces.iter().zip(squares.iter()) {
+        pos.put_piece(player, piece, square / 8, square % 8);
+    }
+    pos
+}
+
+pub struct DtmTable {
+    signature: String,
+    pieces: PieceList,
+    data: Vec<u8>,
+}
+
+impl DtmTable {
+    // Generates the table for material like "KQvKR" together with all the
+    // tables reachable by captures
+    pub fn generate(signature: &str) -> Result<Self, String> {
+        let signature = get_signature(&parse_signature(signature)?);
+        let mut tables = HashMap::new();
+        generate_with_subtables(&signature, &mut tables)?;
+        Ok(tables.remove(&signature).unwrap())
+    }
+
+    pub fn open(path: &Path) -> io::Result<Self> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message);
+        if bytes.len() < 6 || &bytes[0..4] != MAGIC {
+            return Err(invalid("not a DTM table"));
+        }
+        if bytes[4] != VERSION {
+            return Err(invalid("unsupported DTM table version"));
+        }
+        let end = 6 + bytes[5] as usize;
+        let signature = bytes
+            .get(6..end)
+            .and_then(|s| std::str::from_utf8(s).ok())
+            .ok_or_else(|| invalid("broken material signature"))?;
+        let pieces = parse_signature(signature).map_err(|e| invalid(&e))?;
+        let data = bytes[end..].to_vec();
+        if data.len() != get_table_size(pieces.len()) {
+            return Err(invalid("wrong DTM table size"));
+        }
+        Ok(Self {
+            signature: signature.to_string(),
+            pieces,
+            data,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(6 + self.signature.len() + self.data.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.push(self.signature.len() as u8);
+        bytes.extend_from_slice(self.signature.as_bytes());
+        bytes.extend_from_slice(&self.data);
+        bytes
+    }
+
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.to_bytes())
+    }
+
+    pub fn get_signature(&self) -> &str {
+        &self.signature
+    }
+
+    // Returns None when the material of pos differs from the table
+    pub fn probe(&self, pos: &Position) -> Option<Dtm> {
+        self.probe_value(pos).map(Dtm::from_value)
+    }
+
+    fn probe_value(&self, pos: &Position) -> Option<u8> {
+        let signature = format!(
+            "{}v{}",
+            pos.get_material_signature(ChessPlayer::White),
+            pos.get_material_signature(ChessPlayer::Black)
+        );
+        if signature != self.signature {
+            return None;
+        }
+        let squares = get_squares_from_position(pos, &self.pieces);
+        let index = get_index(&self.pieces, pos.get_player_on_move(), &squares);
+        Some(self.data[index])
+    }
+
+    // Number of positions of each outcome and the longest mate in plies
+    pub fn get_statistics(&self) -> (usize, usize, usize, u32) {
+        let (mut wins, mut losses, mut draws, mut longest) = (0, 0, 0, 0);
+        for &value in self.data.iter() {
+            match Dtm::from_value(value) {
+                Dtm::Win(plies) => {
+                    wins += 1;
+                    longest = std::cmp::max(longest, plies);
+                }
+                Dtm::Loss(plies) => {
+                    losses += 1;
+                    longest = std::cmp::max(longest, plies);
+                }
+                Dtm::Draw => draws += 1,
+            }
+        }
+        (wins, losses, draws, longest)
+    }
+}
+
+fn generate_with_subtables(
+    signature: &str,
+    tables: &mut HashMap<String, DtmTable>,
+) -> Result<(), String> {
+    if tables.contains_key(signature) {
+        return Ok(());
+    }
+    let pieces = parse_signature(signature)?;
+    for captured in 2..pieces.len() {
+        let mut rest = pieces.clone();
+        rest.remove(captured);
+        generate_with_subtables(&get_signature(&rest), tables)?;
+    }
+    let table = generate_table(signature, pieces, tables);
+    tables.insert(signature.to_string(), table);
+    Ok(())
+}
+
+// Captures are resolved right away from the smaller tables. Everything else
+// is solved backwards from the mates: a predecessor of a lost position is won,
+// a position is lost once every quiet successor is known to be won for the
+// opponent and no capture saves it. Counters count distinct successor
+// indices, so symmetric positions are not counted twice.
+fn generate_table(
+    signature: &str,
+    pieces: PieceList,
+    tables: &HashMap<String, DtmTable>,
+) -> DtmTable {
+    let size = get_table_size(pieces.len());
+    // plies to mate + 1, 0 while unresolved
+    let mut values = vec![0u8; size];
+    // quiet successors not yet known to be won by the opponent
+    let mut counters = vec![ILLEGAL; size];
+    // some capture wins or draws
+    let mut escapes = vec![false; size];
+    // slowest loss by a capture in plies
+    let mut capture_losses = vec![0u8; size];
+    let mut max_value = 0;
+
+    for index in 0..size {
+        let (player_on_move, squares) = get_squares_from_index(index, pieces.len());
+        let mut occupied = 0u64;
+        if squares.iter().any(|&s| {
+            let taken = occupied & (1 << s) != 0;
+            occupied |= 1 << s;
+            taken
+        }) {
+            continue;
+        }
+        if get_index(&pieces, player_on_move, &squares) != index {
+            continue;
+        }
+        let pos = get_position(&pieces, player_on_move, &squares);
+        if pos.is_in_check(player_on_move.get_opponent()) {
+            continue;
+        }
+
+        let mut successors = Vec::new();
+        let mut best_capture_win = None;
+        for m in pos.get_valid_moves() {
+            let mut next = pos;
+            next.make_move(m.0, m.1, m.2, m.3);
+            if !pos.is_capture(m) {
+                let next_squares = get_squares_from_position(&next, &pieces);
+                successors.push(get_index(&pieces, next.get_player_on_move(), &next_squares));
+                continue;
+            }
+            let rest = format!(
+                "{}v{}",
+                next.get_material_signature(ChessPlayer::White),
+                next.get_material_signature(ChessPlayer::Black)
+            );
+            match tables[&rest].probe_value(&next).unwrap() {
+                0 => escapes[index] = true,
+                // the capture itself adds one ply to the opponent's distance
+                plies if plies % 2 == 1 => {
+                    // opponent gets mated
+                    escapes[index] = true;
+                    best_capture_win =
+                        Some(std::cmp::min(best_capture_win.unwrap_or(u8::MAX), plies));
+                }
+                plies => capture_losses[index] = std::cmp::max(capture_losses[index], plies),
+            }
+        }
+        successors.sort_unstable();
+        successors.dedup();
+        counters[index] = successors.len() as u8;
+
+        if let Some(plies) = best_capture_win {
+            values[index] = plies + 1;
+        } else if successors.is_empty() && !escapes[index] {
+            // every move is a losing capture, or there is no move at all and
+            // the player is mated or stalemated
+            values[index] = if capture_losses[index] > 0 {
+                capture_losses[index] + 1
+            } else if pos.is_in_check(player_on_move) {
+                1
+            } else {
+                0
+            };
+        }
+        max_value = std::cmp::max(max_value, values[index]);
+    }
+
+    let mut plies = 0;
+    while plies < MAX_PLIES && plies < max_value as usize {
+        for index in 0..size {
+            if values[index] as usize != plies + 1 || counters[index] == ILLEGAL {
+                continue;
+            }
+            let (player_on_move, squares) = get_squares_from_index(index, pieces.len());
+            let pos = get_position(&pieces, player_on_move, &squares);
+            let mover = player_on_move.get_opponent();
+            let free = !pos.get_taken_bitboard();
+
+            let mut predecessors = Vec::new();
+            for (k, &(player, _)) in pieces.iter().enumerate() {
+                if player != mover {
+                    continue;
+                }
+                let from = squares[k];
+                let targets = pos.get_piece_moves(from / 8, from % 8) & free;
+                for to in targets.get_ones() {
+                    let mut previous_squares = squares.clone();
+                    previous_squares[k] = to;
+                    let previous = get_position(&pieces, mover, &previous_squares);
+                    if previous.is_in_check(player_on_move) {
+                        continue;
+                    }
+                    predecessors.push(get_index(&pieces, mover, &previous_squares));
+                }
+            }
+            predecessors.sort_unstable();
+            predecessors.dedup();
+
+            for previous in predecessors {
+                let value = if plies % 2 == 0 {
+                    // position is lost, moving into it wins
+                    if values[previous] != 0 && values[previous] as usize <= plies + 2 {
+                        continue;
+                    }
+                    plies as u8 + 2
+                } else {
+                    // position is won, the predecessor loses if all moves do
+                    if values[previous] != 0 {
+                        continue;
+                    }
+                    counters[previous] -= 1;
+                    if counters[previous] != 0 || escapes[previous] {
+                        continue;
+                    }
+                    std::cmp::max(plies as u8 + 1, capture_losses[previous]) + 1
+                };
+                values[previous] = value;
+                max_value = std::cmp::max(max_value, value);
+            }
+        }
+        plies += 1;
+    }
+
+    DtmTable {
+        signature: signature.to_string(),
+        pieces,
+        data: values,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_longest_mate(signature: &str) -> u32 {
+        DtmTable::generate(signature).unwrap().get_statistics().3
+    }
+
+    #[test]
+    fn longest_mates_with_three_pieces() {
+        assert_eq!(get_longest_mate("KQvK"), 20);
+        assert_eq!(get_longest_mate("KRvK"), 32);
+    }
+
+    // four piece tables take minutes to generate without optimizations, run
+    // with cargo test --release -- --ignored
+    #[test]
+    #[ignore = "slow"]
+    fn longest_mate_of_bishop_and_knight() {
+        assert_eq!(get_longest_mate("KBNvK"), 66);
+    }
+
+    #[test]
+    #[ignore = "slow"]
+    fn capture_out_of_check_is_not_mate() {
+        let table = DtmTable::generate("KRRvK").unwrap();
+        let probe = |fen: &str| table.probe(&Position::from_fen(fen).unwrap());
+        // Kxg8 is forced and leads to a KRvK position lost in 17 plies
+        assert_eq!(probe("6Rk/R7/8/8/8/8/8/K7 b - - 0 1"), Some(Dtm::Loss(18)));
+        assert_eq!(probe("6Rk/6R1/8/8/8/8/8/K7 b - - 0 1"), Some(Dtm::Loss(0)));
+    }
+}
@@ -0,0 +1,915 @@
+use super::chess_piece::ChessPiece;
+use super::endgame::probe_endgame;
+use super::evaluation::evaluate;
+use super::move_picker::{MoveOrdering, MovePicker, MAX_PLY};
+use super::nnue::{Network, NnueState};
+use super::position::{Move, Position};
+use super::tablebase::{Tablebase, Wdl};
+use super::time_manager::TimeManager;
+use super::transposition_table::{Bound, TranspositionTable, TtEntry};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub type Score = i32;
+
+pub const INFINITY: Score = 32000;
+pub const MATE_SCORE: Score = 31000;
+// scores above this are mates found within MAX_PLY
+pub const MATE_BOUND: Score = MATE_SCORE - MAX_PLY as Score;
+// iterative deepening limit of searches bounded only by time
+pub const MAX_DEPTH: u32 = 64;
+// tablebase wins are scored below every mate
+pub const TB_WIN_SCORE: Score = MATE_BOUND - MAX_PLY as Score;
+
+const FUTILITY_MARGIN: [Score; 4] = [0, 200, 300, 500];
+const REVERSE_FUTILITY_MARGIN: Score = 120;
+const RAZOR_MARGIN: [Score; 3] = [0, 300, 550];
+const ASPIRATION_WINDOW: Score = 50;
+
+// Selectivity techniques of the search, each can be turned off separately to
+// measure what it is worth
+#[derive(Debug, Copy, Clone)]
+pub struct SearchOptions {
+    pub null_move: bool,
+    pub late_move_reductions: bool,
+    pub futility: bool,
+    pub reverse_futility: bool,
+    pub razoring: bool,
+    pub check_extensions: bool,
+    pub aspiration_windows: bool,
+    // reduction for depth d and move number n is lmr_base + ln(d) * ln(n) / lmr_divisor
+    pub lmr_base: f64,
+    pub lmr_divisor: f64,
+    // number of threads searching the root in parallel, sharing one
+    // transposition table
+    pub threads: usize,
+    pub hash_size_mb: usize,
+    // number of best root moves searched with their own principal variation
+    pub multi_pv: usize,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            null_move: true,
+            late_move_reductions: true,
+            futility: true,
+            reverse_futility: true,
+            razoring: true,
+            check_extensions: true,
+            aspiration_windows: true,
+            lmr_base: 0.75,
+            lmr_divisor: 2.25,
+            threads: 1,
+            hash_size_mb: 16,
+            multi_pv: 1,
+        }
+    }
+}
+
+// Root move, its score and the principal variation starting with it
+pub type PvLine = (Move, Score, Vec<Move>);
+
+// Progress report sent after every principal variation of every iteration
+#[derive(Debug, Clone)]
+pub struct SearchInfo {
+    pub depth: u32,
+    // 1 for the best line
+    pub multi_pv: usize,
+    pub score: Score,
+    pub nodes: u64,
+    pub time: Duration,
+    pub pv: Vec<Move>,
+}
+
+pub type InfoCallback = Box<dyn FnMut(&SearchInfo) + Send>;
+
+pub struct Searcher {
+    pub nodes: u64,
+    options: SearchOptions,
+    ordering: MoveOrdering,
+    // indexed by [depth][move number]
+    reductions: Box<[[i32; 64]; 64]>,
+    tt: Arc<TranspositionTable>,
+    stop: Arc<AtomicBool>,
+    time_manager: Option<TimeManager>,
+    // search stops once nodes reaches it
+    node_limit: Option<u64>,
+    // indexed by [ply], principal variation found from that ply
+    pv_table: Vec<Vec<Move>>,
+    info_callback: Option<InfoCallback>,
+    tablebase: Option<Arc<Tablebase>>,
+    // root moves allowed by the tablebases, all moves if None
+    root_moves: Option<Vec<Move>>,
+    // indexed by [ply], plies since the last capture or pawn move, the
+    // first one is set by the caller
+    halfmove_clocks: Vec<u32>,
+    // network evaluation, the classical one is used if None
+    nnue: Option<NnueState>,
+}
+
+impl Searcher {
+    pub fn new() -> Self {
+        Self::with_options(SearchOptions::default())
+    }
+
+    pub fn with_options(options: SearchOptions) -> Self {
+        let tt = Arc::new(TranspositionTable::new(options.hash_size_mb));
+        Self::with_shared_state(options, tt, Arc::new(AtomicBool::new(false)))
+    }
+
+    fn with_shared_state(
+        options: SearchOptions,
+        tt: Arc<TranspositionTable>,
+        stop: Arc<AtomicBool>,
+    ) -> Self {
+        let mut reductions = Box::new([[0; 64]; 64]);
+        for (depth, row) in reductions.iter_mut().enumerate().skip(1) {
+            for (move_number, reduction) in row.iter_mut().enumerate().skip(1) {
+                let r = options.lmr_base
+                    + (depth as f64).ln() * (move_number as f64).ln() / options.lmr_divisor;
+                *reduction = r as i32;
+            }
+        }
+        Self {
+            nodes: 0,
+            options,
+            ordering: MoveOrdering::new(),
+            reductions,
+            tt,
+            stop,
+            time_manager: None,
+            node_limit: None,
+            pv_table: vec![Vec::new(); MAX_PLY + 1],
+            info_callback: None,
+            tablebase: None,
+            root_moves: None,
+            halfmove_clocks: vec![0],
+            nnue: None,
+        }
+    }
+
+    pub fn set_network(&mut self, network: Option<Arc<Network>>) {
+        self.nnue = network.map(NnueState::new);
+    }
+
+    pub fn set_tablebase(&mut self, tablebase: Option<Arc<Tablebase>>) {
+        self.tablebase = tablebase;
+    }
+
+    // Plies since the last capture or pawn move before the searched
+    // position, tablebases need it to tell wins from cursed wins
+    pub fn set_halfmove_clock(&mut self, halfmove_clock: u32) {
+        self.halfmove_clocks = vec![halfmove_clock];
+    }
+
+    pub fn set_info_callback(&mut self, callback: InfoCallback) {
+        self.info_callback = Some(callback);
+    }
+
+    // Flag that aborts a running search once set, the best move of the last
+    // completed iteration is returned
+    pub fn get_stop_handle(&self) -> Arc<AtomicBool> {
+        self.stop.clone()
+    }
+
+    fn is_stopped(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
+    }
+
+    // Forgets everything learned in earlier searches
+    pub fn clear(&mut self) {
+        self.tt.clear();
+        self.ordering.clear();
+    }
+
+    // Hard time limit is polled only every few nodes, reading the clock is
+    // not free. The node limit is checked always, so where a search stops
+    // does not depend on how many nodes earlier searches visited.
+    fn check_time(&self) {
+        if self.node_limit.is_some_and(|limit| self.nodes >= limit) {
+            self.stop.store(true, Ordering::Relaxed);
+        }
+        if !self.nodes.is_multiple_of(256) {
+            return;
+        }
+        if let Some(time_manager) = &self.time_manager {
+            if time_manager.is_hard_limit_reached() {
+                self.stop.store(true, Ordering::Relaxed);
+            }
+        }
+    }
+
+    // Searches pos for as long as time_manager allows, but not deeper than
+    // depth
+    pub fn search_timed(
+        &mut self,
+        pos: &Position,
+        depth: u32,
+        time_manager: TimeManager,
+    ) -> (Option<Move>, Score) {
+        self.time_manager = Some(time_manager);
+        let result = self.search(pos, depth);
+        self.time_manager = None;
+        result
+    }
+
+    // Searches pos until about nodes more nodes are visited, with a single
+    // thread the result does not depend on the speed of the machine
+    pub fn search_nodes(&mut self, pos: &Position, nodes: u64) -> (Option<Move>, Score) {
+        self.node_limit = Some(self.nodes + nodes);
+        let result = self.search(pos, MAX_DEPTH);
+        self.node_limit = None;
+        result
+    }
+
+    // Searches pos until about nodes more nodes are visited or time_manager
+    // runs out, whichever comes first
+    pub fn search_nodes_timed(
+        &mut self,
+        pos: &Position,
+        nodes: u64,
+        time_manager: TimeManager,
+    ) -> (Option<Move>, Score) {
+        self.time_manager = Some(time_manager);
+        let result = self.search_nodes(pos, nodes);
+        self.time_manager = None;
+        result
+    }
+
+    // Searches pos to the given depth, returns best move (None if there is no
+    // legal move) and its score from the point of view of the player on move
+    pub fn search(&mut self, pos: &Position, depth: u32) -> (Option<Move>, Score) {
+        match self.search_multi_pv(pos, depth).first() {
+            Some((m, score, _)) => (Some(*m), *score),
+            None => (None, self.get_terminal_score(pos, 0)),
+        }
+    }
+
+    // Searches pos to the given depth, returns up to options.multi_pv best
+    // root moves ordered by score, each with its principal variation
+    pub fn search_multi_pv(&mut self, pos: &Position, depth: u32) -> Vec<PvLine> {
+        // moves throwing away a tablebase win or draw are never searched
+        self.root_moves = self
+            .tablebase
+            .as_ref()
+            .and_then(|tablebase| tablebase.filter_root_moves(pos, self.halfmove_clocks[0]));
+        let lines = if self.options.threads <= 1 {
+            self.iterative_deepening(pos, depth)
+        } else {
+            self.search_parallel(pos, depth)
+        };
+        // cleared only afterwards, so a stop requested before the search
+        // started is not lost
+        self.stop.store(false, Ordering::Relaxed);
+        lines
+    }
+
+    fn search_parallel(&mut self, pos: &Position, depth: u32) -> Vec<PvLine> {
+        // Lazy SMP: helper threads search the same root and communicate only
+        // through the transposition table, every other one a ply deeper so
+        // the threads diverge. Only the result of this thread is reported.
+        let helper_options = SearchOptions {
+            multi_pv: 1,
+            ..self.options
+        };
+        let tt = self.tt.clone();
+        let stop = self.stop.clone();
+        let tablebase = self.tablebase.clone();
+        let root_moves = self.root_moves.clone();
+        let halfmove_clock = self.halfmove_clocks[0];
+        let network = self.nnue.as_ref().map(|nnue| nnue.get_network());
+        let (result, helper_nodes) = thread::scope(|scope| {
+            let helpers: Vec<_> = (1..helper_options.threads)
+                .map(|id| {
+                    let tt = tt.clone();
+                    let stop = stop.clone();
+                    let tablebase = tablebase.clone();
+                    let root_moves = root_moves.clone();
+                    let network = network.clone();
+                    scope.spawn(move || {
+                        let mut helper = Searcher::with_shared_state(helper_options, tt, stop);
+                        helper.tablebase = tablebase;
+                        helper.root_moves = root_moves;
+                        helper.set_halfmove_clock(halfmove_clock);
+                        helper.set_network(network);
+                        helper.iterative_deepening(pos, depth + (id % 2) as u32);
+                        helper.nodes
+                    })
+                })
+                .collect();
+
+            let result = self.iterative_deepening(pos, depth);
+            stop.store(true, Ordering::Relaxed);
+            let helper_nodes: u64 = helpers.into_iter().map(|h| h.join().unwrap()).sum();
+            (result, helper_nodes)
+        });
+        self.nodes += helper_nodes;
+        result
+    }
+
+    fn iterative_deepening(&mut self, pos: &Position, depth: u32) -> Vec<PvLine> {
+        let start = Instant::now();
+        let mut lines: Vec<PvLine> = Vec::new();
+        if let Some(nnue) = self.nnue.as_mut() {
+            nnue.refresh(pos);
+        }
+        // iterative deepening, best moves of the previous iteration are tried first
+        for current_depth in 1..=depth.max(1) as i32 {
+            if let Some(time_manager) = self.time_manager.as_mut() {
+                time_manager.on_iteration_start();
+            }
+            let mut current_lines: Vec<PvLine> = Vec::new();
+            for pv_index in 0..self.options.multi_pv.max(1) {
+                let excluded: Vec<Move> = current_lines.iter().map(|line| line.0).collect();
+                let previous = lines.get(pv_index).cloned();
+                let line = self.search_pv_line(pos, current_depth, previous, &excluded);
+                if self.is_stopped() {
+                    if !lines.is_empty() {
+                        return lines;
+                    }
+                    // an unfinished iteration is only better than nothing
+                    current_lines.extend(line);
+                    if current_lines.is_empty() {
+                        current_lines.extend(self.get_fallback_line(pos));
+                    }
+                    return current_lines;
+                }
+                let line = match line {
+                    Some(line) => line,
+                    None => break,
+                };
+
+                if let Some(callback) = self.info_callback.as_mut() {
+                    callback(&SearchInfo {
+                        depth: current_depth as u32,
+                        multi_pv: pv_index + 1,
+                        score: line.1,
+                        nodes: self.nodes,
+                        time: start.elapsed(),
+                        pv: line.2.clone(),
+                    });
+                }
+                current_lines.push(line);
+            }
+
+            // a later line may score better once its search is not cut off
+            current_lines.sort_by_key(|line| -line.1);
+            lines = current_lines;
+            if lines.is_empty() {
+                break;
+            }
+            if let Some(time_manager) = self.time_manager.as_mut() {
+                time_manager.on_iteration(Some(lines[0].0));
+                if !time_manager.can_start_iteration() {
+                    break;
+                }
+            }
+        }
+        lines
+    }
+
+    // Finds the best root move not in excluded, using an aspiration window
+    // around the score of the line with the same index in the last iteration
+    fn search_pv_line(
+        &mut self,
+        pos: &Position,
+        depth: i32,
+        previous: Option<PvLine>,
+        excluded: &[Move],
+    ) -> Option<PvLine> {
+        let mut delta = ASPIRATION_WINDOW;
+        let (mut alpha, mut beta) = (-INFINITY, INFINITY);
+        let previous_move = previous.as_ref().map(|line| line.0);
+        if let Some((_, score, _)) = previous {
+            if self.options.aspiration_windows && depth >= 4 {
+                alpha = (score - delta).max(-INFINITY);
+                beta = (score + delta).min(INFINITY);
+            }
+        }
+
+        loop {
+            let line = self.search_root(pos, depth, previous_move, alpha, beta, excluded);
+            let score = match &line {
+                Some((_, score, _)) => *score,
+                None => return None,
+            };
+            if self.is_stopped() {
+                return line;
+            }
+            if score <= alpha && alpha > -INFINITY {
+                alpha = (score - delta).max(-INFINITY);
+                if let Some(time_manager) = self.time_manager.as_mut() {
+                    time_manager.on_fail_low();
+                }
+            } else if score >= beta && beta < INFINITY {
+                beta = (score + delta).min(INFINITY);
+            } else {
+                return line;
+            }
+            delta *= 2;
+        }
+    }
+
+    fn search_root(
+        &mut self,
+        pos: &Position,
+        depth: i32,
+        best_move: Option<Move>,
+        mut alpha: Score,
+        beta: Score,
+        excluded: &[Move],
+    ) -> Option<PvLine> {
+        let mut best_line: Option<PvLine> = None;
+        let mut picker = MovePicker::new(pos, best_move, [None; 2], None);
+        while let Some(m) = picker.next(&self.ordering) {
+            let allowed = self
+                .root_moves
+                .as_ref()
+                .is_none_or(|moves| moves.contains(&m));
+            if excluded.contains(&m) || !allowed {
+                continue;
+            }
+            let next = self.make_move(pos, m);
+            let score = if best_line.is_none() {
+                -self.alpha_beta(&next, depth - 1, -beta, -alpha, 1, Some(m))
+            } else {
+                let score = -self.alpha_beta(&next, depth - 1, -alpha - 1, -alpha, 1, Some(m));
+                if score > alpha && score < beta {
+                    -self.alpha_beta(&next, depth - 1, -beta, -alpha, 1, Some(m))
+                } else {
+                    score
+                }
+            };
+            self.unmake_move();
+            // the score of an interrupted subtree means nothing
+            if self.is_stopped() {
+                break;
+            }
+
+            let is_best = match &best_line {
+                Some((_, best_score, _)) => score > *best_score,
+                None => true,
+            };
+            if is_best {
+                let mut pv = vec![m];
+                pv.extend(self.pv_table[1].iter());
+                best_line = Some((m, score, pv));
+            }
+            if score > alpha {
+                alpha = score;
+            }
+            if score >= beta {
+                break;
+            }
+        }
+        best_line
+    }
+
+    // Any allowed root move, for searches stopped before one was finished
+    fn get_fallback_line(&self, pos: &Position) -> Option<PvLine> {
+        let m = pos.get_valid_moves().into_iter().find(|m| {
+            self.root_moves
+                .as_ref()
+                .is_none_or(|moves| moves.contains(m))
+        })?;
+        Some((m, self.evaluate(pos), vec![m]))
+    }
+
+    fn get_reduction(&self, depth: i32, move_count: usize) -> i32 {
+        let reduction = self.reductions[(depth as usize).min(63)][move_count.min(63)];
+        reduction.min(depth - 2).max(0)
+    }
+
+    fn alpha_beta(
+        &mut self,
+        pos: &Position,
+        mut depth: i32,
+        mut alpha: Score,
+        beta: Score,
+        ply: usize,
+        prev_move: Option<Move>,
+    ) -> Score {
+        self.pv_table[ply.min(MAX_PLY)].clear();
+        let player = pos.get_player_on_move();
+        let in_check = pos.is_in_check(player);
+        if in_check && self.options.check_extensions {
+            depth += 1;
+        }
+        if depth <= 0 {
+            return self.quiescence(pos, alpha, beta, ply);
+        }
+        self.nodes += 1;
+        self.check_time();
+        if self.is_stopped() {
+            return 0;
+        }
+        if ply >= MAX_PLY {
+            return self.evaluate(pos);
+        }
+
+        let is_pv = beta - alpha > 1;
+        let key = pos.get_zobrist_key();
+        let tt_entry = self.tt.probe(key, ply);
+        if let Some(entry) = tt_entry {
+            let usable = match entry.bound {
+                Bound::Exact => true,
+                Bound::Lower => entry.score >= beta,
+                Bound::Upper => entry.score <= alpha,
+            };
+            if !is_pv && entry.depth >= depth && usable {
+                return entry.score;
+            }
+        }
+
+        // the probe ignores the 50-move rule, it is exact only right after
+        // a capture or pawn move
+        let wdl = self
+            .tablebase
+            .as_ref()
+            .filter(|_| self.get_halfmove_clock() == 0)
+            .and_then(|tablebase| tablebase.probe_wdl(pos));
+        if let Some(wdl) = wdl {
+            let score = match wdl {
+                Wdl::Win => TB_WIN_SCORE - ply as Score,
+                Wdl::Loss => -TB_WIN_SCORE + ply as Score,
+                _ => 0,
+            };
+            self.store(key, ply, None, score, MAX_DEPTH as i32, Bound::Exact);
+            return score;
+        }
+
+        let static_eval = self.evaluate(pos);
+
+        if !is_pv && !in_check && beta.abs() < MATE_BOUND {
+            if self.options.reverse_futility
+                && depth <= 3
+                && static_eval - REVERSE_FUTILITY_MARGIN * depth >= beta
+            {
+                return static_eval;
+            }
+
+            if self.options.razoring
+                && depth <= 2
+                && static_eval + RAZOR_MARGIN[depth as usize] < alpha
+            {
+                let score = self.quiescence(pos, alpha - 1, alpha, ply);
+                if score < alpha {
+                    return score;
+                }
+            }
+
+            // positions with only pawns left are prone to zugzwang, where
+            // passing would be better than any move
+            if self.options.null_move
+                && prev_move.is_some()
+                && depth >= 3
+                && static_eval >= beta
+                && pos.has_non_pawn_material(player)
+            {
+                let reduction = 2 + depth / 4;
+                let next = self.make_null_move(pos);
+                let score = -self.alpha_beta(
+                    &next,
+                    depth - 1 - reduction,
+                    -beta,
+                    -beta + 1,
+                    ply + 1,
+                    None,
+                );
+                self.unmake_move();
+                if score >= beta {
+                    return beta;
+                }
+            }
+        }
+
+        let futility_pruning = self.options.futility
+            && !is_pv
+            && !in_check
+            && depth <= 3
+            && static_eval + FUTILITY_MARGIN[depth as usize] <= alpha;
+
+        let mut picker = MovePicker::new(
+            pos,
+            tt_entry.and_then(|entry| entry.best_move),
+            self.ordering.get_killers(ply),
+            self.ordering.get_counter_move(prev_move),
+        );
+        let original_alpha = alpha;
+        let mut best_move = None;
+        let mut move_count = 0;
+        while let Some(m) = picker.next(&self.ordering) {
+            move_count += 1;
+            let quiet = !pos.is_capture(m) && !pos.is_promotion(m);
+            let next = self.make_move(pos, m);
+            let gives_check = next.is_in_check(next.get_player_on_move());
+
+            if futility_pruning && quiet && move_count > 1 && !gives_check {
+                self.unmake_move();
+                continue;
+            }
+
+            let score = if move_count == 1 {
+                -self.alpha_beta(&next, depth - 1, -beta, -alpha, ply + 1, Some(m))
+            } else {
+                let reduction = if self.options.late_move_reductions
+                    && depth >= 3
+                    && move_count > 3
+                    && quiet
+                    && !in_check
+                    && !gives_check
+                {
+                    self.get_reduction(depth, move_count)
+                } else {
+                    0
+                };
+
+                let mut score = -self.alpha_beta(
+                    &next,
+                    depth - 1 - reduction,
+                    -alpha - 1,
+                    -alpha,
+                    ply + 1,
+                    Some(m),
+                );
+                if score > alpha && reduction > 0 {
+                    score =
+                        -self.alpha_beta(&next, depth - 1, -alpha - 1, -alpha, ply + 1, Some(m));
+                }
+                if score > alpha && score < beta {
+                    score = -self.alpha_beta(&next, depth - 1, -beta, -alpha, ply + 1, Some(m));
+                }
+                score
+            };
+            self.unmake_move();
+
+            if self.is_stopped() {
+                return 0;
+            }
+            if score >= beta {
+                if quiet {
+                    self.ordering
+                        .update(player, m, prev_move, depth as u32, ply);
+                }
+                self.store(key, ply, Some(m), beta, depth, Bound::Lower);
+                return beta;
+            }
+            if score > alpha {
+                alpha = score;
+                best_move = Some(m);
+                if ply < MAX_PLY {
+                    let (current, rest) = self.pv_table.split_at_mut(ply + 1);
+                    current[ply].clear();
+                    current[ply].push(m);
+                    current[ply].extend(rest[0].iter());
+                }
+            }
+        }
+
+        if move_count == 0 {
+            return self.get_terminal_score(pos, ply);
+        }
+        if alpha > original_alpha {
+            self.store(key, ply, best_move, alpha, depth, Bound::Exact);
+        } else {
+            self.store(key, ply, None, alpha, depth, Bound::Upper);
+        }
+        alpha
+    }
+
+    fn store(
+        &self,
+        key: u64,
+        ply: usize,
+        best_move: Option<Move>,
+        score: Score,
+        depth: i32,
+        bound: Bound,
+    ) {
+        let entry = TtEntry {
+            best_move,
+            score,
+            depth,
+            bound,
+        };
+        self.tt.store(key, ply, entry);
+    }
+
+    // Resolves captures and promotions until the position is quiet, so that
+    // static evaluation is never applied in the middle of an exchange. When in
+    // check all evasions are searched instead.
+    fn quiescence(&mut self, pos: &Position, mut alpha: Score, beta: Score, ply: usize) -> Score {
+        self.pv_table[ply.min(MAX_PLY)].clear();
+        self.nodes += 1;
+        self.check_time();
+        if self.is_stopped() {
+            return 0;
+        }
+        if ply >= MAX_PLY {
+            return self.evaluate(pos);
+        }
+
+        let in_check = pos.is_in_check(pos.get_player_on_move());
+        let mut picker = if in_check {
+            MovePicker::new(pos, None, [None; 2], None)
+        } else {
+            let stand_pat = self.evaluate(pos);
+            if stand_pat >= beta {
+                return beta;
+            }
+            if stand_pat > alpha {
+                alpha = stand_pat;
+            }
+            // losing captures are never yielded by the quiescence picker
+            MovePicker::new_quiescence(pos, None)
+        };
+
+        let mut any_move = false;
+        while let Some(m) = picker.next(&self.ordering) {
+            any_move = true;
+            let next = self.make_move(pos, m);
+            let score = -self.quiescence(&next, -beta, -alpha, ply + 1);
+            self.unmake_move();
+            if score >= beta {
+                return beta;
+            }
+            if score > alpha {
+                alpha = score;
+            }
+        }
+
+        if in_check && !any_move {
+            return self.get_terminal_score(pos, ply);
+        }
+        alpha
+    }
+
+    // Plays m on a copy of pos, the network accumulators follow until the
+    // matching unmake_move
+    fn make_move(&mut self, pos: &Position, m: Move) -> Position {
+        let zeroing =
+            pos.is_capture(m) || pos.get_piece_on_position(m.0, m.1) == Some(ChessPiece::Pawn);
+        let halfmove_clock = if zeroing {
+            0
+        } else {
+            self.get_halfmove_clock() + 1
+        };
+        self.halfmove_clocks.push(halfmove_clock);
+        let mut next = *pos;
+        next.make_move(m.0, m.1, m.2, m.3);
+        if let Some(nnue) = self.nnue.as_mut() {
+            nnue.make_move(pos, m, &next);
+        }
+        next
+    }
+
+    fn make_null_move(&mut self, pos: &Position) -> Position {
+        self.halfmove_clocks.push(self.get_halfmove_clock() + 1);
+        let mut next = *pos;
+        next.make_null_move();
+        if let Some(nnue) = self.nnue.as_mut() {
+            nnue.make_null_move();
+        }
+        next
+    }
+
+    fn unmake_move(&mut self) {
+        self.halfmove_clocks.pop();
+        if let Some(nnue) = self.nnue.as_mut() {
+            nnue.unmake_move();
+        }
+    }
+
+    fn get_halfmove_clock(&self) -> u32 {
+        *self.halfmove_clocks.last().unwrap()
+    }
+
+    // Known endgames keep their specialized evaluation with a network too
+    fn evaluate(&self, pos: &Position) -> Score {
+        match &self.nnue {
+            Some(nnue) => probe_endgame(pos).unwrap_or_else(|| nnue.evaluate(pos)),
+            None => evaluate(pos),
+        }
+    }
+
+    // Score of a position without legal moves, mates closer to the root are
+    // preferred
+    fn get_terminal_score(&self, pos: &Position, ply: usize) -> Score {
+        if pos.is_in_check(pos.get_player_on_move()) {
+            -MATE_SCORE + ply as Score
+        } else {
+            0
+        }
+    }
+}
+
+impl Default for Searcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Nd5-e7+ forks the king and the queen
+    const FORK: &str = "2q3k1/5ppp/8/3N4/8/8/5PPP/6K1 w - - 0 1";
+    const FORK_MOVE: Move = (4, 3, 6, 4);
+
+    fn search(fen: &str, depth: u32, options: SearchOptions) -> (Option<Move>, Score) {
+        Searcher::with_options(options).search(&Position::from_fen(fen).unwrap(), depth)
+    }
+
+    #[test]
+    fn mate_is_found_at_its_depth() {
+        // Rh8#
+        let (m, score) = search("k7/8/1K6/8/8/8/8/7R w - - 0 1", 1, SearchOptions::default());
+        assert_eq!((m, score), (Some((0, 7, 7, 7)), MATE_SCORE - 1));
+        // Kb6 Kb8 Rh8#, three plies are needed to see it
+        let fen = "k7/8/2K5/8/8/8/8/7R w - - 0 1";
+        assert!(search(fen, 2, SearchOptions::default()).1 < MATE_BOUND);
+        assert_eq!(search(fen, 3, SearchOptions::default()).1, MATE_SCORE - 3);
+        assert_eq!(search(fen, 5, SearchOptions::default()).1, MATE_SCORE - 3);
+    }
+
+    #[test]
+    fn quiescence_takes_the_hanging_queen() {
+        let pos = Position::from_fen("4k3/5ppp/8/3p4/4Q3/8/5PPP/4K3 b - - 0 1").unwrap();
+        let mut searcher = Searcher::new();
+        assert!(searcher.evaluate(&pos) < -500);
+        assert!(searcher.quiescence(&pos, -INFINITY, INFINITY, 0) > 0);
+    }
+
+    #[test]
+    fn every_pruning_toggle_finds_the_fork() {
+        let toggles: [fn(&mut SearchOptions); 8] = [
+            |_| {},
+            |options| options.null_move = false,
+            |options| options.late_move_reductions = false,
+            |options| options.futility = false,
+            |options| options.reverse_futility = false,
+            |options| options.razoring = false,
+            |options| options.check_extensions = false,
+            |options| options.aspiration_windows = false,
+        ];
+        for toggle in toggles {
+            let mut options = SearchOptions::default();
+            toggle(&mut options);
+            let (m, score) = search(FORK, 5, options);
+            assert_eq!(m, Some(FORK_MOVE), "{:?}", options);
+            // the queen is lost for the knight otherwise
+            assert!(score > 200, "{:?}", options);
+        }
+    }
+
+    #[test]
+    fn parallel_search_returns_a_valid_move() {
+        let options = SearchOptions {
+            threads: 3,
+            ..SearchOptions::default()
+        };
+        let pos = Position::from_fen(FORK).unwrap();
+        let (m, _) = Searcher::with_options(options).search(&pos, 5);
+        assert!(pos.get_valid_moves().contains(&m.unwrap()));
+    }
+
+    #[test]
+    fn multi_pv_lines_are_distinct_and_ordered() {
+        let options = SearchOptions {
+            multi_pv: 4,
+            ..SearchOptions::default()
+        };
+        let lines =
+            Searcher::with_options(options).search_multi_pv(&Position::from_fen(FORK).unwrap(), 4);
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0].0, FORK_MOVE);
+        for (i, (m, score, pv)) in lines.iter().enumerate() {
+            assert_eq!(pv[0], *m);
+            assert!(lines[..i].iter().all(|line| line.0 != *m));
+            assert!(lines[..i].iter().all(|line| line.1 >= *score));
+        }
+    }
+
+    #[test]
+    fn stopped_search_returns_the_last_finished_iteration() {
+        let pos = Position::from_fen(FORK).unwrap();
+        for nodes in [1, 50, 300, 2000, 10000] {
+            let reported = Arc::new(std::sync::Mutex::new(None));
+            let mut searcher = Searcher::new();
+            let last = reported.clone();
+            searcher.set_info_callback(Box::new(move |info: &SearchInfo| {
+                *last.lock().unwrap() = Some((info.pv[0], info.score));
+            }));
+            let (m, score) = searcher.search_nodes(&pos, nodes);
+            assert!(pos.get_valid_moves().contains(&m.unwrap()));
+            let last = *reported.lock().unwrap();
+            if let Some(last) = last {
+                assert_eq!((m.unwrap(), score), last, "{} nodes", nodes);
+            }
+        }
+    }
+}
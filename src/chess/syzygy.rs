@@ -0,0 +1,774 @@
+// Syzygy WDL (.rtbw) and DTZ (.rtbz) tables for a single white piece against
+// the bare king, written in the compressed format tablebase.rs probes.
+// Positions are solved by retrograde iteration, the index encoding and the
+// Huffman layout follow the Syzygy probing code.
+//
+// Every symbol stands for a single value, no symbol pairs are built, so the
+// files are bigger than the published ones but probe to the same results.
+// DTZ is stored for white on move only, the prober searches one ply for
+// black.
+
+use super::chess_piece::ChessPiece;
+use super::chess_player::ChessPlayer;
+use super::position::{Position, PROMOTION_PIECES};
+use std::cmp::{self, Reverse};
+use std::collections::{BTreeMap, BinaryHeap};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+const WDL_MAGIC: [u8; 4] = [0x71, 0xe8, 0x23, 0x5d];
+const DTZ_MAGIC: [u8; 4] = [0xd7, 0x66, 0x0c, 0xa5];
+const WDL_SUFFIX: &str = ".rtbw";
+const DTZ_SUFFIX: &str = ".rtbz";
+
+// header flags, a split table stores both sides on move
+const SPLIT: u8 = 1;
+const HAS_PAWNS: u8 = 2;
+// DTZ flags, wins and losses are stored in plies instead of moves
+const WIN_PLIES: u8 = 4;
+const LOSS_PLIES: u8 = 8;
+
+// log2 of the bytes of a compressed block and of the values per index entry
+const BLOCK_SIZE: u8 = 6;
+const INDEX_BITS: u8 = 8;
+
+// positions of the piece and both kings once symmetries are removed
+const PIECE_TABLE_SIZE: usize = 31332;
+// pawn on one of the 6 ranks of a file, then both kings
+const PAWN_TABLE_SIZE: usize = 6 * 63 * 62;
+
+const SIGNATURE_PIECES: [(char, ChessPiece); 5] = [
+    ('Q', ChessPiece::Queen),
+    ('R', ChessPiece::Rook),
+    ('B', ChessPiece::Bishop),
+    ('N', ChessPiece::Knight),
+    ('P', ChessPiece::Pawn),
+];
+
+// Index tables of the three piece encoding, see encode
+#[rustfmt::skip]
+const TRIANGLE: [usize; 64] = [
+    6, 0, 1, 2, 2, 1, 0, 6,
+    0, 7, 3, 4, 4, 3, 7, 0,
+    1, 3, 8, 5, 5, 8, 3, 1,
+    2, 4, 5, 9, 9, 5, 4, 2,
+    2, 4, 5, 9, 9, 5, 4, 2,
+    1, 3, 8, 5, 5, 8, 3, 1,
+    0, 7, 3, 4, 4, 3, 7, 0,
+    6, 0, 1, 2, 2, 1, 0, 6,
+];
+
+#[rustfmt::skip]
+const LOWER: [usize; 64] = [
+    28,  0,  1,  2,  3,  4,  5,  6,
+     0, 29,  7,  8,  9, 10, 11, 12,
+     1,  7, 30, 13, 14, 15, 16, 17,
+     2,  8, 13, 31, 18, 19, 20, 21,
+     3,  9, 14, 18, 32, 22, 23, 24,
+     4, 10, 15, 19, 22, 33, 25, 26,
+     5, 11, 16, 20, 23, 25, 34, 27,
+     6, 12, 17, 21, 24, 26, 27, 35,
+];
+
+#[rustfmt::skip]
+const DIAG: [usize; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  8,
+     0,  1,  0,  0,  0,  0,  9,  0,
+     0,  0,  2,  0,  0, 10,  0,  0,
+     0,  0,  0,  3, 11,  0,  0,  0,
+     0,  0,  0, 12,  4,  0,  0,  0,
+     0,  0, 13,  0,  0,  5,  0,  0,
+     0, 14,  0,  0,  0,  0,  6,  0,
+    15,  0,  0,  0,  0,  0,  0,  7,
+];
+
+// Result for the player on move, distances are plies to the next capture,
+// pawn move or mate
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Dtz {
+    Win(u32),
+    Loss(u32),
+    Draw,
+}
+
+// White piece besides the kings, None for the bare kings
+type Material = Option<ChessPiece>;
+
+// Solved values by index, None for indices of impossible positions
+type Values = Vec<Option<Dtz>>;
+
+enum Successor {
+    // move of a king or the piece that stays within the solved slice
+    Quiet(usize),
+    // pawn move, capture or promotion, its result is known beforehand
+    Zeroing(Dtz),
+}
+
+fn parse_signature(signature: &str) -> Result<ChessPiece, String> {
+    SIGNATURE_PIECES
+        .iter()
+        .find(|&&(letter, _)| signature == format!("K{}vK", letter))
+        .map(|&(_, piece)| piece)
+        .ok_or_else(|| {
+            format!(
+                "{}: expected one white piece against the king, e.g. KRvK",
+                signature
+            )
+        })
+}
+
+fn get_piece_count(material: Material) -> usize {
+    if material.is_some() {
+        3
+    } else {
+        2
+    }
+}
+
+// Solver index, most significant first: side on move, white king, black king
+// and the piece
+fn get_index(player: ChessPlayer, squares: &[usize]) -> usize {
+    squares
+        .iter()
+        .fold(player as usize, |index, &square| index * 64 + square)
+}
+
+fn get_squares_from_index(mut index: usize, piece_count: usize) -> (ChessPlayer, Vec<usize>) {
+    let mut squares = vec![0; piece_count];
+    for square in squares.iter_mut().rev() {
+        *square = index % 64;
+        index /= 64;
+    }
+    let player = match index {
+        0 => ChessPlayer::White,
+        _ => ChessPlayer::Black,
+    };
+    (player, squares)
+}
+
+fn get_material_squares(pos: &Position) -> (Material, Vec<usize>) {
+    let king = |player| {
+        pos.get_piece_type_by_player(ChessPiece::King, player)
+            .get_ones()[0]
+    };
+    let mut squares = vec![king(ChessPlayer::White), king(ChessPlayer::Black)];
+    let mut material = None;
+    for &(_, piece) in SIGNATURE_PIECES.iter() {
+        let ones = pos
+            .get_piece_type_by_player(piece, ChessPlayer::White)
+            .get_ones();
+        if let Some(&square) = ones.first() {
+            material = Some(piece);
+            squares.push(square);
+        }
+    }
+    (material, squares)
+}
+
+// None when the squares do not make a position that can occur in a game
+fn get_position(material: Material, player: ChessPlayer, squares: &[usize]) -> Option<Position> {
+    let mut occupied = 0u64;
+    for &square in squares.iter() {
+        if occupied & (1 << square) != 0 {
+            return None;
+        }
+        occupied |= 1 << square;
+    }
+    if material == Some(ChessPiece::Pawn) && (squares[2] < 8 || squares[2] >= 56) {
+        return None;
+    }
+
+    let mut pos = Position::empty(player);
+    pos.put_piece(
+        ChessPlayer::White,
+        ChessPiece::King,
+        squares[0] / 8,
+        squares[0] % 8,
+    );
+    pos.put_piece(
+        ChessPlayer::Black,
+        ChessPiece::King,
+        squares[1] / 8,
+        squares[1] % 8,
+    );
+    if let Some(piece) = material {
+        pos.put_piece(ChessPlayer::White, piece, squares[2] / 8, squares[2] % 8);
+    }
+    if pos.is_in_check(player.get_opponent()) {
+        return None;
+    }
+    Some(pos)
+}
+
+// Solves material together with the tables its captures and promotions
+// lead to
+fn solve(material: Material, tables: &mut Vec<(Material, Values)>) {
+    if tables.iter().any(|(m, _)| *m == material) {
+        return;
+    }
+    if material.is_some() {
+        solve(None, tables);
+    }
+    if material == Some(ChessPiece::Pawn) {
+        for &piece in PROMOTION_PIECES.iter() {
+            solve(Some(piece), tables);
+        }
+    }
+    let values = solve_table(material, tables);
+    tables.push((material, values));
+}
+
+fn solve_table(material: Material, tables: &[(Material, Values)]) -> Values {
+    let piece_count = get_piece_count(material);
+    let size = 2 * 64usize.pow(piece_count as u32);
+    let mut values: Values = vec![None; size];
+
+    // a pawn only moves forward, positions are solved one pawn rank at a
+    // time starting next to promotion, so pawn moves lead to known results
+    let pawn = material == Some(ChessPiece::Pawn);
+    let slices: Vec<usize> = if pawn {
+        (1..7).rev().collect()
+    } else {
+        vec![0]
+    };
+    for slice in slices {
+        let mut unresolved = Vec::new();
+        for index in 0..size {
+            if pawn && index % 64 / 8 != slice {
+                continue;
+            }
+            let (player, squares) = get_squares_from_index(index, piece_count);
+            let pos = match get_position(material, player, &squares) {
+                Some(pos) => pos,
+                None => continue,
+            };
+
+            let mut successors = Vec::new();
+            for m in pos.get_valid_moves() {
+                let pieces = if pos.is_promotion(m) {
+                    &PROMOTION_PIECES[..]
+                } else {
+                    &PROMOTION_PIECES[..1]
+                };
+                for &piece in pieces.iter() {
+                    let mut next = pos;
+                    next.make_move_promoting(m.0, m.1, m.2, m.3, piece);
+                    let (next_material, next_squares) = get_material_squares(&next);
+                    let next_index = get_index(next.get_player_on_move(), &next_squares);
+                    if next_material != material {
+                        let (_, next_values) =
+                            tables.iter().find(|(m, _)| *m == next_material).unwrap();
+                        successors.push(Successor::Zeroing(next_values[next_index].unwrap()));
+                    } else if pos.get_piece_on_position(m.0, m.1) == Some(ChessPiece::Pawn) {
+                        successors.push(Successor::Zeroing(values[next_index].unwrap()));
+                    } else {
+                        successors.push(Successor::Quiet(next_index));
+                    }
+                }
+            }
+
+            if !successors.is_empty() {
+                unresolved.push((index, successors));
+            } else if pos.is_in_check(player) {
+                values[index] = Some(Dtz::Loss(0));
+            } else {
+                values[index] = Some(Dtz::Draw);
+            }
+        }
+
+        let mut plies = 1;
+        loop {
+            let found: Vec<(usize, Dtz)> = unresolved
+                .iter()
+                .filter_map(|(index, successors)| {
+                    get_value(successors, &values, plies).map(|value| (*index, value))
+                })
+                .collect();
+            if found.is_empty() {
+                break;
+            }
+            for &(index, value) in found.iter() {
+                values[index] = Some(value);
+            }
+            unresolved.retain(|&(index, _)| values[index].is_none());
+            plies += 1;
+        }
+        for (index, _) in unresolved {
+            values[index] = Some(Dtz::Draw);
+        }
+    }
+    values
+}
+
+// Value found in the pass for plies, every result closer than plies is known
+// by then. A loss counts the longest way to a zeroing move or mate.
+fn get_value(successors: &[Successor], values: &[Option<Dtz>], plies: u32) -> Option<Dtz> {
+    let mut loss = Some(0);
+    for successor in successors.iter() {
+        let (value, zeroing) = match *successor {
+            Successor::Quiet(index) => (values[index], false),
+            Successor::Zeroing(value) => (Some(value), true),
+        };
+        match value {
+            Some(Dtz::Loss(_)) if zeroing => return Some(Dtz::Win(1)),
+            Some(Dtz::Loss(distance)) if distance + 1 == plies => return Some(Dtz::Win(plies)),
+            Some(Dtz::Win(_)) if zeroing => loss = loss.map(|l| cmp::max(l, 1)),
+            Some(Dtz::Win(distance)) => loss = loss.map(|l| cmp::max(l, distance + 1)),
+            _ => loss = None,
+        }
+    }
+    loss.map(Dtz::Loss)
+}
+
+fn get_piece_code(player: ChessPlayer, piece: ChessPiece) -> u8 {
+    let code = match piece {
+        ChessPiece::Pawn => 1,
+        ChessPiece::Knight => 2,
+        ChessPiece::Bishop => 3,
+        ChessPiece::Rook => 4,
+        ChessPiece::Queen => 5,
+        ChessPiece::King => 6,
+    };
+    match player {
+        ChessPlayer::White => code,
+        ChessPlayer::Black => code | 8,
+    }
+}
+
+// Sign of the rank minus the file, 0 on the a1-h8 diagonal
+fn get_off_diagonal(square: usize) -> i32 {
+    (square / 8).cmp(&(square % 8)) as i32
+}
+
+fn flip_diagonal(square: usize) -> usize {
+    square % 8 * 8 + square / 8
+}
+
+// File of the pawn and the Syzygy index of a position with the squares of
+// the piece, the white king and the black king in this order
+fn encode(mut p: [usize; 3], pawn: bool) -> (usize, usize) {
+    if p[0] & 4 != 0 {
+        p = p.map(|square| square ^ 7);
+    }
+    if pawn {
+        // a lone pawn is indexed by its rank, the kings skip taken squares
+        let mut index = p[0] / 8 - 1;
+        let mut factor = 6;
+        for k in 1..3 {
+            let skips = p[..k].iter().filter(|&&square| square < p[k]).count();
+            index += (p[k] - skips) * factor;
+            factor *= 64 - k;
+        }
+        return (p[0] & 7, index);
+    }
+
+    if p[0] & 0x20 != 0 {
+        p = p.map(|square| square ^ 0x38);
+    }
+    if let Some(&square) = p.iter().find(|&&square| get_off_diagonal(square) != 0) {
+        if get_off_diagonal(square) > 0 {
+            p = p.map(flip_diagonal);
+        }
+    }
+    let s1 = (p[1] > p[0]) as usize;
+    let s2 = (p[2] > p[0]) as usize + (p[2] > p[1]) as usize;
+    let index = if get_off_diagonal(p[0]) != 0 {
+        TRIANGLE[p[0]] * 63 * 62 + (p[1] - s1) * 62 + (p[2] - s2)
+    } else if get_off_diagonal(p[1]) != 0 {
+        6 * 63 * 62 + DIAG[p[0]] * 28 * 62 + LOWER[p[1]] * 62 + p[2] - s2
+    } else if get_off_diagonal(p[2]) != 0 {
+        6 * 63 * 62 + 4 * 28 * 62 + DIAG[p[0]] * 7 * 28 + (DIAG[p[1]] - s1) * 28 + LOWER[p[2]]
+    } else {
+        6 * 63 * 62
+            + 4 * 28 * 62
+            + 4 * 7 * 28
+            + DIAG[p[0]] * 7 * 6
+            + (DIAG[p[1]] - s1) * 6
+            + (DIAG[p[2]] - s2)
+    };
+    (0, index)
+}
+
+// Huffman code length of each value
+fn get_code_lengths(counts: &BTreeMap<u16, usize>) -> Vec<(u16, u32)> {
+    let symbols: Vec<u16> = counts.keys().copied().collect();
+    let mut parents = vec![0; symbols.len()];
+    let mut heap: BinaryHeap<Reverse<(usize, usize)>> = counts
+        .values()
+        .enumerate()
+        .map(|(node, &count)| Reverse((count, node)))
+        .collect();
+    while heap.len() > 1 {
+        let Reverse((first, a)) = heap.pop().unwrap();
+        let Reverse((second, b)) = heap.pop().unwrap();
+        parents[a] = parents.len();
+        parents[b] = parents.len();
+        heap.push(Reverse((first + second, parents.len())));
+        parents.push(0);
+    }
+    let root = parents.len() - 1;
+    symbols
+        .iter()
+        .enumerate()
+        .map(|(mut node, &symbol)| {
+            let mut length = 0;
+            while node != root {
+                node = parents[node];
+                length += 1;
+            }
+            (symbol, length)
+        })
+        .collect()
+}
+
+// Compressed values of one table side
+struct PairsData {
+    header: Vec<u8>,
+    index: Vec<u8>,
+    sizes: Vec<u8>,
+    data: Vec<u8>,
+}
+
+impl PairsData {
+    // values no position reaches are None, they take the most common value
+    fn new(values: &[Option<u16>], flags: u8) -> Self {
+        let mut counts = BTreeMap::new();
+        for &value in values.iter().flatten() {
+            *counts.entry(value).or_insert(0) += 1;
+        }
+        let filler = counts
+            .iter()
+            .max_by_key(|&(&value, &count)| (count, Reverse(value)))
+            .map_or(0, |(&value, _)| value);
+        let values: Vec<u16> = values.iter().map(|value| value.unwrap_or(filler)).collect();
+        let mut counts = BTreeMap::new();
+        for &value in values.iter() {
+            *counts.entry(value).or_insert(0) += 1;
+        }
+        // a code needs two symbols, the second one is never used
+        if counts.len() == 1 {
+            counts.insert((filler + 1) & 0xfff, 0);
+        }
+
+        // canonical code, longer codes take the lower symbol numbers and
+        // the lower code values
+        let mut symbols = get_code_lengths(&counts);
+        symbols.sort_by_key(|&(value, length)| (Reverse(length), value));
+        let min_length = symbols.last().unwrap().1;
+        let max_length = symbols[0].1;
+        assert!(max_length <= 32);
+        let mut lowest_symbols = BTreeMap::new();
+        let mut bases = BTreeMap::new();
+        let mut base = 0u64;
+        for length in (min_length..=max_length).rev() {
+            let longer = symbols.iter().filter(|&&(_, l)| l > length).count();
+            if length != max_length {
+                // first code of this length follows the codes one bit longer
+                let next = symbols.iter().filter(|&&(_, l)| l == length + 1).count();
+                base = (base + next as u64) / 2;
+            }
+            lowest_symbols.insert(length, longer);
+            bases.insert(length, base);
+        }
+        let mut codes = BTreeMap::new();
+        for (number, &(value, length)) in symbols.iter().enumerate() {
+            let code = bases[&length] + (number - lowest_symbols[&length]) as u64;
+            codes.insert(value, (code, length));
+        }
+
+        // blocks hold whole codes, written from the most significant bit
+        let block_bits = 8usize << BLOCK_SIZE;
+        let mut data = Vec::new();
+        let mut block_starts = vec![0];
+        let mut bit = block_bits;
+        for (i, value) in values.iter().enumerate() {
+            let (code, length) = codes[value];
+            let length = length as usize;
+            if bit + length > block_bits {
+                if i > 0 {
+                    block_starts.push(i);
+                }
+                data.resize(data.len() + (block_bits / 8), 0);
+                bit = 0;
+            }
+            let start = data.len() - block_bits / 8;
+            for k in 0..length {
+                if code >> (length - 1 - k) & 1 != 0 {
+                    data[start + (bit + k) / 8] |= 0x80 >> ((bit + k) % 8);
+                }
+            }
+            bit += length;
+        }
+
+        let mut sizes = Vec::new();
+        for (k, &start) in block_starts.iter().enumerate() {
+            let end = block_starts.get(k + 1).copied().unwrap_or(values.len());
+            sizes.extend_from_slice(&((end - start - 1) as u16).to_le_bytes());
+        }
+
+        // each entry points at the value in the middle of its span
+        let span = 1 << INDEX_BITS;
+        let mut index = Vec::new();
+        for i in 0..values.len().div_ceil(span) {
+            let middle = i * span + span / 2;
+            let block = match block_starts.binary_search(&cmp::min(middle, values.len() - 1)) {
+                Ok(block) => block,
+                Err(block) => block - 1,
+            };
+            index.extend_from_slice(&(block as u32).to_le_bytes());
+            index.extend_from_slice(&((middle - block_starts[block]) as u16).to_le_bytes());
+        }
+
+        let mut header = vec![flags, BLOCK_SIZE, INDEX_BITS, 0];
+        header.extend_from_slice(&(block_starts.len() as u32).to_le_bytes());
+        header.push(max_length as u8);
+        header.push(min_length as u8);
+        for length in min_length..=max_length {
+            header.extend_from_slice(&(lowest_symbols[&length] as u16).to_le_bytes());
+        }
+        header.extend_from_slice(&(symbols.len() as u16).to_le_bytes());
+        for &(value, _) in symbols.iter() {
+            // a leaf symbol, the right half is all ones
+            header.extend_from_slice(&[value as u8, (value >> 8) as u8 | 0xf0, 0xff]);
+        }
+        if symbols.len() % 2 == 1 {
+            header.push(0);
+        }
+
+        Self {
+            header,
+            index,
+            sizes,
+            data,
+        }
+    }
+}
+
+pub struct SyzygyTable {
+    signature: String,
+    piece: ChessPiece,
+    values: Values,
+}
+
+impl SyzygyTable {
+    // Solves material like "KRvK" or "KPvK"
+    pub fn generate(signature: &str) -> Result<Self, String> {
+        let piece = parse_signature(signature)?;
+        let mut tables = Vec::new();
+        solve(Some(piece), &mut tables);
+        let (_, values) = tables.pop().unwrap();
+        Ok(Self {
+            signature: signature.to_string(),
+            piece,
+            values,
+        })
+    }
+
+    pub fn get_signature(&self) -> &str {
+        &self.signature
+    }
+
+    // Number of positions of each outcome and the longest distance in plies
+    pub fn get_statistics(&self) -> (usize, usize, usize, u32) {
+        let (mut wins, mut losses, mut draws, mut longest) = (0, 0, 0, 0);
+        for value in self.values.iter().flatten() {
+            match *value {
+                Dtz::Win(plies) => {
+                    wins += 1;
+                    longest = cmp::max(longest, plies);
+                }
+                Dtz::Loss(plies) => {
+                    losses += 1;
+                    longest = cmp::max(longest, plies);
+                }
+                Dtz::Draw => draws += 1,
+            }
+        }
+        (wins, losses, draws, longest)
+    }
+
+    // Writes <signature>.rtbw and <signature>.rtbz into dir
+    pub fn write(&self, dir: &Path) -> io::Result<()> {
+        let (wdl, dtz) = self.get_stored_values();
+        let wdl: Vec<Vec<PairsData>> = wdl
+            .iter()
+            .map(|sides| {
+                sides
+                    .iter()
+                    .map(|values| PairsData::new(values, 0))
+                    .collect()
+            })
+            .collect();
+        // DTZ is stored for the first side, white on move
+        let dtz: Vec<Vec<PairsData>> = dtz
+            .iter()
+            .map(|values| vec![PairsData::new(values, WIN_PLIES | LOSS_PLIES)])
+            .collect();
+        fs::write(
+            dir.join(format!("{}{}", self.signature, WDL_SUFFIX)),
+            self.to_bytes(&WDL_MAGIC, SPLIT, &wdl),
+        )?;
+        fs::write(
+            dir.join(format!("{}{}", self.signature, DTZ_SUFFIX)),
+            self.to_bytes(&DTZ_MAGIC, 0, &dtz),
+        )
+    }
+
+    // WDL values by file and side on move and DTZ values by file, the
+    // prober returns the stored WDL value minus 2 and the stored DTZ plus 1
+    #[allow(clippy::type_complexity)]
+    fn get_stored_values(&self) -> (Vec<[Vec<Option<u16>>; 2]>, Vec<Vec<Option<u16>>>) {
+        let pawn = self.piece == ChessPiece::Pawn;
+        let (files, size) = if pawn {
+            (4, PAWN_TABLE_SIZE)
+        } else {
+            (1, PIECE_TABLE_SIZE)
+        };
+        let mut wdl = vec![[vec![None; size], vec![None; size]]; files];
+        let mut dtz = vec![vec![None; size]; files];
+        for (index, value) in self.values.iter().enumerate() {
+            let value = match value {
+                Some(value) => *value,
+                None => continue,
+            };
+            let (player, squares) = get_squares_from_index(index, 3);
+            let (file, i) = encode([squares[2], squares[0], squares[1]], pawn);
+            let (wdl_value, dtz_value) = match value {
+                Dtz::Win(plies) => (4, Some(plies as u16 - 1)),
+                Dtz::Loss(plies) => (0, Some(cmp::max(plies, 1) as u16 - 1)),
+                Dtz::Draw => (2, None),
+            };
+            let stored = &mut wdl[file][player as usize][i];
+            // symmetric positions share the index
+            assert!(stored.is_none() || *stored == Some(wdl_value));
+            *stored = Some(wdl_value);
+            if player == ChessPlayer::White && dtz_value.is_some() {
+                dtz[file][i] = dtz_value;
+            }
+        }
+        (wdl, dtz)
+    }
+
+    fn to_bytes(&self, magic: &[u8; 4], flags: u8, files: &[Vec<PairsData>]) -> Vec<u8> {
+        let pawn = self.piece == ChessPiece::Pawn;
+        let mut bytes = magic.to_vec();
+        bytes.push(if pawn { flags | HAS_PAWNS } else { flags });
+        // pieces in index order for both sides on move, the order byte
+        // puts the leading group first
+        let pieces = [
+            get_piece_code(ChessPlayer::White, self.piece),
+            get_piece_code(ChessPlayer::White, ChessPiece::King),
+            get_piece_code(ChessPlayer::Black, ChessPiece::King),
+        ];
+        for _ in files.iter() {
+            bytes.push(0);
+            bytes.extend(pieces.iter().map(|&code| code | code << 4));
+        }
+        if bytes.len() % 2 == 1 {
+            bytes.push(0);
+        }
+        for sides in files.iter() {
+            for side in sides.iter() {
+                bytes.extend_from_slice(&side.header);
+            }
+        }
+        for sides in files.iter() {
+            for side in sides.iter() {
+                bytes.extend_from_slice(&side.index);
+            }
+        }
+        for sides in files.iter() {
+            for side in sides.iter() {
+                bytes.extend_from_slice(&side.sizes);
+            }
+        }
+        for sides in files.iter() {
+            for side in sides.iter() {
+                bytes.resize(bytes.len().div_ceil(64) * 64, 0);
+                bytes.extend_from_slice(&side.data);
+            }
+        }
+        // the prober expects 16 bytes of checksum after the data
+        bytes.resize(bytes.len().div_ceil(64) * 64 + 16, 0);
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // squares of the piece, the white king and the black king
+    fn encode_squares(squares: [usize; 3], pawn: bool) -> (usize, usize) {
+        let (file, index) = encode(squares, pawn);
+        assert!(
+            index
+                < if pawn {
+                    PAWN_TABLE_SIZE
+                } else {
+                    PIECE_TABLE_SIZE
+                }
+        );
+        (file, index)
+    }
+
+    #[test]
+    fn symmetric_positions_share_the_index() {
+        let mirror = |squares: [usize; 3]| squares.map(|square| square ^ 7);
+        let flip = |squares: [usize; 3]| squares.map(|square| square ^ 0x38);
+        for &squares in [[15, 0, 18], [27, 63, 9], [12, 40, 33]].iter() {
+            let index = encode_squares(squares, false);
+            assert_eq!(encode_squares(mirror(squares), false), index);
+            assert_eq!(encode_squares(flip(squares), false), index);
+            assert_eq!(encode_squares(squares.map(flip_diagonal), false), index);
+            // only files are mirrored with a pawn on the board
+            let index = encode_squares(squares, true);
+            assert_eq!(encode_squares(mirror(squares), true), index);
+        }
+    }
+
+    #[test]
+    fn distinct_positions_get_distinct_indices() {
+        let mut seen = vec![false; PIECE_TABLE_SIZE];
+        for index in 0..64 * 64 * 64 {
+            let (_, squares) = get_squares_from_index(index, 3);
+            if get_position(Some(ChessPiece::Queen), ChessPlayer::White, &squares).is_none() {
+                continue;
+            }
+            // one position out of each group of the 8 board symmetries
+            let squares = [squares[2], squares[0], squares[1]];
+            let images = (0..8).map(|symmetry| {
+                squares.map(|mut square| {
+                    if symmetry & 1 != 0 {
+                        square ^= 7;
+                    }
+                    if symmetry & 2 != 0 {
+                        square ^= 0x38;
+                    }
+                    if symmetry & 4 != 0 {
+                        square = flip_diagonal(square);
+                    }
+                    square
+                })
+            });
+            if images.min() != Some(squares) {
+                continue;
+            }
+            let (_, i) = encode_squares(squares, false);
+            assert!(!seen[i]);
+            seen[i] = true;
+        }
+    }
+
+    #[test]
+    fn dtz_counts_to_the_pawn_move() {
+        let win = Successor::Zeroing(Dtz::Loss(5));
+        assert_eq!(get_value(&[win], &[], 1), Some(Dtz::Win(1)));
+        let values = [Some(Dtz::Win(3)), Some(Dtz::Win(1))];
+        let moves = [Successor::Quiet(0), Successor::Quiet(1)];
+        assert_eq!(get_value(&moves, &values, 4), Some(Dtz::Loss(4)));
+        let escape = [Successor::Quiet(0), Successor::Zeroing(Dtz::Draw)];
+        assert_eq!(get_value(&escape, &values, 4), None);
+    }
+}
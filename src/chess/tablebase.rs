@@ -0,0 +1,286 @@
+use super::bitboard::Bitboard;
+use super::chess_piece::ChessPiece;
+use super::chess_player::ChessPlayer;
+use super::magic_bitboards::{
+    BISHOP_BLOCKER_MASKS, BISHOP_MAP, KING_POSSIBLE_MOVES, KNIGHT_POSSIBLE_MOVES,
+    ROOK_BLOCKER_MASKS, ROOK_MAP,
+};
+use super::position::{Move, Position};
+use pyrrhic_rs::{Color, DtzProbeValue, EngineAdapter, Piece, TBError, TableBases, WdlProbeResult};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+const FILE_A: u64 = 0x0101_0101_0101_0101;
+const FILE_H: u64 = FILE_A << 7;
+const WDL_SUFFIX: &str = ".rtbw";
+
+// Win/draw/loss of the player on move, wins and losses that can't be
+// realised before the 50-move rule applies are told apart
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Wdl {
+    Loss,
+    BlessedLoss,
+    Draw,
+    CursedWin,
+    Win,
+}
+
+impl From<WdlProbeResult> for Wdl {
+    fn from(wdl: WdlProbeResult) -> Self {
+        match wdl {
+            WdlProbeResult::Loss => Wdl::Loss,
+            WdlProbeResult::BlessedLoss => Wdl::BlessedLoss,
+            WdlProbeResult::Draw => Wdl::Draw,
+            WdlProbeResult::CursedWin => Wdl::CursedWin,
+            WdlProbeResult::Win => Wdl::Win,
+        }
+    }
+}
+
+// Attack generation of the prober, backed by our own lookup tables
+#[derive(Clone)]
+pub struct PositionAdapter;
+
+impl EngineAdapter for PositionAdapter {
+    fn pawn_attacks(color: Color, square: u64) -> u64 {
+        let pawn = 1u64 << square;
+        match color {
+            Color::White => ((pawn & !FILE_A) << 7) | ((pawn & !FILE_H) << 9),
+            Color::Black => ((pawn & !FILE_H) >> 7) | ((pawn & !FILE_A) >> 9),
+        }
+    }
+
+    fn knight_attacks(square: u64) -> u64 {
+        KNIGHT_POSSIBLE_MOVES[square as usize].get_value()
+    }
+
+    fn bishop_attacks(square: u64, occupied: u64) -> u64 {
+        let square = square as usize;
+        let blockers = Bitboard::new(occupied) & BISHOP_BLOCKER_MASKS[square];
+        BISHOP_MAP.get(&(square, blockers)).unwrap().get_value()
+    }
+
+    fn rook_attacks(square: u64, occupied: u64) -> u64 {
+        let square = square as usize;
+        let blockers = Bitboard::new(occupied) & ROOK_BLOCKER_MASKS[square];
+        ROOK_MAP.get(&(square, blockers)).unwrap().get_value()
+    }
+
+    fn queen_attacks(square: u64, occupied: u64) -> u64 {
+        Self::bishop_attacks(square, occupied) | Self::rook_attacks(square, occupied)
+    }
+
+    fn king_attacks(square: u64) -> u64 {
+        KING_POSSIBLE_MOVES[square as usize].get_value()
+    }
+}
+
+// Syzygy endgame tablebases loaded from a directory. Only one instance can
+// exist at a time, share it behind an Arc.
+pub struct Tablebase {
+    tables: TableBases<PositionAdapter>,
+    // material signatures like KQvKR that have a WDL table
+    available: HashSet<String>,
+    max_pieces: u32,
+}
+
+impl Tablebase {
+    pub fn open(path: &Path) -> Result<Self, TBError> {
+        // the prober treats missing files as present, so which tables exist
+        // is decided here, before any probe tries to open them
+        let available: HashSet<String> = fs::read_dir(path)
+            .map_err(|_| TBError::BadPath)?
+            .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+            .filter_map(|name| name.strip_suffix(WDL_SUFFIX).map(String::from))
+            .collect();
+        let max_pieces = available
+            .iter()
+            .map(|name| name.len() as u32 - 1)
+            .max()
+            .ok_or(TBError::BadPath)?;
+
+        let path = path.to_str().ok_or(TBError::BadPath)?;
+        Ok(Self {
+            tables: TableBases::new(path)?,
+            available,
+            max_pieces,
+        })
+    }
+
+    pub fn get_max_pieces(&self) -> u32 {
+        self.max_pieces
+    }
+
+    pub fn can_probe(&self, pos: &Position) -> bool {
+        if pos.get_taken_bitboard().count_ones() as u32 > self.max_pieces {
+            return false;
+        }
+        let white = pos.get_material_signature(ChessPlayer::White);
+        let black = pos.get_material_signature(ChessPlayer::Black);
+        self.available.contains(&format!("{}v{}", white, black))
+            || self.available.contains(&format!("{}v{}", black, white))
+    }
+
+    // Position has neither castling nor en passant, so neither is passed on
+    fn get_bitboards(pos: &Position) -> [u64; 8] {
+        let piece = |piece: ChessPiece| pos.get_piece_mask(piece).get_value();
+        [
+            pos.get_pieces_of_player(ChessPlayer::White).get_value(),
+            pos.get_pieces_of_player(ChessPlayer::Black).get_value(),
+            piece(ChessPiece::King),
+            piece(ChessPiece::Queen),
+            piece(ChessPiece::Rook),
+            piece(ChessPiece::Bishop),
+            piece(ChessPiece::Knight),
+            piece(ChessPiece::Pawn),
+        ]
+    }
+
+    // Assumes the last move reset the 50-move counter, the search probes
+    // only right after captures and pawn moves
+    pub fn probe_wdl(&self, pos: &Position) -> Option<Wdl> {
+        if !self.can_probe(pos) {
+            return None;
+        }
+        let [white, black, kings, queens, rooks, bishops, knights, pawns] =
+            Self::get_bitboards(pos);
+        let white_on_move = pos.get_player_on_move() == ChessPlayer::White;
+        self.tables
+            .probe_wdl(
+                white,
+                black,
+                kings,
+                queens,
+                rooks,
+                bishops,
+                knights,
+                pawns,
+                0,
+                white_on_move,
+            )
+            .ok()
+            .map(Wdl::from)
+    }
+
+    // Probes distance to zeroing for every valid move of pos, halfmove_clock
+    // is the number of plies since the last capture or pawn move. Returns the
+    // moves with their outcome for the player on move and distance to zero.
+    // Underpromotions are left out, the search only plays queen promotions.
+    pub fn probe_root(&self, pos: &Position, halfmove_clock: u32) -> Option<Vec<(Move, Wdl, u16)>> {
+        if !self.can_probe(pos) {
+            return None;
+        }
+        let [white, black, kings, queens, rooks, bishops, knights, pawns] =
+            Self::get_bitboards(pos);
+        let white_on_move = pos.get_player_on_move() == ChessPlayer::White;
+        let result = self
+            .tables
+            .probe_root(
+                white,
+                black,
+                kings,
+                queens,
+                rooks,
+                bishops,
+                knights,
+                pawns,
+                halfmove_clock,
+                0,
+                white_on_move,
+            )
+            .ok()?;
+
+        let valid_moves = pos.get_valid_moves();
+        let moves = result.moves[..result.num_moves]
+            .iter()
+            .filter_map(|value| match value {
+                DtzProbeValue::DtzResult(dtz) => {
+                    let (from, to) = (dtz.from_square as usize, dtz.to_square as usize);
+                    let m = (from / 8, from % 8, to / 8, to % 8);
+                    let promotion_valid = match dtz.promotion {
+                        Piece::Pawn => !pos.is_promotion(m),
+                        Piece::Queen => pos.is_promotion(m),
+                        _ => false,
+                    };
+                    if promotion_valid && valid_moves.contains(&m) {
+                        Some((m, Wdl::from(dtz.wdl), dtz.dtz))
+                    } else {
+                        None
+                    }
+                }
+                _ => None,
+            })
+            .collect();
+        Some(moves)
+    }
+
+    // Moves of pos that keep the best outcome reachable under the 50-move
+    // rule, None when pos is not covered by the tablebases
+    pub fn filter_root_moves(&self, pos: &Position, halfmove_clock: u32) -> Option<Vec<Move>> {
+        let moves = self.probe_root(pos, halfmove_clock)?;
+        let best = moves.iter().map(|&(_, wdl, _)| wdl).max()?;
+        Some(
+            moves
+                .into_iter()
+                .filter(|&(_, wdl, _)| wdl == best)
+                .map(|(m, _, _)| m)
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::sync::OnceLock;
+
+    // Only one set of tables may be open at a time, the tests share it. The
+    // fixtures hold the 3-piece tables written by tbgen --syzygy.
+    fn get_tables() -> &'static Tablebase {
+        static TABLES: OnceLock<Tablebase> = OnceLock::new();
+        TABLES.get_or_init(|| {
+            let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/syzygy");
+            Tablebase::open(&path).unwrap()
+        })
+    }
+
+    fn probe_wdl(fen: &str) -> Option<Wdl> {
+        get_tables().probe_wdl(&Position::from_fen(fen).unwrap())
+    }
+
+    #[test]
+    fn open_fails_without_tables() {
+        let path = env::temp_dir().join("chess-no-tablebases");
+        fs::create_dir_all(&path).unwrap();
+        assert!(Tablebase::open(&path).is_err());
+    }
+
+    #[test]
+    fn wdl_of_small_endgames() {
+        assert_eq!(probe_wdl("4k3/8/8/8/8/8/8/4K2Q w - - 0 1"), Some(Wdl::Win));
+        assert_eq!(probe_wdl("4k3/8/8/8/8/8/8/4K2Q b - - 0 1"), Some(Wdl::Loss));
+        // the rook hangs
+        assert_eq!(probe_wdl("8/8/8/8/8/8/1k6/R3K3 b - - 0 1"), Some(Wdl::Draw));
+        assert_eq!(probe_wdl("8/8/8/8/8/8/4P3/4K2k w - - 0 1"), Some(Wdl::Win));
+        assert_eq!(probe_wdl("8/8/8/8/8/k7/8/KQRBN3 w - - 0 1"), None);
+    }
+
+    #[test]
+    fn dtz_of_pawn_push_is_one() {
+        let pos = Position::from_fen("8/8/8/8/8/8/4P3/4K2k w - - 0 1").unwrap();
+        let moves = get_tables().probe_root(&pos, 0).unwrap();
+        assert_eq!(moves.len(), pos.get_valid_moves().len());
+        let (_, wdl, dtz) = moves.iter().find(|&&(m, _, _)| m == (1, 4, 3, 4)).unwrap();
+        assert_eq!((*wdl, *dtz), (Wdl::Win, 1));
+    }
+
+    #[test]
+    fn root_filter_drops_hanging_the_queen() {
+        let pos = Position::from_fen("8/8/8/8/8/8/1k6/4K2Q w - - 0 1").unwrap();
+        let moves = get_tables().filter_root_moves(&pos, 0).unwrap();
+        assert!(!moves.contains(&(0, 7, 0, 1)));
+        assert!(moves.contains(&(0, 7, 7, 7)));
+    }
+}
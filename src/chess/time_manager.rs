@@ -0,0 +1,229 @@
+use super::position::{Move, Position};
+use std::time::{Duration, Instant};
+
+// moves the game is assumed to still last when the clock gives no hint
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+const MAX_INSTABILITY: f64 = 2.5;
+const FAIL_LOW_FACTOR: f64 = 1.5;
+
+#[derive(Debug, Copy, Clone)]
+pub struct TimeControl {
+    pub remaining: Duration,
+    pub increment: Duration,
+    pub moves_to_go: Option<u32>,
+    // time lost per move to communication, subtracted from every budget
+    pub move_overhead: Duration,
+}
+
+// Decides how long the search of one move may take. The soft limit is
+// checked between iterations and grows when the search looks unsure, the
+// hard limit aborts the search even in the middle of an iteration.
+#[derive(Debug, Clone)]
+pub struct TimeManager {
+    start: Instant,
+    soft_limit: Duration,
+    hard_limit: Duration,
+    instability: f64,
+    failed_low: bool,
+    last_best_move: Option<Move>,
+}
+
+impl TimeManager {
+    pub fn new(time_control: &TimeControl, pos: &Position) -> Self {
+        let available = time_control
+            .remaining
+            .checked_sub(time_control.move_overhead)
+            .unwrap_or_default();
+        let moves_to_go = time_control
+            .moves_to_go
+            .unwrap_or(DEFAULT_MOVES_TO_GO)
+            .max(1);
+
+        let mut soft_limit = if moves_to_go == 1 {
+            available.mul_f64(0.8)
+        } else {
+            available / moves_to_go + time_control.increment.mul_f64(0.75)
+        };
+        soft_limit = soft_limit.min(available / 2);
+        let mut hard_limit = (soft_limit * 4)
+            .min(available.mul_f64(0.75))
+            .max(soft_limit);
+
+        // nothing to think about, one iteration to get a score is enough
+        if pos.get_valid_moves().len() == 1 {
+            soft_limit = Duration::default();
+            hard_limit = hard_limit.min(available / 20);
+        }
+
+        Self {
+            start: Instant::now(),
+            soft_limit,
+            hard_limit,
+            instability: 1.0,
+            failed_low: false,
+            last_best_move: None,
+        }
+    }
+
+    // Time manager with both limits equal, for searches of a fixed time
+    pub fn with_move_time(move_time: Duration) -> Self {
+        Self {
+            start: Instant::now(),
+            soft_limit: move_time,
+            hard_limit: move_time,
+            instability: 1.0,
+            failed_low: false,
+            last_best_move: None,
+        }
+    }
+
+    pub fn get_elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    pub fn get_soft_limit(&self) -> Duration {
+        let mut factor = self.instability;
+        if self.failed_low {
+            factor = factor.max(FAIL_LOW_FACTOR);
+        }
+        self.soft_limit.mul_f64(factor).min(self.hard_limit)
+    }
+
+    pub fn get_hard_limit(&self) -> Duration {
+        self.hard_limit
+    }
+
+    pub fn is_hard_limit_reached(&self) -> bool {
+        self.get_elapsed() >= self.hard_limit
+    }
+
+    // Whether another iteration of iterative deepening should be started
+    pub fn can_start_iteration(&self) -> bool {
+        self.get_elapsed() < self.get_soft_limit()
+    }
+
+    // The root score dropped below the aspiration window
+    pub fn on_fail_low(&mut self) {
+        self.failed_low = true;
+    }
+
+    // Called before every iteration, a fail low extends only the soft limit
+    // checked right after the iteration it happened in
+    pub fn on_iteration_start(&mut self) {
+        self.failed_low = false;
+    }
+
+    // Called after every completed iteration with its best move
+    pub fn on_iteration(&mut self, best_move: Option<Move>) {
+        if self.last_best_move.is_some() && best_move != self.last_best_move {
+            self.instability = (self.instability * 1.4).min(MAX_INSTABILITY);
+        } else {
+            self.instability = (self.instability * 0.9).max(1.0);
+        }
+        self.last_best_move = best_move;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_time_control(remaining: u64, increment: u64, moves_to_go: Option<u32>) -> TimeControl {
+        TimeControl {
+            remaining: Duration::from_secs(remaining),
+            increment: Duration::from_secs(increment),
+            moves_to_go,
+            move_overhead: Duration::default(),
+        }
+    }
+
+    fn get_limits(time_control: &TimeControl) -> (Duration, Duration) {
+        let time_manager = TimeManager::new(time_control, &Position::default());
+        (time_manager.get_soft_limit(), time_manager.get_hard_limit())
+    }
+
+    #[test]
+    fn soft_and_hard_limits() {
+        // a thirtieth of the clock, the hard limit four times that
+        let limits = get_limits(&get_time_control(60, 0, None));
+        assert_eq!(limits, (Duration::from_secs(2), Duration::from_secs(8)));
+        // three quarters of the increment are spent too
+        let limits = get_limits(&get_time_control(60, 4, None));
+        assert_eq!(limits, (Duration::from_secs(5), Duration::from_secs(20)));
+        // the hard limit never takes more than three quarters of the clock
+        let limits = get_limits(&get_time_control(4, 10, None));
+        assert_eq!(limits, (Duration::from_secs(2), Duration::from_secs(3)));
+    }
+
+    #[test]
+    fn moves_to_go_divides_the_clock() {
+        let limits = get_limits(&get_time_control(60, 0, Some(10)));
+        assert_eq!(limits, (Duration::from_secs(6), Duration::from_secs(24)));
+        // the last move before the time control may use half of the clock
+        let limits = get_limits(&get_time_control(60, 0, Some(1)));
+        assert_eq!(limits, (Duration::from_secs(30), Duration::from_secs(45)));
+        // zero is treated as one
+        assert_eq!(get_limits(&get_time_control(60, 0, Some(0))), limits);
+    }
+
+    #[test]
+    fn move_overhead_is_subtracted() {
+        let mut time_control = get_time_control(90, 0, None);
+        time_control.move_overhead = Duration::from_secs(30);
+        let limits = get_limits(&time_control);
+        assert_eq!(limits, (Duration::from_secs(2), Duration::from_secs(8)));
+        time_control.move_overhead = Duration::from_secs(120);
+        assert_eq!(
+            get_limits(&time_control),
+            (Duration::default(), Duration::default())
+        );
+    }
+
+    #[test]
+    fn single_legal_move_needs_no_thought() {
+        let pos = Position::from_fen("7k/8/8/8/8/8/6r1/r6K w - - 0 1").unwrap();
+        assert_eq!(pos.get_valid_moves().len(), 1);
+        let time_manager = TimeManager::new(&get_time_control(60, 0, None), &pos);
+        assert_eq!(time_manager.get_soft_limit(), Duration::default());
+        assert_eq!(time_manager.get_hard_limit(), Duration::from_secs(3));
+    }
+
+    #[test]
+    fn fail_low_extends_the_soft_limit() {
+        let mut time_manager =
+            TimeManager::new(&get_time_control(60, 0, None), &Position::default());
+        let m = Some((1, 4, 3, 4));
+        time_manager.on_iteration_start();
+        time_manager.on_fail_low();
+        time_manager.on_iteration(m);
+        // checked after the iteration, before the next one starts
+        assert_eq!(time_manager.get_soft_limit(), Duration::from_secs(3));
+        time_manager.on_iteration_start();
+        assert_eq!(time_manager.get_soft_limit(), Duration::from_secs(2));
+    }
+
+    #[test]
+    fn changing_best_move_extends_the_soft_limit() {
+        let mut time_manager =
+            TimeManager::new(&get_time_control(60, 0, None), &Position::default());
+        time_manager.on_iteration(Some((1, 4, 3, 4)));
+        assert_eq!(time_manager.get_soft_limit(), Duration::from_secs(2));
+        time_manager.on_iteration(Some((1, 3, 3, 3)));
+        assert_eq!(time_manager.get_soft_limit(), Duration::from_secs_f64(2.8));
+        // never beyond the hard limit
+        for _ in 0..10 {
+            time_manager.on_fail_low();
+            time_manager.on_iteration(Some((1, 3, 3, 3)));
+        }
+        assert!(time_manager.get_soft_limit() <= time_manager.get_hard_limit());
+    }
+
+    #[test]
+    fn move_time_limits_are_equal() {
+        let time_manager = TimeManager::with_move_time(Duration::from_millis(500));
+        assert_eq!(time_manager.get_soft_limit(), Duration::from_millis(500));
+        assert_eq!(time_manager.get_hard_limit(), Duration::from_millis(500));
+        assert!(time_manager.can_start_iteration());
+        assert!(!time_manager.is_hard_limit_reached());
+    }
+}
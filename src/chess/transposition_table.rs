@@ -0,0 +1,136 @@
+use super::position::Move;
+use super::search::{Score, MATE_BOUND};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Bound {
+    Exact = 0,
+    Lower = 1,
+    Upper = 2,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TtEntry {
+    pub best_move: Option<Move>,
+    pub score: Score,
+    pub depth: i32,
+    pub bound: Bound,
+}
+
+// Entries are stored as key ^ data next to data, so a torn write from another
+// thread shows up as a key mismatch instead of a corrupted entry
+struct Slot {
+    check: AtomicU64,
+    data: AtomicU64,
+}
+
+// Transposition table that can be shared between search threads without locks
+pub struct TranspositionTable {
+    slots: Vec<Slot>,
+}
+
+impl TranspositionTable {
+    pub fn new(size_mb: usize) -> Self {
+        let slot_count = (size_mb.max(1) << 20) / std::mem::size_of::<Slot>();
+        let slots = (0..slot_count)
+            .map(|_| Slot {
+                check: AtomicU64::new(0),
+                data: AtomicU64::new(0),
+            })
+            .collect();
+        Self { slots }
+    }
+
+    fn get_slot(&self, key: u64) -> &Slot {
+        &self.slots[(key % self.slots.len() as u64) as usize]
+    }
+
+    pub fn probe(&self, key: u64, ply: usize) -> Option<TtEntry> {
+        let slot = self.get_slot(key);
+        let data = slot.data.load(Ordering::Relaxed);
+        let check = slot.check.load(Ordering::Relaxed);
+        if data == 0 || check ^ data != key {
+            return None;
+        }
+        let mut entry = unpack(data);
+        entry.score = score_from_tt(entry.score, ply);
+        Some(entry)
+    }
+
+    pub fn store(&self, key: u64, ply: usize, mut entry: TtEntry) {
+        let slot = self.get_slot(key);
+        let old_data = slot.data.load(Ordering::Relaxed);
+        let old_check = slot.check.load(Ordering::Relaxed);
+        // deeper results for the same position are kept
+        if old_data != 0 && old_check ^ old_data == key && unpack(old_data).depth > entry.depth {
+            return;
+        }
+        entry.score = score_to_tt(entry.score, ply);
+        let data = pack(entry);
+        slot.check.store(key ^ data, Ordering::Relaxed);
+        slot.data.store(data, Ordering::Relaxed);
+    }
+
+    pub fn clear(&self) {
+        for slot in self.slots.iter() {
+            slot.check.store(0, Ordering::Relaxed);
+            slot.data.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+// Mate scores are stored relative to the node instead of the root
+fn score_to_tt(score: Score, ply: usize) -> Score {
+    if score >= MATE_BOUND {
+        score + ply as Score
+    } else if score <= -MATE_BOUND {
+        score - ply as Score
+    } else {
+        score
+    }
+}
+
+fn score_from_tt(score: Score, ply: usize) -> Score {
+    if score >= MATE_BOUND {
+        score - ply as Score
+    } else if score <= -MATE_BOUND {
+        score + ply as Score
+    } else {
+        score
+    }
+}
+
+// Layout of data: bits 0-11 move, bit 12 move present, bits 16-31 score,
+// bits 32-39 depth, bits 40-41 bound, bit 48 entry present
+fn pack(entry: TtEntry) -> u64 {
+    let move_bits = match entry.best_move {
+        Some((i, j, k, l)) => (1 << 12) | ((i * 8 + j) << 6) as u64 | (k * 8 + l) as u64,
+        None => 0,
+    };
+    move_bits
+        | ((entry.score as i16 as u16 as u64) << 16)
+        | ((entry.depth.clamp(0, 255) as u64) << 32)
+        | ((entry.bound as u64) << 40)
+        | (1 << 48)
+}
+
+fn unpack(data: u64) -> TtEntry {
+    let best_move = if data & (1 << 12) != 0 {
+        let from = ((data >> 6) & 63) as usize;
+        let to = (data & 63) as usize;
+        Some((from / 8, from % 8, to / 8, to % 8))
+    } else {
+        None
+    };
+    let bound = match (data >> 40) & 3 {
+        0 => Bound::Exact,
+        1 => Bound::Lower,
+        _ => Bound::Upper,
+    };
+    TtEntry {
+        best_move,
+        score: ((data >> 16) & 0xffff) as u16 as i16 as Score,
+        depth: ((data >> 32) & 0xff) as i32,
+        bound,
+    }
+}
@@ -0,0 +1,299 @@
+// Texel tuning: the linear part of the evaluation is fitted to game results
+// by minimizing the mean squared error between the results and
+// sigmoid(K * eval / 400), first over K and then over the parameters with
+// Adam. Everything runs in a fixed order, so the same dataset always gives
+// the same parameters.
+
+use super::chess_player::ChessPlayer;
+use super::endgame::{get_scale_factor, probe_endgame, SCALE_NORMAL};
+use super::evaluation::{get_parameters, get_terms, PARAMETER_COUNT, PIECE_SQUARE_OFFSET};
+use super::position::Position;
+
+// The pawn value stays fixed, it sets the scale of all the other parameters
+const FIXED_PARAMETERS: [usize; 1] = [0];
+
+const ADAM_BETA1: f64 = 0.9;
+const ADAM_BETA2: f64 = 0.999;
+const ADAM_EPSILON: f64 = 1e-8;
+
+pub struct Sample {
+    // terms of white minus terms of black
+    terms: Vec<(usize, i32)>,
+    // 1.0 white wins, 0.5 draw, 0.0 black wins
+    result: f64,
+}
+
+fn parse_result(text: &str) -> Option<f64> {
+    match text {
+        "1-0" => Some(1.0),
+        "0-1" => Some(0.0),
+        "1/2-1/2" => Some(0.5),
+        _ => text.parse().ok().filter(|r| (0.0..=1.0).contains(r)),
+    }
+}
+
+// Positions where a capture wins material would be misjudged by a static
+// evaluation, as would known endgames that are not evaluated linearly
+fn is_quiet(pos: &Position) -> bool {
+    let player = pos.get_player_on_move();
+    !pos.is_in_check(player)
+        && probe_endgame(pos).is_none()
+        && get_scale_factor(pos) == SCALE_NORMAL
+        && !pos
+            .get_valid_moves()
+            .iter()
+            .any(|&m| pos.is_capture(m) && pos.see(m) > 0)
+}
+
+// Reads lines "FEN | score | result" as written by datagen, the score is not
+// used. Returns the quiet samples and the number of skipped lines.
+pub fn load_dataset(text: &str) -> (Vec<Sample>, usize) {
+    let mut samples = Vec::new();
+    let mut skipped = 0;
+    for line in text.lines().filter(|line| !line.trim().is_empty()) {
+        let fields: Vec<&str> = line.split('|').map(|field| field.trim()).collect();
+        let pos = Position::from_fen(fields[0]).ok();
+        let result = fields.last().and_then(|&r| parse_result(r));
+        let (pos, result) = match (pos, result) {
+            (Some(pos), Some(result)) if fields.len() >= 2 && is_quiet(&pos) => (pos, result),
+            _ => {
+                skipped += 1;
+                continue;
+            }
+        };
+
+        let mut terms = get_terms(&pos, ChessPlayer::White);
+        terms.extend(
+            get_terms(&pos, ChessPlayer::Black)
+                .into_iter()
+                .map(|(index, count)| (index, -count)),
+        );
+        terms.sort_unstable();
+        let mut merged: Vec<(usize, i32)> = Vec::with_capacity(terms.len());
+        for (index, count) in terms {
+            match merged.last_mut() {
+                Some(last) if last.0 == index => last.1 += count,
+                _ => merged.push((index, count)),
+            }
+        }
+        merged.retain(|&(_, count)| count != 0);
+        samples.push(Sample {
+            terms: merged,
+            result,
+        });
+    }
+    (samples, skipped)
+}
+
+fn sigmoid(k: f64, score: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-k * score / 400.0))
+}
+
+pub struct Tuner {
+    samples: Vec<Sample>,
+    parameters: Vec<f64>,
+    k: f64,
+    // Adam moment estimates
+    first_moments: Vec<f64>,
+    second_moments: Vec<f64>,
+    steps: i32,
+}
+
+impl Tuner {
+    // Starts from the parameters the engine is built with
+    pub fn new(samples: Vec<Sample>) -> Self {
+        Self {
+            samples,
+            parameters: get_parameters().iter().map(|&p| p as f64).collect(),
+            k: 1.0,
+            first_moments: vec![0.0; PARAMETER_COUNT],
+            second_moments: vec![0.0; PARAMETER_COUNT],
+            steps: 0,
+        }
+    }
+
+    fn evaluate(&self, sample: &Sample) -> f64 {
+        sample
+            .terms
+            .iter()
+            .map(|&(index, count)| self.parameters[index] * count as f64)
+            .sum()
+    }
+
+    pub fn get_error(&self, k: f64) -> f64 {
+        let total: f64 = self
+            .samples
+            .iter()
+            .map(|sample| (sample.result - sigmoid(k, self.evaluate(sample))).powi(2))
+            .sum();
+        total / self.samples.len().max(1) as f64
+    }
+
+    pub fn get_k(&self) -> f64 {
+        self.k
+    }
+
+    // Golden section search for the K with the lowest error
+    pub fn fit_k(&mut self) -> f64 {
+        let ratio = (5f64.sqrt() - 1.0) / 2.0;
+        let (mut low, mut high) = (0.01, 10.0);
+        for _ in 0..100 {
+            let a = high - ratio * (high - low);
+            let b = low + ratio * (high - low);
+            if self.get_error(a) < self.get_error(b) {
+                high = b;
+            } else {
+                low = a;
+            }
+        }
+        self.k = (low + high) / 2.0;
+        self.k
+    }
+
+    fn get_gradient(&self) -> Vec<f64> {
+        let mut gradient = vec![0.0; PARAMETER_COUNT];
+        let factor = self.k * 10f64.ln() / 400.0;
+        for sample in self.samples.iter() {
+            let p = sigmoid(self.k, self.evaluate(sample));
+            let slope = -2.0 * (sample.result - p) * p * (1.0 - p) * factor;
+            for &(index, count) in sample.terms.iter() {
+                gradient[index] += slope * count as f64;
+            }
+        }
+        let n = self.samples.len().max(1) as f64;
+        gradient.iter_mut().for_each(|g| *g /= n);
+        gradient
+    }
+
+    // One full batch Adam step, returns the error afterwards
+    pub fn step(&mut self, learning_rate: f64) -> f64 {
+        let gradient = self.get_gradient();
+        self.steps += 1;
+        let correction1 = 1.0 - ADAM_BETA1.powi(self.steps);
+        let correction2 = 1.0 - ADAM_BETA2.powi(self.steps);
+        for (index, &g) in gradient.iter().enumerate() {
+            if FIXED_PARAMETERS.contains(&index) {
+                continue;
+            }
+            let m = &mut self.first_moments[index];
+            *m = ADAM_BETA1 * *m + (1.0 - ADAM_BETA1) * g;
+            let v = &mut self.second_moments[index];
+            *v = ADAM_BETA2 * *v + (1.0 - ADAM_BETA2) * g * g;
+            let m_hat = self.first_moments[index] / correction1;
+            let v_hat = self.second_moments[index] / correction2;
+            self.parameters[index] -= learning_rate * m_hat / (v_hat.sqrt() + ADAM_EPSILON);
+        }
+        self.get_error(self.k)
+    }
+
+    pub fn get_parameters(&self) -> Vec<i32> {
+        self.parameters.iter().map(|p| p.round() as i32).collect()
+    }
+}
+
+// Source of eval_params.rs holding parameters
+pub fn write_parameters_source(parameters: &[i32]) -> String {
+    let mut source = String::new();
+    source.push_str(
+        "// Evaluation parameters, generated by the tune binary. Piece values are\n\
+         // ordered pawn, rook, knight, bishop, queen. Piece-square tables are indexed\n\
+         // by piece (pawn, rook, knight, bishop, queen, king) and square i * 8 + j\n\
+         // seen from white, black squares are mirrored vertically.\n\n",
+    );
+    let values: Vec<String> = parameters[..PIECE_SQUARE_OFFSET]
+        .iter()
+        .map(|v| v.to_string())
+        .collect();
+    source.push_str(&format!(
+        "pub const PIECE_VALUES: [i32; 5] = [{}];\n\n",
+        values.join(", ")
+    ));
+    source.push_str("#[rustfmt::skip]\npub const PIECE_SQUARE_TABLES: [[i32; 64]; 6] = [\n");
+    for table in parameters[PIECE_SQUARE_OFFSET..].chunks(64) {
+        source.push_str("    [\n");
+        for row in table.chunks(8) {
+            let row: Vec<String> = row.iter().map(|v| format!("{:4},", v)).collect();
+            source.push_str(&format!("    {}\n", row.join(" ")));
+        }
+        source.push_str("    ],\n");
+    }
+    source.push_str("];\n");
+    source
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Results are the expected scores under the given K and parameter values,
+    // terms count parameter index 0 (pawn) and 1 (rook)
+    fn get_samples(k: f64, pawn: f64, rook: f64) -> Vec<Sample> {
+        let mut samples = Vec::new();
+        for pawns in -3..=3 {
+            for rooks in -1..=1 {
+                let score = pawn * pawns as f64 + rook * rooks as f64;
+                samples.push(Sample {
+                    terms: vec![(0, pawns), (1, rooks)],
+                    result: sigmoid(k, score),
+                });
+            }
+        }
+        samples
+    }
+
+    // Numbers between "= [" and "];" of the constant name
+    fn parse_constant(source: &str, name: &str) -> Vec<i32> {
+        let start = source.find(&format!("const {}", name)).unwrap();
+        let body = &source[start..];
+        let body = &body[body.find("= [").unwrap() + 3..];
+        body[..body.find("];").unwrap()]
+            .split(|c: char| c != '-' && !c.is_ascii_digit())
+            .filter(|token| !token.is_empty())
+            .map(|token| token.parse().unwrap())
+            .collect()
+    }
+
+    // Source without the header comment
+    fn get_constants(source: &str) -> &str {
+        &source[source.find("pub const").unwrap()..]
+    }
+
+    #[test]
+    fn fit_k_finds_the_scaling_of_the_results() {
+        let mut tuner = Tuner::new(get_samples(1.6, 100.0, 500.0));
+        let k = tuner.fit_k();
+        assert!((k - 1.6).abs() < 0.01, "{}", k);
+        assert!(tuner.get_error(k) < 1e-9);
+        assert!(tuner.get_error(1.0) > tuner.get_error(k));
+    }
+
+    #[test]
+    fn adam_steps_reduce_the_error() {
+        // the rook is worth less in this dataset than in the engine
+        let mut tuner = Tuner::new(get_samples(1.0, 100.0, 300.0));
+        let before = tuner.get_error(tuner.get_k());
+        let mut error = before;
+        for _ in 0..20 {
+            error = tuner.step(5.0);
+        }
+        assert!(error < before / 2.0, "{} {}", before, error);
+        let parameters = tuner.get_parameters();
+        assert_eq!(parameters[0], 100);
+        assert!(parameters[1] < 500);
+    }
+
+    #[test]
+    fn written_source_reads_back() {
+        let parameters: Vec<i32> = (0..PARAMETER_COUNT as i32).map(|i| i * 7 - 1000).collect();
+        let source = write_parameters_source(&parameters);
+        let mut read = parse_constant(&source, "PIECE_VALUES");
+        assert_eq!(read.len(), PIECE_SQUARE_OFFSET);
+        read.extend(parse_constant(&source, "PIECE_SQUARE_TABLES"));
+        assert_eq!(read, parameters);
+
+        // the parameters the engine is built with give back eval_params.rs
+        let source = write_parameters_source(&get_parameters());
+        let built = include_str!("eval_params.rs");
+        assert_eq!(get_constants(&source), get_constants(built));
+    }
+}
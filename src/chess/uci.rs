@@ -0,0 +1,382 @@
+use super::chess_piece::ChessPiece;
+use super::chess_player::ChessPlayer;
+use super::nnue::Network;
+use super::position::{Move, Position};
+use super::search::{
+    Score, SearchInfo, SearchOptions, Searcher, MATE_BOUND, MATE_SCORE, MAX_DEPTH,
+};
+use super::tablebase::Tablebase;
+use super::time_manager::{TimeControl, TimeManager};
+use std::io::BufRead;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+const FILES: &[u8; 8] = b"abcdefgh";
+
+// Formats m in coordinate notation, e.g. e2e4 or e7e8q
+pub fn move_to_string(pos: &Position, m: Move) -> String {
+    let (i, j, k, l) = m;
+    let promotion = if pos.is_promotion(m) { "q" } else { "" };
+    format!(
+        "{}{}{}{}{}",
+        FILES[j] as char,
+        i + 1,
+        FILES[l] as char,
+        k + 1,
+        promotion
+    )
+}
+
+fn parse_square(s: &[u8]) -> Option<(usize, usize)> {
+    let j = FILES.iter().position(|&f| f == s[0])?;
+    let i = (s[1] as char).to_digit(10)?.checked_sub(1)? as usize;
+    if i < 8 {
+        Some((i, j))
+    } else {
+        None
+    }
+}
+
+// Parses a move in coordinate notation, returns None unless it is a valid
+// move in pos. Promotions are always to a queen.
+pub fn parse_move(pos: &Position, s: &str) -> Option<Move> {
+    let bytes = s.as_bytes();
+    if bytes.len() != 4 && bytes.len() != 5 {
+        return None;
+    }
+    let (i, j) = parse_square(&bytes[0..2])?;
+    let (k, l) = parse_square(&bytes[2..4])?;
+    let m = (i, j, k, l);
+    let promotion_valid = match bytes.get(4) {
+        Some(b'q') => pos.is_promotion(m),
+        Some(_) => false,
+        None => !pos.is_promotion(m),
+    };
+    if promotion_valid && pos.get_valid_moves().contains(&m) {
+        Some(m)
+    } else {
+        None
+    }
+}
+
+pub fn score_to_string(score: Score) -> String {
+    if score >= MATE_BOUND {
+        format!("mate {}", (MATE_SCORE - score + 1) / 2)
+    } else if score <= -MATE_BOUND {
+        format!("mate -{}", (MATE_SCORE + score) / 2)
+    } else {
+        format!("cp {}", score)
+    }
+}
+
+// Line of moves starting in pos, in coordinate notation
+pub fn line_to_string(pos: &Position, line: &[Move]) -> String {
+    let mut pos = *pos;
+    let mut res = Vec::with_capacity(line.len());
+    for &m in line {
+        if !pos.get_valid_moves().contains(&m) {
+            break;
+        }
+        res.push(move_to_string(&pos, m));
+        pos.make_move(m.0, m.1, m.2, m.3);
+    }
+    res.join(" ")
+}
+
+// Engine side of the Universal Chess Interface, reads commands from input
+// and writes responses to standard output
+pub struct Uci {
+    pos: Position,
+    // plies since the last capture or pawn move before pos
+    halfmove_clock: u32,
+    options: SearchOptions,
+    move_overhead: Duration,
+    searcher: Option<Searcher>,
+    search_thread: Option<JoinHandle<Searcher>>,
+    stop: Arc<AtomicBool>,
+    // set while a go infinite search holds back its best move until stop
+    infinite: Arc<AtomicBool>,
+    tablebase: Option<Arc<Tablebase>>,
+    network: Option<Arc<Network>>,
+}
+
+impl Uci {
+    pub fn new() -> Self {
+        let options = SearchOptions::default();
+        let searcher = Searcher::with_options(options);
+        Self {
+            pos: Position::default(),
+            halfmove_clock: 0,
+            options,
+            move_overhead: Duration::from_millis(30),
+            stop: searcher.get_stop_handle(),
+            searcher: Some(searcher),
+            search_thread: None,
+            infinite: Arc::new(AtomicBool::new(false)),
+            tablebase: None,
+            network: None,
+        }
+    }
+
+    pub fn run(&mut self, input: impl BufRead) {
+        for line in input.lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(_) => break,
+            };
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            match tokens.first() {
+                Some(&"uci") => self.uci(),
+                Some(&"isready") => println!("readyok"),
+                Some(&"setoption") => self.set_option(&tokens[1..]),
+                Some(&"ucinewgame") => {
+                    self.reset_searcher();
+                    self.pos = Position::default();
+                    self.halfmove_clock = 0;
+                }
+                Some(&"position") => self.position(&tokens[1..]),
+                Some(&"go") => self.go(&tokens[1..]),
+                Some(&"stop") => self.wait_for_search(true),
+                Some(&"quit") => {
+                    self.wait_for_search(true);
+                    break;
+                }
+                Some(command) => println!("info string unknown command {}", command),
+                None => {}
+            }
+        }
+        self.wait_for_search(true);
+    }
+
+    fn uci(&self) {
+        println!("id name chess");
+        println!("id author Aj0SK");
+        println!("option name Threads type spin default 1 min 1 max 256");
+        println!("option name Hash type spin default 16 min 1 max 4096");
+        println!("option name MultiPV type spin default 1 min 1 max 64");
+        println!("option name Move Overhead type spin default 30 min 0 max 5000");
+        println!("option name SyzygyPath type string default <empty>");
+        println!("option name EvalFile type string default <empty>");
+        println!("uciok");
+    }
+
+    // Waits until the running search finishes, stopping it first if asked to
+    fn wait_for_search(&mut self, stop: bool) {
+        if let Some(handle) = self.search_thread.take() {
+            if stop {
+                self.stop.store(true, Ordering::Relaxed);
+                self.infinite.store(false, Ordering::Relaxed);
+            }
+            self.searcher = Some(handle.join().unwrap());
+            // set too late if the search already finished, it would stop the next one
+            self.stop.store(false, Ordering::Relaxed);
+        }
+    }
+
+    fn reset_searcher(&mut self) {
+        self.wait_for_search(true);
+        let mut searcher = Searcher::with_options(self.options);
+        searcher.set_tablebase(self.tablebase.clone());
+        searcher.set_network(self.network.clone());
+        self.stop = searcher.get_stop_handle();
+        self.searcher = Some(searcher);
+    }
+
+    // setoption name <name> value <value>
+    fn set_option(&mut self, tokens: &[&str]) {
+        let value_index = tokens.iter().position(|&t| t == "value");
+        let (name, value) = match (tokens.first(), value_index) {
+            (Some(&"name"), Some(ind)) => (tokens[1..ind].join(" "), tokens[ind + 1..].join(" ")),
+            _ => return,
+        };
+        if name.eq_ignore_ascii_case("SyzygyPath") {
+            self.set_syzygy_path(&value);
+            return;
+        }
+        if name.eq_ignore_ascii_case("EvalFile") {
+            self.set_eval_file(&value);
+            return;
+        }
+        let value: usize = match value.parse() {
+            Ok(value) => value,
+            Err(_) => {
+                println!("info string invalid value {}", value);
+                return;
+            }
+        };
+        match name.to_lowercase().as_str() {
+            "threads" => self.options.threads = value.max(1),
+            "hash" => self.options.hash_size_mb = value.max(1),
+            "multipv" => self.options.multi_pv = value.max(1),
+            "move overhead" => self.move_overhead = Duration::from_millis(value as u64),
+            _ => {
+                println!("info string unknown option {}", name);
+                return;
+            }
+        }
+        self.reset_searcher();
+    }
+
+    fn set_syzygy_path(&mut self, path: &str) {
+        // tablebases can be loaded only once, the old ones have to go first
+        self.wait_for_search(true);
+        if let Some(searcher) = self.searcher.as_mut() {
+            searcher.set_tablebase(None);
+        }
+        self.tablebase = None;
+
+        if !path.is_empty() && path != "<empty>" {
+            match Tablebase::open(Path::new(path)) {
+                Ok(tablebase) => {
+                    println!(
+                        "info string found tablebases with up to {} pieces",
+                        tablebase.get_max_pieces()
+                    );
+                    self.tablebase = Some(Arc::new(tablebase));
+                }
+                Err(err) => println!("info string can't load tablebases: {:?}", err),
+            }
+        }
+        if let Some(searcher) = self.searcher.as_mut() {
+            searcher.set_tablebase(self.tablebase.clone());
+        }
+    }
+
+    // Empty path switches back to the classical evaluation
+    fn set_eval_file(&mut self, path: &str) {
+        self.wait_for_search(true);
+        self.network = None;
+        if !path.is_empty() && path != "<empty>" {
+            match Network::open(Path::new(path)) {
+                Ok(network) => {
+                    println!(
+                        "info string loaded {:?} network with {} hidden neurons",
+                        network.get_feature_set(),
+                        network.get_hidden_size()
+                    );
+                    self.network = Some(Arc::new(network));
+                }
+                Err(err) => println!("info string can't load network: {}", err),
+            }
+        }
+        if let Some(searcher) = self.searcher.as_mut() {
+            searcher.set_network(self.network.clone());
+        }
+    }
+
+    // position (startpos | fen <fen>) [moves <move>...]
+    fn position(&mut self, tokens: &[&str]) {
+        let moves_index = tokens.iter().position(|&t| t == "moves");
+        let (mut pos, mut halfmove_clock) = match tokens.first() {
+            Some(&"startpos") => (Position::default(), 0),
+            Some(&"fen") => {
+                let fen = &tokens[1..moves_index.unwrap_or(tokens.len())];
+                let halfmove_clock = fen.get(4).and_then(|c| c.parse().ok()).unwrap_or(0);
+                match Position::from_fen(&fen.join(" ")) {
+                    Ok(pos) => (pos, halfmove_clock),
+                    Err(err) => {
+                        println!("info string invalid position: {}", err);
+                        return;
+                    }
+                }
+            }
+            _ => {
+                println!("info string expected startpos or fen");
+                return;
+            }
+        };
+        if let Some(ind) = moves_index {
+            for s in tokens[ind + 1..].iter() {
+                match parse_move(&pos, s) {
+                    Some(m) => {
+                        let resets_clock = pos.is_capture(m)
+                            || pos.get_piece_on_position(m.0, m.1) == Some(ChessPiece::Pawn);
+                        halfmove_clock = if resets_clock { 0 } else { halfmove_clock + 1 };
+                        pos.make_move(m.0, m.1, m.2, m.3);
+                    }
+                    None => {
+                        println!("info string invalid move {}", s);
+                        break;
+                    }
+                }
+            }
+        }
+        self.pos = pos;
+        self.halfmove_clock = halfmove_clock;
+    }
+
+    fn go(&mut self, tokens: &[&str]) {
+        self.wait_for_search(true);
+
+        let get_value = |name: &str| -> Option<u64> {
+            let ind = tokens.iter().position(|&t| t == name)?;
+            tokens.get(ind + 1)?.parse().ok()
+        };
+        let (time, increment) = match self.pos.get_player_on_move() {
+            ChessPlayer::White => (get_value("wtime"), get_value("winc")),
+            ChessPlayer::Black => (get_value("btime"), get_value("binc")),
+        };
+        let time_manager = if let Some(move_time) = get_value("movetime") {
+            Some(TimeManager::with_move_time(
+                Duration::from_millis(move_time).saturating_sub(self.move_overhead),
+            ))
+        } else {
+            time.map(|time| {
+                let time_control = TimeControl {
+                    remaining: Duration::from_millis(time),
+                    increment: Duration::from_millis(increment.unwrap_or(0)),
+                    moves_to_go: get_value("movestogo").map(|m| m as u32),
+                    move_overhead: self.move_overhead,
+                };
+                TimeManager::new(&time_control, &self.pos)
+            })
+        };
+        let depth = get_value("depth").map_or(MAX_DEPTH, |d| d as u32);
+        let nodes = get_value("nodes");
+        let infinite = self.infinite.clone();
+        infinite.store(tokens.contains(&"infinite"), Ordering::Relaxed);
+
+        let pos = self.pos;
+        let mut searcher = self.searcher.take().unwrap();
+        searcher.set_halfmove_clock(self.halfmove_clock);
+        searcher.set_info_callback(Box::new(move |info: &SearchInfo| {
+            let millis = info.time.as_millis().max(1);
+            println!(
+                "info depth {} multipv {} score {} nodes {} nps {} time {} pv {}",
+                info.depth,
+                info.multi_pv,
+                score_to_string(info.score),
+                info.nodes,
+                info.nodes as u128 * 1000 / millis,
+                millis,
+                line_to_string(&pos, &info.pv)
+            );
+        }));
+        self.search_thread = Some(thread::spawn(move || {
+            let (best_move, _) = match (time_manager, nodes) {
+                (Some(time_manager), _) => searcher.search_timed(&pos, depth, time_manager),
+                (None, Some(nodes)) => searcher.search_nodes(&pos, nodes),
+                (None, None) => searcher.search(&pos, depth),
+            };
+            // the protocol forbids a best move before stop, even if the
+            // search ran out of depth
+            while infinite.load(Ordering::Relaxed) {
+                thread::sleep(Duration::from_millis(1));
+            }
+            match best_move {
+                Some(m) => println!("bestmove {}", move_to_string(&pos, m)),
+                None => println!("bestmove 0000"),
+            }
+            searcher
+        }));
+    }
+}
+
+impl Default for Uci {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,357 @@
+use super::position::{Move, Position};
+use super::uci::{move_to_string, parse_move};
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// time an engine gets to answer uci and isready
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+// time an engine gets to send bestmove once it was told to stop
+const STOP_TIMEOUT: Duration = Duration::from_secs(1);
+// time an engine gets to exit after quit before it is killed
+const QUIT_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum EngineScore {
+    Centipawns(i32),
+    // moves to mate, negative when the engine is getting mated
+    Mate(i32),
+}
+
+// One info line of an engine, fields it did not send are None
+#[derive(Debug, Clone, Default)]
+pub struct EngineInfo {
+    pub depth: Option<u32>,
+    pub seldepth: Option<u32>,
+    pub multi_pv: Option<usize>,
+    pub score: Option<EngineScore>,
+    pub nodes: Option<u64>,
+    pub time: Option<Duration>,
+    // moves of the principal variation up to the first one that is not valid
+    pub pv: Vec<Move>,
+}
+
+#[derive(Debug, Clone)]
+pub struct EngineResult {
+    // None if the engine had no move to play
+    pub best_move: Option<Move>,
+    pub ponder: Option<Move>,
+    // info lines sent during the search, in order
+    pub infos: Vec<EngineInfo>,
+}
+
+impl EngineResult {
+    // Last info line with a score, the one belonging to the best move
+    pub fn get_last_info(&self) -> Option<&EngineInfo> {
+        self.infos
+            .iter()
+            .rev()
+            .find(|info| info.score.is_some() && info.multi_pv.unwrap_or(1) == 1)
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub enum SearchLimit {
+    Depth(u32),
+    Nodes(u64),
+    MoveTime(Duration),
+    Clock {
+        white_time: Duration,
+        black_time: Duration,
+        white_increment: Duration,
+        black_increment: Duration,
+        moves_to_go: Option<u32>,
+    },
+    Infinite,
+}
+
+impl SearchLimit {
+    fn to_command(self) -> String {
+        match self {
+            SearchLimit::Depth(depth) => format!("go depth {}", depth),
+            SearchLimit::Nodes(nodes) => format!("go nodes {}", nodes),
+            SearchLimit::MoveTime(time) => format!("go movetime {}", time.as_millis()),
+            SearchLimit::Clock {
+                white_time,
+                black_time,
+                white_increment,
+                black_increment,
+                moves_to_go,
+            } => {
+                let mut command = format!(
+                    "go wtime {} btime {} winc {} binc {}",
+                    white_time.as_millis(),
+                    black_time.as_millis(),
+                    white_increment.as_millis(),
+                    black_increment.as_millis()
+                );
+                if let Some(moves_to_go) = moves_to_go {
+                    command.push_str(&format!(" movestogo {}", moves_to_go));
+                }
+                command
+            }
+            SearchLimit::Infinite => "go infinite".to_string(),
+        }
+    }
+}
+
+// Number of values following a field of an info line
+fn get_arity(field: &str) -> usize {
+    match field {
+        "lowerbound" | "upperbound" => 0,
+        "score" => 2,
+        "wdl" => 3,
+        _ => 1,
+    }
+}
+
+// Parses one info line in pos, None for lines without search information
+// such as info string
+pub fn parse_info(pos: &Position, line: &str) -> Option<EngineInfo> {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    if tokens.first() != Some(&"info") || tokens.contains(&"string") {
+        return None;
+    }
+    let mut info = EngineInfo::default();
+    let mut k = 1;
+    while k < tokens.len() {
+        let value = tokens.get(k + 1).copied().unwrap_or("");
+        match tokens[k] {
+            "depth" => info.depth = value.parse().ok(),
+            "seldepth" => info.seldepth = value.parse().ok(),
+            "multipv" => info.multi_pv = value.parse().ok(),
+            "nodes" => info.nodes = value.parse().ok(),
+            "time" => info.time = value.parse().ok().map(Duration::from_millis),
+            "score" => {
+                let score = tokens.get(k + 2).and_then(|s| s.parse().ok());
+                info.score = match (value, score) {
+                    ("cp", Some(score)) => Some(EngineScore::Centipawns(score)),
+                    ("mate", Some(score)) => Some(EngineScore::Mate(score)),
+                    _ => None,
+                };
+            }
+            // the rest of the line is the principal variation
+            "pv" => {
+                let mut pos = *pos;
+                for s in tokens[k + 1..].iter() {
+                    match parse_move(&pos, s) {
+                        Some(m) => {
+                            pos.make_move(m.0, m.1, m.2, m.3);
+                            info.pv.push(m);
+                        }
+                        None => break,
+                    }
+                }
+                break;
+            }
+            // move lists of unknown length
+            "currline" | "refutation" => break,
+            _ => {}
+        }
+        k += 1 + get_arity(tokens[k]);
+    }
+    Some(info)
+}
+
+// Client of an external engine speaking the Universal Chess Interface. The
+// engine runs as a child process, its output is read by a separate thread
+// so every wait can time out.
+pub struct UciEngine {
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+    name: String,
+    author: String,
+    options: Vec<String>,
+    // position set by the last set_position, moves are validated in it
+    pos: Position,
+}
+
+impl UciEngine {
+    // Starts the engine and waits for uciok and readyok
+    pub fn start(path: &str, args: &[String]) -> Result<Self, String> {
+        let mut child = Command::new(path)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| format!("can't start {}: {}", path, e))?;
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut engine = Self {
+            child,
+            stdin,
+            lines,
+            name: path.to_string(),
+            author: String::new(),
+            options: Vec::new(),
+            pos: Position::default(),
+        };
+        engine.send("uci")?;
+        let deadline = Instant::now() + HANDSHAKE_TIMEOUT;
+        loop {
+            let line = engine.read_line(deadline)?;
+            if let Some(name) = line.strip_prefix("id name ") {
+                engine.name = name.trim().to_string();
+            } else if let Some(author) = line.strip_prefix("id author ") {
+                engine.author = author.trim().to_string();
+            } else if let Some(option) = line.strip_prefix("option name ") {
+                let name = option.split(" type ").next().unwrap_or(option);
+                engine.options.push(name.trim().to_string());
+            } else if line.trim() == "uciok" {
+                break;
+            }
+        }
+        engine.wait_ready()?;
+        Ok(engine)
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_author(&self) -> &str {
+        &self.author
+    }
+
+    // Names of the options the engine announced
+    pub fn get_options(&self) -> &[String] {
+        &self.options
+    }
+
+    fn send(&mut self, command: &str) -> Result<(), String> {
+        writeln!(self.stdin, "{}", command)
+            .and_then(|_| self.stdin.flush())
+            .map_err(|e| format!("{}: can't send {}: {}", self.name, command, e))
+    }
+
+    fn read_line(&self, deadline: Instant) -> Result<String, String> {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        match self.lines.recv_timeout(timeout) {
+            Ok(line) => Ok(line),
+            Err(RecvTimeoutError::Timeout) => Err(format!("{}: timed out", self.name)),
+            Err(RecvTimeoutError::Disconnected) => Err(format!("{}: engine exited", self.name)),
+        }
+    }
+
+    pub fn wait_ready(&mut self) -> Result<(), String> {
+        self.send("isready")?;
+        let deadline = Instant::now() + HANDSHAKE_TIMEOUT;
+        while self.read_line(deadline)?.trim() != "readyok" {}
+        Ok(())
+    }
+
+    pub fn set_option(&mut self, name: &str, value: &str) -> Result<(), String> {
+        self.send(&format!("setoption name {} value {}", name, value))
+    }
+
+    pub fn new_game(&mut self) -> Result<(), String> {
+        self.send("ucinewgame")?;
+        self.wait_ready()
+    }
+
+    // Sets the position reached by playing moves from start, the moves have
+    // to be valid
+    pub fn set_position(&mut self, start: &Position, moves: &[Move]) -> Result<(), String> {
+        let mut command = if start.to_fen() == Position::default().to_fen() {
+            "position startpos".to_string()
+        } else {
+            format!("position fen {}", start.to_fen())
+        };
+        let mut pos = *start;
+        if !moves.is_empty() {
+            command.push_str(" moves");
+        }
+        for &m in moves {
+            if !pos.get_valid_moves().contains(&m) {
+                return Err(format!("{}: invalid move in position", self.name));
+            }
+            command.push(' ');
+            command.push_str(&move_to_string(&pos, m));
+            pos.make_move(m.0, m.1, m.2, m.3);
+        }
+        self.pos = pos;
+        self.send(&command)
+    }
+
+    // Searches the position set last and waits for bestmove. After timeout
+    // the engine is told to stop, it fails if it does not answer even then.
+    pub fn go(&mut self, limit: SearchLimit, timeout: Duration) -> Result<EngineResult, String> {
+        self.send(&limit.to_command())?;
+        let mut deadline = Instant::now() + timeout;
+        let mut stopped = false;
+        let mut infos = Vec::new();
+        loop {
+            let line = match self.read_line(deadline) {
+                Ok(line) => line,
+                Err(_) if !stopped => {
+                    self.send("stop")?;
+                    stopped = true;
+                    deadline = Instant::now() + STOP_TIMEOUT;
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            if let Some(info) = parse_info(&self.pos, &line) {
+                infos.push(info);
+                continue;
+            }
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            if tokens.first() != Some(&"bestmove") {
+                continue;
+            }
+            let best_move = match tokens.get(1) {
+                None | Some(&"0000") | Some(&"(none)") => None,
+                Some(s) => Some(
+                    parse_move(&self.pos, s)
+                        .ok_or_else(|| format!("{}: invalid best move {}", self.name, s))?,
+                ),
+            };
+            let ponder = match (best_move, tokens.get(2), tokens.get(3)) {
+                (Some(m), Some(&"ponder"), Some(s)) => {
+                    let mut next = self.pos;
+                    next.make_move(m.0, m.1, m.2, m.3);
+                    parse_move(&next, s)
+                }
+                _ => None,
+            };
+            return Ok(EngineResult {
+                best_move,
+                ponder,
+                infos,
+            });
+        }
+    }
+
+    // Asks the engine to exit, kills it if it does not
+    pub fn quit(&mut self) {
+        let _ = self.send("quit");
+        let deadline = Instant::now() + QUIT_TIMEOUT;
+        while Instant::now() < deadline {
+            match self.child.try_wait() {
+                Ok(Some(_)) | Err(_) => return,
+                Ok(None) => thread::sleep(Duration::from_millis(10)),
+            }
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+impl Drop for UciEngine {
+    fn drop(&mut self) {
+        self.quit();
+    }
+}
@@ -0,0 +1,347 @@
+use super::chess_player::ChessPlayer;
+use super::game::{Game, GameStatus};
+use super::search::{Score, SearchInfo, Searcher, MATE_BOUND, MATE_SCORE, MAX_DEPTH};
+use super::time_manager::{TimeControl, TimeManager};
+use super::uci::{line_to_string, move_to_string, parse_move};
+use std::io::BufRead;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+// Mates are reported as 100000 + moves to mate, the convention of xboard
+const XBOARD_MATE_SCORE: i32 = 100000;
+
+fn score_to_xboard(score: Score) -> i32 {
+    if score >= MATE_BOUND {
+        XBOARD_MATE_SCORE + (MATE_SCORE - score + 1) / 2
+    } else if score <= -MATE_BOUND {
+        -XBOARD_MATE_SCORE - (MATE_SCORE + score) / 2
+    } else {
+        score
+    }
+}
+
+// Base time of the level command, minutes or minutes:seconds
+fn parse_base_time(s: &str) -> Option<Duration> {
+    let (minutes, seconds) = match s.split_once(':') {
+        Some((minutes, seconds)) => (minutes.parse::<u64>().ok()?, seconds.parse::<u64>().ok()?),
+        None => (s.parse::<u64>().ok()?, 0),
+    };
+    Some(Duration::from_secs(minutes * 60 + seconds))
+}
+
+fn parse_seconds(s: &str) -> Option<Duration> {
+    s.parse::<f64>()
+        .ok()
+        .filter(|&seconds| seconds >= 0.0)
+        .map(Duration::from_secs_f64)
+}
+
+// Result claim sent once the game is over
+fn print_result(status: GameStatus) {
+    if status.is_over() {
+        println!("{} {{{}}}", status.get_result(), status);
+    }
+}
+
+// Engine side of the Chess Engine Communication Protocol (xboard), reads
+// commands from input and writes responses to standard output
+pub struct Xboard {
+    // shared with the search thread, which plays the move it finds
+    game: Arc<Mutex<Game>>,
+    // side played by the engine, None in force mode
+    engine_player: Option<ChessPlayer>,
+    post: bool,
+    max_depth: u32,
+    // fixed time per move set by st, overrides the clock
+    move_time: Option<Duration>,
+    // level <moves per session> <base> <increment>, 0 moves means the whole
+    // game
+    moves_per_session: u32,
+    base_time: Duration,
+    increment: Duration,
+    engine_time: Duration,
+    opponent_time: Duration,
+    searcher: Option<Searcher>,
+    search_thread: Option<JoinHandle<Searcher>>,
+    stop: Arc<AtomicBool>,
+    // set when the running search must not play its move
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Xboard {
+    pub fn new() -> Self {
+        let searcher = Searcher::new();
+        let base_time = Duration::from_secs(300);
+        Self {
+            game: Arc::new(Mutex::new(Game::default())),
+            engine_player: Some(ChessPlayer::Black),
+            post: false,
+            max_depth: MAX_DEPTH,
+            move_time: None,
+            moves_per_session: 40,
+            base_time,
+            increment: Duration::default(),
+            engine_time: base_time,
+            opponent_time: base_time,
+            stop: searcher.get_stop_handle(),
+            searcher: Some(searcher),
+            search_thread: None,
+            cancelled: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn run(&mut self, input: impl BufRead) {
+        for line in input.lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(_) => break,
+            };
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            let argument = tokens.get(1).copied().unwrap_or("");
+            match tokens.first() {
+                Some(&"xboard") => {}
+                Some(&"protover") => self.protover(),
+                Some(&"new") => self.new_game(),
+                Some(&"quit") => break,
+                Some(&"force") => {
+                    self.cancel_search();
+                    self.engine_player = None;
+                }
+                Some(&"go") => {
+                    self.cancel_search();
+                    self.engine_player = Some(self.get_player_on_move());
+                    self.start_search_if_on_move();
+                }
+                Some(&"playother") => {
+                    self.cancel_search();
+                    self.engine_player = Some(self.get_player_on_move().get_opponent());
+                }
+                Some(&"usermove") => self.user_move(argument),
+                Some(&"?") => self.wait_for_search(true),
+                Some(&"setboard") => self.set_board(&tokens[1..].join(" ")),
+                Some(&"undo") => self.undo(1),
+                Some(&"remove") => self.undo(2),
+                Some(&"result") => {
+                    self.cancel_search();
+                    self.engine_player = None;
+                }
+                Some(&"level") => self.level(&tokens[1..]),
+                Some(&"st") => match parse_seconds(argument) {
+                    Some(move_time) => self.move_time = Some(move_time),
+                    None => println!("Error (invalid time): {}", argument),
+                },
+                Some(&"sd") => match argument.parse::<u32>() {
+                    Ok(depth) => self.max_depth = depth.clamp(1, MAX_DEPTH),
+                    Err(_) => println!("Error (invalid depth): {}", argument),
+                },
+                // clocks are given in centiseconds
+                Some(&"time") => match argument.parse::<u64>() {
+                    Ok(time) => self.engine_time = Duration::from_millis(time * 10),
+                    Err(_) => println!("Error (invalid time): {}", argument),
+                },
+                Some(&"otim") => match argument.parse::<u64>() {
+                    Ok(time) => self.opponent_time = Duration::from_millis(time * 10),
+                    Err(_) => println!("Error (invalid time): {}", argument),
+                },
+                Some(&"post") => self.post = true,
+                Some(&"nopost") => self.post = false,
+                Some(&"ping") => println!("pong {}", argument),
+                Some(&"accepted") | Some(&"rejected") | Some(&"random") | Some(&"hard")
+                | Some(&"easy") | Some(&"computer") | Some(&"name") | Some(&"rating")
+                | Some(&"ics") => {}
+                // without the usermove feature moves come on their own
+                Some(command) => {
+                    let pos = *self.game.lock().unwrap().get_position();
+                    if parse_move(&pos, command).is_some() {
+                        self.user_move(command);
+                    } else {
+                        println!("Error (unknown command): {}", command);
+                    }
+                }
+                None => {}
+            }
+        }
+        self.cancel_search();
+    }
+
+    fn protover(&self) {
+        println!(
+            "feature myname=\"chess\" setboard=1 usermove=1 time=1 ping=1 playother=1 \
+             san=0 colors=0 sigint=0 sigterm=0 analyze=0 reuse=1"
+        );
+        println!("feature done=1");
+    }
+
+    fn get_player_on_move(&self) -> ChessPlayer {
+        self.game
+            .lock()
+            .unwrap()
+            .get_position()
+            .get_player_on_move()
+    }
+
+    // Waits until the running search finishes and plays its move, stopping
+    // it first if asked to
+    fn wait_for_search(&mut self, stop: bool) {
+        if let Some(handle) = self.search_thread.take() {
+            if stop {
+                self.stop.store(true, Ordering::Relaxed);
+            }
+            self.searcher = Some(handle.join().unwrap());
+            // set too late if the search already finished, it would stop the next one
+            self.stop.store(false, Ordering::Relaxed);
+        }
+    }
+
+    // Stops the running search without playing its move
+    fn cancel_search(&mut self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+        self.wait_for_search(true);
+        self.cancelled.store(false, Ordering::Relaxed);
+    }
+
+    fn new_game(&mut self) {
+        self.cancel_search();
+        *self.game.lock().unwrap() = Game::default();
+        self.engine_player = Some(ChessPlayer::Black);
+        self.max_depth = MAX_DEPTH;
+        self.move_time = None;
+        self.engine_time = self.base_time;
+        self.opponent_time = self.base_time;
+        if let Some(searcher) = self.searcher.as_mut() {
+            searcher.clear();
+        }
+    }
+
+    fn set_board(&mut self, fen: &str) {
+        self.cancel_search();
+        match Game::from_fen(fen) {
+            Ok(game) => *self.game.lock().unwrap() = game,
+            Err(err) => println!("tellusererror Illegal position: {}", err),
+        }
+    }
+
+    fn undo(&mut self, plies: usize) {
+        self.cancel_search();
+        let mut game = self.game.lock().unwrap();
+        for _ in 0..plies {
+            game.undo_move();
+        }
+    }
+
+    // level <moves per session> <base> <increment>
+    fn level(&mut self, tokens: &[&str]) {
+        let moves = tokens.first().and_then(|m| m.parse::<u32>().ok());
+        let base = tokens.get(1).and_then(|b| parse_base_time(b));
+        let increment = tokens.get(2).and_then(|i| parse_seconds(i));
+        match (moves, base, increment) {
+            (Some(moves), Some(base), Some(increment)) => {
+                self.moves_per_session = moves;
+                self.base_time = base;
+                self.increment = increment;
+                self.engine_time = base;
+                self.opponent_time = base;
+                self.move_time = None;
+            }
+            _ => println!("Error (invalid level): {}", tokens.join(" ")),
+        }
+    }
+
+    fn user_move(&mut self, s: &str) {
+        self.wait_for_search(true);
+        {
+            let mut game = self.game.lock().unwrap();
+            let pos = *game.get_position();
+            match parse_move(&pos, s) {
+                Some(m) => game.make_move(m),
+                None => {
+                    println!("Illegal move: {}", s);
+                    return;
+                }
+            };
+        }
+        self.start_search_if_on_move();
+    }
+
+    fn get_time_manager(&self, game: &Game) -> TimeManager {
+        if let Some(move_time) = self.move_time {
+            return TimeManager::with_move_time(move_time);
+        }
+        // moves left until the next time control, counted from the first move
+        let moves_to_go = match self.moves_per_session {
+            0 => None,
+            moves => Some(moves - (game.get_fullmove_number() - 1) % moves),
+        };
+        // half of a lead on the opponent's clock is kept in reserve
+        let lead = self.engine_time.saturating_sub(self.opponent_time);
+        let time_control = TimeControl {
+            remaining: self.engine_time - lead / 2,
+            increment: self.increment,
+            moves_to_go,
+            move_overhead: Duration::from_millis(30),
+        };
+        TimeManager::new(&time_control, game.get_position())
+    }
+
+    // Thinks about the current position if the engine is on move and the
+    // game goes on, the move is played as soon as it is found. Outside of
+    // force mode a finished game is claimed instead.
+    fn start_search_if_on_move(&mut self) {
+        if self.engine_player.is_none() {
+            return;
+        }
+        let (pos, halfmove_clock, time_manager) = {
+            let game = self.game.lock().unwrap();
+            let status = game.get_status();
+            if status.is_over() {
+                print_result(status);
+                return;
+            }
+            let pos = *game.get_position();
+            if self.engine_player != Some(pos.get_player_on_move()) {
+                return;
+            }
+            (pos, game.get_halfmove_clock(), self.get_time_manager(&game))
+        };
+
+        let mut searcher = self.searcher.take().unwrap();
+        searcher.set_halfmove_clock(halfmove_clock);
+        if self.post {
+            searcher.set_info_callback(Box::new(move |info: &SearchInfo| {
+                println!(
+                    "{} {} {} {} {}",
+                    info.depth,
+                    score_to_xboard(info.score),
+                    info.time.as_millis() / 10,
+                    info.nodes,
+                    line_to_string(&pos, &info.pv)
+                );
+            }));
+        } else {
+            searcher.set_info_callback(Box::new(|_: &SearchInfo| {}));
+        }
+
+        let depth = self.max_depth;
+        let game = self.game.clone();
+        let cancelled = self.cancelled.clone();
+        self.search_thread = Some(thread::spawn(move || {
+            let (best_move, _) = searcher.search_timed(&pos, depth, time_manager);
+            // checked under the lock, a cancelled search never plays
+            let mut game = game.lock().unwrap();
+            if let Some(m) = best_move.filter(|_| !cancelled.load(Ordering::Relaxed)) {
+                println!("move {}", move_to_string(&pos, m));
+                game.make_move(m);
+                print_result(game.get_status());
+            }
+            searcher
+        }));
+    }
+}
+
+impl Default for Xboard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
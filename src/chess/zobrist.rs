@@ -0,0 +1,24 @@
+use lazy_static::lazy_static;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+const ZOBRIST_SEED: u64 = 0x5eed_c4e5_5000_0001;
+
+lazy_static! {
+    // indexed by [player][piece][square]
+    pub static ref ZOBRIST_PIECES: [[[u64; 64]; 6]; 2] = get_piece_keys();
+    pub static ref ZOBRIST_BLACK_ON_MOVE: u64 = StdRng::seed_from_u64(!ZOBRIST_SEED).gen();
+}
+
+fn get_piece_keys() -> [[[u64; 64]; 6]; 2] {
+    let mut rng = StdRng::seed_from_u64(ZOBRIST_SEED);
+    let mut res = [[[0; 64]; 6]; 2];
+    for player_keys in res.iter_mut() {
+        for piece_keys in player_keys.iter_mut() {
+            for key in piece_keys.iter_mut() {
+                *key = rng.gen();
+            }
+        }
+    }
+    res
+}
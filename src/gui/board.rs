@@ -0,0 +1,283 @@
+use chess::chess::chess_piece::ChessPiece;
+use chess::chess::chess_player::ChessPlayer;
+use chess::chess::position::{Move, Position};
+use num::ToPrimitive;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::{Canvas, Texture};
+use sdl2::video::Window;
+use std::time::Duration;
+
+use super::font::{draw_text, get_text_size};
+
+pub const WIDTH: u32 = 800;
+pub const HEIGHT: u32 = 800;
+pub const SQUARE_WIDTH: i32 = (WIDTH / 8) as i32;
+pub const SQUARE_HEIGHT: i32 = (HEIGHT / 8) as i32;
+// pixel size of the board labels
+const LABEL_SCALE: u32 = 2;
+
+const LIGHT_SQUARE_COLOR: Color = Color::RGB(240, 217, 181);
+const DARK_SQUARE_COLOR: Color = Color::RGB(181, 136, 99);
+// drawn over the squares, so translucent
+pub const LAST_MOVE_COLOR: Color = Color::RGBA(205, 210, 106, 150);
+const SELECTED_COLOR: Color = Color::RGBA(20, 85, 30, 110);
+const DESTINATION_COLOR: Color = Color::RGBA(20, 85, 30, 140);
+pub const HIGHLIGHT_COLOR: Color = Color::RGB(255, 215, 0);
+
+// Square (rank, file) under the point x, y of the window. A flipped board
+// is seen from the side of black.
+pub fn get_square_at(x: i32, y: i32, flipped: bool) -> Option<(usize, usize)> {
+    if x < 0 || y < 0 || x >= WIDTH as i32 || y >= HEIGHT as i32 {
+        return None;
+    }
+    let (i, j) = (
+        7 - (y / SQUARE_HEIGHT) as usize,
+        (x / SQUARE_WIDTH) as usize,
+    );
+    if flipped {
+        Some((7 - i, 7 - j))
+    } else {
+        Some((i, j))
+    }
+}
+
+// a1 is a dark square
+fn get_square_color(i: usize, j: usize) -> Color {
+    if (i + j).is_multiple_of(2) {
+        DARK_SQUARE_COLOR
+    } else {
+        LIGHT_SQUARE_COLOR
+    }
+}
+
+// Window rectangle of the square (rank, file)
+pub fn get_square_rect(i: usize, j: usize, flipped: bool) -> Rect {
+    let (i, j) = if flipped { (7 - i, 7 - j) } else { (i, j) };
+    Rect::new(
+        j as i32 * SQUARE_WIDTH,
+        (7 - i as i32) * SQUARE_HEIGHT,
+        SQUARE_WIDTH as u32,
+        SQUARE_HEIGHT as u32,
+    )
+}
+
+pub fn get_texture_index(player: ChessPlayer, piece: ChessPiece) -> usize {
+    piece.to_usize().unwrap() + 6 * player as usize
+}
+
+// Piece picked up by the human together with its moves
+pub struct Selection {
+    square: Option<(usize, usize)>,
+    // the piece follows the cursor while the button is held
+    dragging: bool,
+    mouse_position: (i32, i32),
+    moves: Vec<Move>,
+}
+
+impl Selection {
+    pub fn new() -> Self {
+        Self {
+            square: None,
+            dragging: false,
+            mouse_position: (0, 0),
+            moves: Vec::new(),
+        }
+    }
+
+    pub fn get_square(&self) -> Option<(usize, usize)> {
+        self.square
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.dragging
+    }
+
+    pub fn set_mouse_position(&mut self, x: i32, y: i32) {
+        self.mouse_position = (x, y);
+    }
+
+    // Picks up the piece of pos on square and starts dragging it
+    pub fn pick(&mut self, pos: &Position, square: (usize, usize)) {
+        self.square = Some(square);
+        self.dragging = true;
+        self.moves = pos
+            .get_valid_moves()
+            .into_iter()
+            .filter(|&(i, j, _, _)| (i, j) == square)
+            .collect();
+    }
+
+    // Stops dragging, the piece stays selected
+    pub fn release(&mut self) {
+        self.dragging = false;
+    }
+
+    pub fn clear(&mut self) {
+        self.square = None;
+        self.dragging = false;
+        self.moves.clear();
+    }
+}
+
+// Draws the board with pos on it, last_move and the selection highlighted
+pub fn draw_board(
+    canvas: &mut Canvas<Window>,
+    textures: &[Texture],
+    pos: &Position,
+    flipped: bool,
+    last_move: Option<Move>,
+    selection: &Selection,
+) {
+    for i in 0..8 {
+        for j in 0..8 {
+            canvas.set_draw_color(get_square_color(i, j));
+            canvas.fill_rect(get_square_rect(i, j, flipped)).unwrap();
+        }
+    }
+
+    canvas.set_draw_color(LAST_MOVE_COLOR);
+    if let Some((from_i, from_j, to_i, to_j)) = last_move {
+        canvas
+            .fill_rect(get_square_rect(from_i, from_j, flipped))
+            .unwrap();
+        canvas
+            .fill_rect(get_square_rect(to_i, to_j, flipped))
+            .unwrap();
+    }
+    if let Some((i, j)) = selection.square {
+        canvas.set_draw_color(SELECTED_COLOR);
+        canvas.fill_rect(get_square_rect(i, j, flipped)).unwrap();
+    }
+    draw_check(canvas, pos, flipped);
+
+    for i in 0..8 {
+        for j in 0..8 {
+            if selection.dragging && selection.square == Some((i, j)) {
+                continue;
+            }
+            if let (Some(player), Some(piece)) = (
+                pos.get_player_on_position(i, j),
+                pos.get_piece_on_position(i, j),
+            ) {
+                let num = get_texture_index(player, piece);
+                canvas
+                    .copy(&textures[num], None, get_square_rect(i, j, flipped))
+                    .unwrap();
+            }
+        }
+    }
+
+    draw_destinations(canvas, pos, flipped, &selection.moves);
+    draw_labels(canvas, flipped);
+
+    if let (true, Some((i, j))) = (selection.dragging, selection.square) {
+        let player = pos.get_player_on_position(i, j).unwrap();
+        let piece = pos.get_piece_on_position(i, j).unwrap();
+        let num = get_texture_index(player, piece);
+        let (x, y) = selection.mouse_position;
+        canvas
+            .copy(
+                &textures[num],
+                None,
+                Rect::new(
+                    x - SQUARE_WIDTH / 2,
+                    y - SQUARE_HEIGHT / 2,
+                    WIDTH / 8,
+                    HEIGHT / 8,
+                ),
+            )
+            .unwrap();
+    }
+}
+
+// Nested translucent squares on the king in check, the glow is strongest
+// in the middle
+fn draw_check(canvas: &mut Canvas<Window>, pos: &Position, flipped: bool) {
+    let player = pos.get_player_on_move();
+    if !pos.is_in_check(player) {
+        return;
+    }
+    let king = pos
+        .get_piece_type_by_player(ChessPiece::King, player)
+        .get_ones()[0];
+    let rect = get_square_rect(king / 8, king % 8, flipped);
+    canvas.set_draw_color(Color::RGBA(255, 0, 0, 40));
+    for k in 0..6 {
+        let inset = k * SQUARE_WIDTH / 14;
+        let size = (SQUARE_WIDTH - 2 * inset) as u32;
+        canvas
+            .fill_rect(Rect::new(rect.x() + inset, rect.y() + inset, size, size))
+            .unwrap();
+    }
+}
+
+// A dot on empty squares, a frame around pieces that can be taken
+fn draw_destinations(canvas: &mut Canvas<Window>, pos: &Position, flipped: bool, moves: &[Move]) {
+    canvas.set_draw_color(DESTINATION_COLOR);
+    for &m in moves.iter() {
+        let rect = get_square_rect(m.2, m.3, flipped);
+        if pos.is_capture(m) {
+            let width = SQUARE_WIDTH as u32 / 12;
+            let (x, y) = (rect.x(), rect.y());
+            let (w, h) = (rect.width(), rect.height());
+            canvas
+                .fill_rects(&[
+                    Rect::new(x, y, w, width),
+                    Rect::new(x, y + (h - width) as i32, w, width),
+                    Rect::new(x, y + width as i32, width, h - 2 * width),
+                    Rect::new(
+                        x + (w - width) as i32,
+                        y + width as i32,
+                        width,
+                        h - 2 * width,
+                    ),
+                ])
+                .unwrap();
+        } else {
+            let size = SQUARE_WIDTH as u32 / 4;
+            let center = rect.center();
+            canvas
+                .fill_rect(Rect::from_center(center, size, size))
+                .unwrap();
+        }
+    }
+}
+
+// Files along the bottom edge, ranks along the left one
+fn draw_labels(canvas: &mut Canvas<Window>, flipped: bool) {
+    let (bottom_rank, left_file) = if flipped { (7, 7) } else { (0, 0) };
+    for k in 0..8 {
+        // in the colour of the other squares, to be readable on this one
+        canvas.set_draw_color(get_square_color(bottom_rank, k + 1));
+        let label = ((b'a' + k as u8) as char).to_string();
+        let (width, height) = get_text_size(&label, LABEL_SCALE);
+        let rect = get_square_rect(bottom_rank, k, flipped);
+        let (x, y) = (
+            rect.right() - width as i32 - 4,
+            rect.bottom() - height as i32 - 4,
+        );
+        draw_text(canvas, &label, x, y, LABEL_SCALE).unwrap();
+
+        canvas.set_draw_color(get_square_color(k + 1, left_file));
+        let rect = get_square_rect(k, left_file, flipped);
+        let label = (k + 1).to_string();
+        draw_text(canvas, &label, rect.x() + 4, rect.y() + 4, LABEL_SCALE).unwrap();
+    }
+}
+
+// A block running along the top edge while the engine thinks, elapsed is
+// the time since the start of the program
+pub fn draw_thinking(canvas: &mut Canvas<Window>, elapsed: Duration) {
+    let period = 2 * WIDTH as u128;
+    let offset = (elapsed.as_millis() % period) as i32;
+    let x = if offset < WIDTH as i32 {
+        offset
+    } else {
+        2 * WIDTH as i32 - offset
+    };
+    canvas.set_draw_color(HIGHLIGHT_COLOR);
+    canvas
+        .fill_rect(Rect::new(x - SQUARE_WIDTH / 2, 0, SQUARE_WIDTH as u32, 6))
+        .unwrap();
+}
@@ -0,0 +1,264 @@
+use chess::chess::chess_player::ChessPlayer;
+use chess::chess::time_manager::TimeControl;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+// Choices of the setup screen, a base time of 0 plays without clocks
+const BASE_MINUTES: [u64; 9] = [0, 1, 3, 5, 10, 15, 30, 60, 90];
+const BONUS_SECONDS: [u64; 8] = [0, 1, 2, 3, 5, 10, 15, 30];
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BonusKind {
+    // Fischer, added after every move
+    Increment,
+    // Bronstein, the time a move took is given back up to the delay
+    Delay,
+}
+
+// Time control of a game picked on the setup screen
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ClockSetting {
+    // indices into BASE_MINUTES and BONUS_SECONDS
+    base: usize,
+    bonus: usize,
+    bonus_kind: BonusKind,
+}
+
+impl ClockSetting {
+    pub fn new() -> Self {
+        Self {
+            base: 3,
+            bonus: 3,
+            bonus_kind: BonusKind::Increment,
+        }
+    }
+
+    pub fn is_timed(&self) -> bool {
+        BASE_MINUTES[self.base] != 0
+    }
+
+    pub fn get_base(&self) -> Duration {
+        Duration::from_secs(60 * BASE_MINUTES[self.base])
+    }
+
+    pub fn get_bonus(&self) -> Duration {
+        Duration::from_secs(BONUS_SECONDS[self.bonus])
+    }
+
+    // Steps the base time by delta choices, stops at the ends
+    pub fn change_base(&mut self, delta: i32) {
+        let base = self.base as i32 + delta;
+        self.base = base.clamp(0, BASE_MINUTES.len() as i32 - 1) as usize;
+    }
+
+    pub fn change_bonus(&mut self, delta: i32) {
+        let bonus = self.bonus as i32 + delta;
+        self.bonus = bonus.clamp(0, BONUS_SECONDS.len() as i32 - 1) as usize;
+    }
+
+    pub fn toggle_bonus_kind(&mut self) {
+        self.bonus_kind = match self.bonus_kind {
+            BonusKind::Increment => BonusKind::Delay,
+            BonusKind::Delay => BonusKind::Increment,
+        };
+    }
+}
+
+impl fmt::Display for ClockSetting {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if !self.is_timed() {
+            return write!(f, "no clock");
+        }
+        let kind = match self.bonus_kind {
+            BonusKind::Increment => "increment",
+            BonusKind::Delay => "delay",
+        };
+        write!(
+            f,
+            "{} min + {} s {}",
+            BASE_MINUTES[self.base], BONUS_SECONDS[self.bonus], kind
+        )
+    }
+}
+
+// Clocks of both players, at most one of them runs
+pub struct ChessClock {
+    setting: ClockSetting,
+    // time left when the clock of the player was stopped last, white first
+    remaining: [Duration; 2],
+    running: Option<(ChessPlayer, Instant)>,
+}
+
+impl ChessClock {
+    pub fn new(setting: ClockSetting) -> Self {
+        Self {
+            setting,
+            remaining: [setting.get_base(); 2],
+            running: None,
+        }
+    }
+
+    // Runs the clock of player. Stopping the other clock ends the move of
+    // the other player, who gets the bonus unless their time ran out.
+    pub fn run(&mut self, player: ChessPlayer) {
+        self.run_at(player, Instant::now());
+    }
+
+    fn run_at(&mut self, player: ChessPlayer, now: Instant) {
+        match self.running {
+            Some((running, _)) if running == player => return,
+            Some((running, since)) => {
+                let used = now.saturating_duration_since(since);
+                let left = self.remaining[running as usize].saturating_sub(used);
+                self.remaining[running as usize] = if left.is_zero() {
+                    left
+                } else {
+                    match self.setting.bonus_kind {
+                        BonusKind::Increment => left + self.setting.get_bonus(),
+                        BonusKind::Delay => left + used.min(self.setting.get_bonus()),
+                    }
+                };
+            }
+            None => {}
+        }
+        self.running = Some((player, now));
+    }
+
+    // Stops the running clock without a bonus, at the end of the game
+    pub fn stop(&mut self) {
+        if let Some((running, since)) = self.running.take() {
+            let left = self.remaining[running as usize].saturating_sub(since.elapsed());
+            self.remaining[running as usize] = left;
+        }
+    }
+
+    pub fn get_remaining(&self, player: ChessPlayer) -> Duration {
+        self.get_remaining_at(player, Instant::now())
+    }
+
+    fn get_remaining_at(&self, player: ChessPlayer, now: Instant) -> Duration {
+        let remaining = self.remaining[player as usize];
+        match self.running {
+            Some((running, since)) if running == player => {
+                remaining.saturating_sub(now.saturating_duration_since(since))
+            }
+            _ => remaining,
+        }
+    }
+
+    pub fn is_running(&self, player: ChessPlayer) -> bool {
+        self.running.is_some_and(|(running, _)| running == player)
+    }
+
+    // Time the engine playing player may think within, a delay is treated
+    // as an increment since it gives back at most as much
+    pub fn get_time_control(&self, player: ChessPlayer) -> TimeControl {
+        TimeControl {
+            remaining: self.get_remaining(player),
+            increment: self.setting.get_bonus(),
+            moves_to_go: None,
+            move_overhead: Duration::from_millis(50),
+        }
+    }
+
+    // Player whose time ran out
+    pub fn get_flagged(&self) -> Option<ChessPlayer> {
+        [ChessPlayer::White, ChessPlayer::Black]
+            .iter()
+            .copied()
+            .find(|&player| self.get_remaining(player).is_zero())
+    }
+}
+
+// Time as shown on a clock, tenths of a second in the last ten seconds
+pub fn format_clock_time(time: Duration) -> String {
+    let seconds = time.as_secs();
+    if seconds < 10 {
+        format!("0:{:02}.{}", seconds, time.subsec_millis() / 100)
+    } else if seconds < 3600 {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    } else {
+        format!(
+            "{}:{:02}:{:02}",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    // One minute with a bonus of five seconds
+    fn get_clock(bonus_kind: BonusKind) -> ChessClock {
+        ChessClock::new(ClockSetting {
+            base: 1,
+            bonus: 4,
+            bonus_kind,
+        })
+    }
+
+    #[test]
+    fn fischer_increment_is_added_after_every_move() {
+        let mut clock = get_clock(BonusKind::Increment);
+        let start = Instant::now();
+        clock.run_at(ChessPlayer::White, start);
+        assert_eq!(
+            clock.get_remaining_at(ChessPlayer::White, start + 2 * SECOND),
+            58 * SECOND
+        );
+        clock.run_at(ChessPlayer::Black, start + 2 * SECOND);
+        clock.run_at(ChessPlayer::White, start + 10 * SECOND);
+        let later = start + 20 * SECOND;
+        assert_eq!(
+            clock.get_remaining_at(ChessPlayer::White, later),
+            53 * SECOND
+        );
+        assert_eq!(
+            clock.get_remaining_at(ChessPlayer::Black, later),
+            57 * SECOND
+        );
+    }
+
+    #[test]
+    fn bronstein_delay_gives_back_at_most_the_delay() {
+        let mut clock = get_clock(BonusKind::Delay);
+        let start = Instant::now();
+        clock.run_at(ChessPlayer::White, start);
+        clock.run_at(ChessPlayer::Black, start + 2 * SECOND);
+        clock.run_at(ChessPlayer::White, start + 10 * SECOND);
+        let later = start + 10 * SECOND;
+        assert_eq!(
+            clock.get_remaining_at(ChessPlayer::White, later),
+            60 * SECOND
+        );
+        assert_eq!(
+            clock.get_remaining_at(ChessPlayer::Black, later),
+            57 * SECOND
+        );
+    }
+
+    #[test]
+    fn flag_falls_without_a_bonus() {
+        let mut clock = get_clock(BonusKind::Increment);
+        let start = Instant::now();
+        clock.run_at(ChessPlayer::White, start);
+        assert_eq!(clock.get_flagged(), None);
+        clock.run_at(ChessPlayer::Black, start + 61 * SECOND);
+        assert_eq!(clock.get_remaining(ChessPlayer::White), Duration::ZERO);
+        assert_eq!(clock.get_flagged(), Some(ChessPlayer::White));
+    }
+
+    #[test]
+    fn clock_time_is_formatted_by_magnitude() {
+        assert_eq!(format_clock_time(Duration::ZERO), "0:00.0");
+        assert_eq!(format_clock_time(Duration::from_millis(9870)), "0:09.8");
+        assert_eq!(format_clock_time(10 * SECOND), "0:10");
+        assert_eq!(format_clock_time(75 * SECOND), "1:15");
+        assert_eq!(format_clock_time(3725 * SECOND), "1:02:05");
+    }
+}
@@ -0,0 +1,103 @@
+use chess::chess::chess_piece::ChessPiece;
+use chess::chess::chess_player::ChessPlayer;
+use chess::chess::position::Position;
+use num::FromPrimitive;
+
+// Pieces of white, then of black, in the order of their textures, and
+// the eraser as the last entry
+pub const PALETTE_SIZE: usize = 13;
+pub const ERASER: usize = 12;
+
+// Piece of the k-th palette entry, None for the eraser
+pub fn get_palette_piece(k: usize) -> Option<(ChessPlayer, ChessPiece)> {
+    if k >= ERASER {
+        return None;
+    }
+    let player = if k < 6 {
+        ChessPlayer::White
+    } else {
+        ChessPlayer::Black
+    };
+    Some((player, ChessPiece::from_usize(k % 6).unwrap()))
+}
+
+// Board where the human sets up any position, pieces are put with the brush
+// picked from the palette. Castling and en passant are not part of the
+// rules, so there is nothing to set for them.
+pub struct PositionEditor {
+    pos: Position,
+    // index into the palette
+    brush: usize,
+}
+
+impl PositionEditor {
+    pub fn new(pos: Position) -> Self {
+        Self { pos, brush: 0 }
+    }
+
+    pub fn get_position(&self) -> &Position {
+        &self.pos
+    }
+
+    pub fn get_brush(&self) -> usize {
+        self.brush
+    }
+
+    pub fn set_brush(&mut self, k: usize) {
+        self.brush = k.min(PALETTE_SIZE - 1);
+    }
+
+    // Puts the brush piece on the square, the same piece there is taken
+    // away instead. A king moves from where it stood, each side has one.
+    pub fn apply(&mut self, i: usize, j: usize) {
+        let (player, piece) = match get_palette_piece(self.brush) {
+            Some(entry) => entry,
+            None => {
+                self.pos.remove_piece(i, j);
+                return;
+            }
+        };
+        let current = (
+            self.pos.get_player_on_position(i, j),
+            self.pos.get_piece_on_position(i, j),
+        );
+        if current == (Some(player), Some(piece)) {
+            self.pos.remove_piece(i, j);
+            return;
+        }
+        if piece == ChessPiece::King {
+            for square in self
+                .pos
+                .get_piece_type_by_player(ChessPiece::King, player)
+                .get_ones()
+            {
+                self.pos.remove_piece(square / 8, square % 8);
+            }
+        }
+        self.pos.put_piece(player, piece, i, j);
+    }
+
+    pub fn remove(&mut self, i: usize, j: usize) {
+        self.pos.remove_piece(i, j);
+    }
+
+    pub fn toggle_player_on_move(&mut self) {
+        let player = self.pos.get_player_on_move().get_opponent();
+        self.pos.set_player_on_move(player);
+    }
+
+    // Empties the board, the side to move stays
+    pub fn clear(&mut self) {
+        self.pos = Position::empty(self.pos.get_player_on_move());
+    }
+
+    pub fn set_position(&mut self, pos: Position) {
+        self.pos = pos;
+    }
+
+    // Whether play can start from the position: one king per side, no pawns
+    // on the back ranks, the side not on move not in check
+    pub fn validate(&self) -> Result<(), String> {
+        self.pos.validate()
+    }
+}
@@ -0,0 +1,147 @@
+use chess::chess::game::Game;
+use chess::chess::position::Move;
+use chess::chess::search::{Searcher, MAX_DEPTH};
+use chess::chess::time_manager::{TimeControl, TimeManager};
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+pub const MAX_STRENGTH_LEVEL: u32 = 8;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StrengthKind {
+    Depth,
+    Nodes,
+    Time,
+}
+
+impl StrengthKind {
+    pub fn next(&self) -> StrengthKind {
+        match self {
+            StrengthKind::Depth => StrengthKind::Nodes,
+            StrengthKind::Nodes => StrengthKind::Time,
+            StrengthKind::Time => StrengthKind::Depth,
+        }
+    }
+}
+
+// What limits one search of the engine, stronger with a higher level
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum EngineStrength {
+    Depth(u32),
+    Nodes(u64),
+    Time(Duration),
+}
+
+impl EngineStrength {
+    // level goes from 1 to MAX_STRENGTH_LEVEL, every level doubles the
+    // nodes or the time
+    pub fn new(kind: StrengthKind, level: u32) -> Self {
+        let level = level.clamp(1, MAX_STRENGTH_LEVEL);
+        match kind {
+            StrengthKind::Depth => EngineStrength::Depth(level),
+            StrengthKind::Nodes => EngineStrength::Nodes(500 << level),
+            StrengthKind::Time => EngineStrength::Time(Duration::from_millis(50 << level)),
+        }
+    }
+}
+
+impl fmt::Display for EngineStrength {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EngineStrength::Depth(depth) => write!(f, "depth {}", depth),
+            EngineStrength::Nodes(nodes) => write!(f, "{} nodes", nodes),
+            EngineStrength::Time(time) => write!(f, "{:.1} s per move", time.as_secs_f64()),
+        }
+    }
+}
+
+// Engine opponent searching on its own thread, so the window keeps being
+// redrawn while it thinks
+pub struct BackgroundEngine {
+    // None while a search owns it
+    searcher: Option<Searcher>,
+    stop: Arc<AtomicBool>,
+    result: Option<Receiver<(Searcher, Option<Move>)>>,
+}
+
+impl BackgroundEngine {
+    pub fn new() -> Self {
+        let searcher = Searcher::new();
+        Self {
+            stop: searcher.get_stop_handle(),
+            searcher: Some(searcher),
+            result: None,
+        }
+    }
+
+    pub fn is_thinking(&self) -> bool {
+        self.result.is_some()
+    }
+
+    // Searches the current position of game with the limit of strength, in
+    // a timed game also within what the clock of the engine allows
+    pub fn start(&mut self, game: &Game, strength: EngineStrength, clock: Option<TimeControl>) {
+        let mut searcher = match self.searcher.take() {
+            Some(searcher) => searcher,
+            None => return,
+        };
+        searcher.set_halfmove_clock(game.get_halfmove_clock());
+        let (sender, receiver) = mpsc::channel();
+        let pos = *game.get_position();
+        thread::spawn(move || {
+            let clock_manager = clock.map(|clock| TimeManager::new(&clock, &pos));
+            let (best_move, _) = match (strength, clock_manager) {
+                (EngineStrength::Depth(depth), Some(time_manager)) => {
+                    searcher.search_timed(&pos, depth, time_manager)
+                }
+                (EngineStrength::Depth(depth), None) => searcher.search(&pos, depth),
+                (EngineStrength::Nodes(nodes), Some(time_manager)) => {
+                    searcher.search_nodes_timed(&pos, nodes, time_manager)
+                }
+                (EngineStrength::Nodes(nodes), None) => searcher.search_nodes(&pos, nodes),
+                (EngineStrength::Time(time), clock_manager) => {
+                    let time = clock_manager.map_or(time, |m| time.min(m.get_soft_limit()));
+                    searcher.search_timed(&pos, MAX_DEPTH, TimeManager::with_move_time(time))
+                }
+            };
+            // the window may be gone already
+            let _ = sender.send((searcher, best_move));
+        });
+        self.result = Some(receiver);
+    }
+
+    // Move found by the finished search, None while it still runs. The
+    // inner None means there was no move to play.
+    pub fn poll(&mut self) -> Option<Option<Move>> {
+        let (searcher, best_move) = match self.result.as_ref()?.try_recv() {
+            Ok(result) => result,
+            Err(TryRecvError::Empty) => return None,
+            Err(TryRecvError::Disconnected) => panic!("engine thread panicked"),
+        };
+        self.searcher = Some(searcher);
+        self.result = None;
+        Some(best_move)
+    }
+
+    // Stops the search and throws its move away
+    pub fn cancel(&mut self) {
+        if let Some(receiver) = self.result.take() {
+            self.stop.store(true, Ordering::Relaxed);
+            let (searcher, _) = receiver.recv().unwrap();
+            self.searcher = Some(searcher);
+            self.stop.store(false, Ordering::Relaxed);
+        }
+    }
+
+    // Cancels the search, forgets earlier games
+    pub fn new_game(&mut self) {
+        self.cancel();
+        if let Some(searcher) = self.searcher.as_mut() {
+            searcher.clear();
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use chess::chess::game::Game;
+use chess::chess::pgn::{parse_pgn, write_pgn};
+use std::fs;
+
+// Game going on from the position of a FEN, e.g. one pasted from the
+// clipboard
+pub fn load_fen(fen: &str) -> Result<Game, String> {
+    Game::from_fen(fen.trim())
+}
+
+// First game of a PGN file with its moves played, so it can be continued
+// from its last position. The message to show tells whether moves after an
+// invalid one were dropped.
+pub fn load_pgn(path: &str) -> Result<(Game, String), String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    let pgn = parse_pgn(&text)
+        .into_iter()
+        .next()
+        .ok_or_else(|| format!("{}: no game found", path))?;
+    let mut game = Game::new(pgn.start);
+    for (&m, &promotion) in pgn.moves.iter().zip(pgn.promotions.iter()) {
+        game.make_move_promoting(m, promotion);
+    }
+    let message = if pgn.truncated {
+        format!(
+            "Loaded {} up to ply {}, the next move is not valid",
+            path,
+            pgn.moves.len()
+        )
+    } else {
+        format!("Loaded {}", path)
+    };
+    Ok((game, message))
+}
+
+// Writes the game to a PGN file, result is * while it goes on
+pub fn save_pgn(
+    path: &str,
+    game: &Game,
+    white: &str,
+    black: &str,
+    result: &str,
+    termination: &str,
+) -> Result<(), String> {
+    let headers: Vec<(String, String)> = vec![
+        ("Event".to_string(), "Casual game".to_string()),
+        ("Site".to_string(), "?".to_string()),
+        ("Date".to_string(), "????.??.??".to_string()),
+        ("Round".to_string(), "-".to_string()),
+        ("White".to_string(), white.to_string()),
+        ("Black".to_string(), black.to_string()),
+        ("Termination".to_string(), termination.to_string()),
+    ];
+    let pgn = write_pgn(&headers, game, result);
+    fs::write(path, pgn).map_err(|e| format!("{}: {}", path, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn load(name: &str, text: &str) -> Result<(Game, String), String> {
+        let path = env::temp_dir().join(name);
+        fs::write(&path, text).unwrap();
+        let loaded = load_pgn(path.to_str().unwrap());
+        fs::remove_file(&path).unwrap();
+        loaded.map(|(game, message)| (game, message.replace(path.to_str().unwrap(), "game")))
+    }
+
+    #[test]
+    fn truncated_pgn_is_reported() {
+        let (game, message) = load("complete.pgn", "1. e4 e5 2. Nf3 *").unwrap();
+        assert_eq!(game.get_moves().len(), 3);
+        assert_eq!(message, "Loaded game");
+
+        let (game, message) = load("truncated.pgn", "1. e4 e5 2. Ke3 Nc6 *").unwrap();
+        assert_eq!(game.get_moves().len(), 2);
+        assert_eq!(
+            message,
+            "Loaded game up to ply 2, the next move is not valid"
+        );
+    }
+}
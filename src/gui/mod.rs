@@ -0,0 +1,11 @@
+pub mod board;
+pub mod clock;
+pub mod editor;
+pub mod engine;
+pub mod font;
+pub mod game_file;
+pub mod move_list;
+pub mod panel;
+pub mod promotion;
+pub mod prompt;
+pub mod setup;
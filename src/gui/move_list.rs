@@ -0,0 +1,160 @@
+use chess::chess::chess_piece::ChessPiece;
+use chess::chess::chess_player::ChessPlayer;
+use chess::chess::game::Game;
+use chess::chess::pgn::move_to_san;
+use chess::chess::position::Move;
+use sdl2::rect::Rect;
+
+pub const ROW_HEIGHT: i32 = 24;
+// x offsets inside the list of the move number and of the two moves of a row
+pub const NUMBER_OFFSET: i32 = 8;
+const WHITE_OFFSET: i32 = 80;
+const BLACK_OFFSET: i32 = 200;
+const MOVE_WIDTH: u32 = 112;
+
+// Moves of the game in SAN, one row per full move, together with the ply
+// whose position is viewed on the board
+pub struct MoveList {
+    start_key: u64,
+    // with the piece each one promotes to
+    moves: Vec<(Move, ChessPiece)>,
+    sans: Vec<String>,
+    // 1 if black moved first, the first row then has only a black move
+    start_offset: usize,
+    // None follows the game, Some(ply) shows the position after ply moves
+    viewed: Option<usize>,
+}
+
+impl MoveList {
+    pub fn new() -> Self {
+        Self {
+            start_key: 0,
+            moves: Vec::new(),
+            sans: Vec::new(),
+            start_offset: 0,
+            viewed: None,
+        }
+    }
+
+    // Brings the list up to date with game, only moves that changed are
+    // formatted again
+    pub fn update(&mut self, game: &Game) {
+        let start = game.get_start_position();
+        if start.get_zobrist_key() != self.start_key {
+            self.start_key = start.get_zobrist_key();
+            self.moves.clear();
+            self.sans.clear();
+            self.viewed = None;
+        }
+        self.start_offset = match start.get_player_on_move() {
+            ChessPlayer::White => 0,
+            ChessPlayer::Black => 1,
+        };
+        let moves = game.get_moves();
+        let common = self
+            .moves
+            .iter()
+            .zip(moves.iter().enumerate())
+            .take_while(|(&a, (k, &m))| a == (m, game.get_promotion(*k)))
+            .count();
+        self.moves.truncate(common);
+        self.sans.truncate(common);
+        for (k, &m) in moves.iter().enumerate().skip(common) {
+            let promotion = game.get_promotion(k);
+            self.moves.push((m, promotion));
+            self.sans
+                .push(move_to_san(game.get_position_at(k), m, promotion));
+        }
+        if self.viewed.is_some_and(|ply| ply >= moves.len()) {
+            self.viewed = None;
+        }
+    }
+
+    pub fn get_san(&self, k: usize) -> &str {
+        &self.sans[k]
+    }
+
+    pub fn get_move_count(&self) -> usize {
+        self.sans.len()
+    }
+
+    pub fn get_row_count(&self) -> usize {
+        (self.sans.len() + self.start_offset).div_ceil(2)
+    }
+
+    // Number of the full move shown in row, games are counted from move 1
+    pub fn get_move_number(&self, row: usize) -> usize {
+        row + 1
+    }
+
+    // Ply whose position is shown on the board
+    pub fn get_viewed_ply(&self) -> usize {
+        self.viewed.unwrap_or(self.sans.len())
+    }
+
+    pub fn is_viewing_history(&self) -> bool {
+        self.viewed.is_some()
+    }
+
+    // Shows the position after ply moves, the last one follows the game again
+    pub fn view(&mut self, ply: usize) {
+        self.viewed = if ply >= self.sans.len() {
+            None
+        } else {
+            Some(ply)
+        };
+    }
+
+    pub fn step(&mut self, delta: i32) {
+        let ply = (self.get_viewed_ply() as i32 + delta).max(0);
+        self.view(ply as usize);
+    }
+
+    // First row shown in area, the list scrolls so the viewed move is on
+    // the screen
+    fn get_first_row(&self, area: Rect) -> usize {
+        let visible_rows = (area.height() as i32 / ROW_HEIGHT).max(1) as usize;
+        let viewed_row = match self.get_viewed_ply() {
+            0 => 0,
+            ply => (ply - 1 + self.start_offset) / 2,
+        };
+        (viewed_row + 1).saturating_sub(visible_rows)
+    }
+
+    // Rectangle of row in area, None if it is scrolled out
+    pub fn get_row_rect(&self, area: Rect, row: usize) -> Option<Rect> {
+        let first_row = self.get_first_row(area);
+        if row < first_row {
+            return None;
+        }
+        let y = area.y() + (row - first_row) as i32 * ROW_HEIGHT;
+        if y + ROW_HEIGHT > area.bottom() {
+            return None;
+        }
+        Some(Rect::new(area.x(), y, area.width(), ROW_HEIGHT as u32))
+    }
+
+    // Rectangle of the k-th move in area, None if it is scrolled out
+    pub fn get_move_rect(&self, area: Rect, k: usize) -> Option<Rect> {
+        let slot = k + self.start_offset;
+        let row = self.get_row_rect(area, slot / 2)?;
+        let offset = if slot.is_multiple_of(2) {
+            WHITE_OFFSET
+        } else {
+            BLACK_OFFSET
+        };
+        Some(Rect::new(
+            row.x() + offset,
+            row.y(),
+            MOVE_WIDTH,
+            ROW_HEIGHT as u32,
+        ))
+    }
+
+    pub fn get_move_at(&self, area: Rect, x: i32, y: i32) -> Option<usize> {
+        (0..self.sans.len()).find(|&k| {
+            self.get_move_rect(area, k)
+                .is_some_and(|rect| rect.contains_point((x, y)))
+        })
+    }
+}
@@ -0,0 +1,198 @@
+use chess::chess::chess_player::ChessPlayer;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::{Canvas, Texture};
+use sdl2::video::Window;
+
+use super::board::{get_texture_index, HEIGHT, HIGHLIGHT_COLOR, LAST_MOVE_COLOR, WIDTH};
+use super::clock::{format_clock_time, ChessClock};
+use super::editor::{get_palette_piece, ERASER, PALETTE_SIZE};
+use super::font::{draw_text, get_max_chars, get_text_size};
+use super::move_list::{MoveList, NUMBER_OFFSET, ROW_HEIGHT};
+
+// the side panel with the status and the moves is right of the board
+pub const PANEL_WIDTH: u32 = 360;
+pub const PANEL_SCALE: u32 = 2;
+pub const PANEL_MARGIN: i32 = 16;
+
+pub const PANEL_COLOR: Color = Color::RGB(48, 46, 43);
+pub const PANEL_TEXT_COLOR: Color = Color::RGB(230, 230, 230);
+pub const PANEL_DIM_TEXT_COLOR: Color = Color::RGB(140, 140, 140);
+
+const CLOCK_HEIGHT: i32 = 56;
+const CLOCK_SCALE: u32 = 4;
+const CLOCK_COLOR: Color = Color::RGB(70, 67, 63);
+const RUNNING_CLOCK_COLOR: Color = Color::RGB(230, 230, 230);
+const FLAGGED_CLOCK_COLOR: Color = Color::RGB(200, 40, 40);
+
+const PALETTE_SLOT: i32 = 56;
+
+// Clock of the player at the top or the bottom edge of the board, in the
+// corners of the side panel
+fn get_clock_rect(top: bool) -> Rect {
+    let y = if top {
+        PANEL_MARGIN / 2
+    } else {
+        HEIGHT as i32 - PANEL_MARGIN / 2 - CLOCK_HEIGHT
+    };
+    Rect::new(
+        WIDTH as i32 + PANEL_MARGIN / 2,
+        y,
+        PANEL_WIDTH - PANEL_MARGIN as u32,
+        CLOCK_HEIGHT as u32,
+    )
+}
+
+// Top of the status lines, below the top clock
+fn get_status_top() -> i32 {
+    get_clock_rect(true).bottom() + PANEL_MARGIN / 2
+}
+
+// Part of the side panel listing the moves, between the status lines and
+// the help line above the bottom clock
+pub fn get_move_list_rect() -> Rect {
+    let top = get_status_top() + 4 * ROW_HEIGHT + PANEL_MARGIN / 2;
+    let bottom = get_clock_rect(false).y() - ROW_HEIGHT - PANEL_MARGIN / 2;
+    Rect::new(WIDTH as i32, top, PANEL_WIDTH, (bottom - top) as u32)
+}
+
+// Slot of the k-th entry of the editor palette, white pieces in the first
+// row, black ones in the second and the eraser below them
+pub fn get_palette_rect(k: usize) -> Rect {
+    let top = get_status_top() + 6 * ROW_HEIGHT;
+    let left = WIDTH as i32 + (PANEL_WIDTH as i32 - 6 * PALETTE_SLOT) / 2;
+    Rect::new(
+        left + (k % 6) as i32 * PALETTE_SLOT,
+        top + (k / 6) as i32 * PALETTE_SLOT,
+        PALETTE_SLOT as u32,
+        PALETTE_SLOT as u32,
+    )
+}
+
+// Number of characters that fit on a status line
+pub fn get_status_max_chars() -> usize {
+    get_max_chars(PANEL_WIDTH - 2 * PANEL_MARGIN as u32, PANEL_SCALE)
+}
+
+// Draws the background of the panel and the status lines at its top
+pub fn draw_status(canvas: &mut Canvas<Window>, lines: &[String]) {
+    canvas.set_draw_color(PANEL_COLOR);
+    canvas
+        .fill_rect(Rect::new(WIDTH as i32, 0, PANEL_WIDTH, HEIGHT))
+        .unwrap();
+    canvas.set_draw_color(PANEL_TEXT_COLOR);
+    draw_lines(canvas, lines, get_status_top());
+}
+
+// Draws lines one below the other starting at top
+fn draw_lines<S: AsRef<str>>(canvas: &mut Canvas<Window>, lines: &[S], top: i32) {
+    for (k, line) in lines.iter().enumerate() {
+        let y = top + k as i32 * ROW_HEIGHT;
+        draw_text(
+            canvas,
+            line.as_ref(),
+            WIDTH as i32 + PANEL_MARGIN,
+            y,
+            PANEL_SCALE,
+        )
+        .unwrap();
+    }
+}
+
+// Draws the moves of the game, the move leading to the viewed position is
+// highlighted
+pub fn draw_move_list(canvas: &mut Canvas<Window>, move_list: &MoveList) {
+    let list_rect = get_move_list_rect();
+    let (_, text_height) = get_text_size("", PANEL_SCALE);
+    let text_offset = (ROW_HEIGHT - text_height as i32) / 2;
+    for row in 0..move_list.get_row_count() {
+        if let Some(rect) = move_list.get_row_rect(list_rect, row) {
+            canvas.set_draw_color(PANEL_DIM_TEXT_COLOR);
+            let number = format!("{}.", move_list.get_move_number(row));
+            let (x, y) = (rect.x() + NUMBER_OFFSET, rect.y() + text_offset);
+            draw_text(canvas, &number, x, y, PANEL_SCALE).unwrap();
+        }
+    }
+    for k in 0..move_list.get_move_count() {
+        if let Some(rect) = move_list.get_move_rect(list_rect, k) {
+            if k + 1 == move_list.get_viewed_ply() {
+                canvas.set_draw_color(LAST_MOVE_COLOR);
+                canvas.fill_rect(rect).unwrap();
+            }
+            canvas.set_draw_color(PANEL_TEXT_COLOR);
+            let (x, y) = (rect.x() + 4, rect.y() + text_offset);
+            draw_text(canvas, move_list.get_san(k), x, y, PANEL_SCALE).unwrap();
+        }
+    }
+    canvas.set_draw_color(PANEL_DIM_TEXT_COLOR);
+    let help = ["Left/Right/Home/End browse"];
+    draw_lines(canvas, &help, get_clock_rect(false).y() - ROW_HEIGHT);
+}
+
+// Draws the palette of the editor with brush highlighted and the help
+// below it
+pub fn draw_palette(canvas: &mut Canvas<Window>, textures: &[Texture], brush: usize) {
+    for k in 0..PALETTE_SIZE {
+        let rect = get_palette_rect(k);
+        if k == brush {
+            canvas.set_draw_color(HIGHLIGHT_COLOR);
+            canvas.fill_rect(rect).unwrap();
+        }
+        match get_palette_piece(k) {
+            Some((player, piece)) => {
+                let num = get_texture_index(player, piece);
+                canvas.copy(&textures[num], None, rect).unwrap();
+            }
+            // the eraser is a cross
+            None => {
+                canvas.set_draw_color(FLAGGED_CLOCK_COLOR);
+                let inner = Rect::from_center(rect.center(), rect.width() / 2, rect.height() / 2);
+                canvas
+                    .draw_line(inner.top_left(), inner.bottom_right())
+                    .unwrap();
+                canvas
+                    .draw_line(inner.top_right(), inner.bottom_left())
+                    .unwrap();
+            }
+        }
+    }
+    canvas.set_draw_color(PANEL_DIM_TEXT_COLOR);
+    let help = [
+        "Click puts, right removes",
+        "C clear, I initial",
+        "Enter plays, Esc back",
+    ];
+    draw_lines(
+        canvas,
+        &help,
+        get_palette_rect(ERASER).bottom() + PANEL_MARGIN,
+    );
+}
+
+// Draws both clocks, the one of bottom_player, who is at the bottom edge
+// of the board, at the bottom of the panel
+pub fn draw_clocks(canvas: &mut Canvas<Window>, clock: &ChessClock, bottom_player: ChessPlayer) {
+    for &player in [bottom_player, bottom_player.get_opponent()].iter() {
+        let rect = get_clock_rect(player != bottom_player);
+        let remaining = clock.get_remaining(player);
+        let (background, text) = if remaining.is_zero() {
+            (FLAGGED_CLOCK_COLOR, PANEL_TEXT_COLOR)
+        } else if clock.is_running(player) {
+            (RUNNING_CLOCK_COLOR, PANEL_COLOR)
+        } else {
+            (CLOCK_COLOR, PANEL_TEXT_COLOR)
+        };
+        canvas.set_draw_color(background);
+        canvas.fill_rect(rect).unwrap();
+        canvas.set_draw_color(text);
+        let time = format_clock_time(remaining);
+        let (width, height) = get_text_size(&time, CLOCK_SCALE);
+        let y = rect.y() + (CLOCK_HEIGHT - height as i32) / 2;
+        let x = rect.right() - PANEL_MARGIN - width as i32;
+        draw_text(canvas, &time, x, y, CLOCK_SCALE).unwrap();
+        let name = format!("{:?}", player);
+        let (_, height) = get_text_size(&name, PANEL_SCALE);
+        let y = rect.y() + (CLOCK_HEIGHT - height as i32) / 2;
+        draw_text(canvas, &name, rect.x() + PANEL_MARGIN, y, PANEL_SCALE).unwrap();
+    }
+}
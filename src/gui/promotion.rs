@@ -0,0 +1,83 @@
+use chess::chess::chess_piece::ChessPiece;
+use chess::chess::chess_player::ChessPlayer;
+use chess::chess::position::{Move, PROMOTION_PIECES};
+use sdl2::pixels::Color;
+use sdl2::render::{Canvas, Texture};
+use sdl2::video::Window;
+
+use super::board::{get_square_rect, get_texture_index, HIGHLIGHT_COLOR};
+
+const CHOICE_COLOR: Color = Color::RGB(240, 240, 240);
+
+// Choice of the piece a pawn of the human promotes to, shown over the
+// promotion file until a piece is picked or the move is cancelled. The
+// choices are PROMOTION_PIECES, from the promotion square towards the centre.
+#[derive(Debug, Copy, Clone)]
+pub struct PromotionChooser {
+    m: Move,
+    player: ChessPlayer,
+    // index into PROMOTION_PIECES
+    highlighted: usize,
+}
+
+impl PromotionChooser {
+    pub fn new(m: Move, player: ChessPlayer) -> Self {
+        Self {
+            m,
+            player,
+            highlighted: 0,
+        }
+    }
+
+    pub fn get_move(&self) -> Move {
+        self.m
+    }
+
+    pub fn get_highlighted(&self) -> ChessPiece {
+        PROMOTION_PIECES[self.highlighted]
+    }
+
+    // Square (rank, file) showing the k-th choice
+    fn get_square(&self, k: usize) -> (usize, usize) {
+        let (_, _, rank, file) = self.m;
+        match self.player {
+            ChessPlayer::White => (rank - k, file),
+            ChessPlayer::Black => (rank + k, file),
+        }
+    }
+
+    pub fn get_choice_at(&self, square: (usize, usize)) -> Option<ChessPiece> {
+        (0..PROMOTION_PIECES.len())
+            .find(|&k| self.get_square(k) == square)
+            .map(|k| PROMOTION_PIECES[k])
+    }
+
+    // Moves the highlight by delta choices, wrapping around
+    pub fn move_highlight(&mut self, delta: i32) {
+        let count = PROMOTION_PIECES.len() as i32;
+        self.highlighted = (self.highlighted as i32 + delta).rem_euclid(count) as usize;
+    }
+}
+
+// Draws the choices over the dimmed board
+pub fn draw_promotion(
+    canvas: &mut Canvas<Window>,
+    textures: &[Texture],
+    chooser: &PromotionChooser,
+    flipped: bool,
+) {
+    canvas.set_draw_color(Color::RGBA(0, 0, 0, 120));
+    canvas.fill_rect(None).unwrap();
+    for (k, &piece) in PROMOTION_PIECES.iter().enumerate() {
+        let (i, j) = chooser.get_square(k);
+        let rect = get_square_rect(i, j, flipped);
+        if k == chooser.highlighted {
+            canvas.set_draw_color(HIGHLIGHT_COLOR);
+        } else {
+            canvas.set_draw_color(CHOICE_COLOR);
+        }
+        canvas.fill_rect(rect).unwrap();
+        let texture = &textures[get_texture_index(chooser.player, piece)];
+        canvas.copy(texture, None, rect).unwrap();
+    }
+}
@@ -0,0 +1,79 @@
+use sdl2::rect::Rect;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+
+use super::board::{SQUARE_HEIGHT, SQUARE_WIDTH, WIDTH};
+use super::font::{draw_text, get_max_chars};
+use super::panel::{
+    PANEL_COLOR, PANEL_DIM_TEXT_COLOR, PANEL_MARGIN, PANEL_SCALE, PANEL_TEXT_COLOR,
+};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PromptAction {
+    OpenPgn,
+    SavePgn,
+}
+
+// Line where the human types the path of a file to open or save, it takes
+// all keyboard input while it is shown
+pub struct PathPrompt {
+    action: PromptAction,
+    text: String,
+}
+
+impl PathPrompt {
+    pub fn new(action: PromptAction, text: &str) -> Self {
+        Self {
+            action,
+            text: text.to_string(),
+        }
+    }
+
+    pub fn get_action(&self) -> PromptAction {
+        self.action
+    }
+
+    pub fn get_label(&self) -> &'static str {
+        match self.action {
+            PromptAction::OpenPgn => "Open PGN:",
+            PromptAction::SavePgn => "Save PGN:",
+        }
+    }
+
+    pub fn get_text(&self) -> &str {
+        &self.text
+    }
+
+    // Typed or pasted text, line breaks are dropped
+    pub fn insert(&mut self, text: &str) {
+        self.text.extend(text.chars().filter(|c| !c.is_control()));
+    }
+
+    pub fn delete_last(&mut self) {
+        self.text.pop();
+    }
+}
+
+// Draws prompt over the middle of the board. The end of a long path is
+// shown, it is where the typing happens.
+pub fn draw_prompt(canvas: &mut Canvas<Window>, prompt: &PathPrompt) {
+    let rect = Rect::new(
+        SQUARE_WIDTH / 2,
+        7 * SQUARE_HEIGHT / 2,
+        WIDTH - SQUARE_WIDTH as u32,
+        SQUARE_HEIGHT as u32,
+    );
+    canvas.set_draw_color(PANEL_COLOR);
+    canvas.fill_rect(rect).unwrap();
+    canvas.set_draw_color(PANEL_TEXT_COLOR);
+    canvas.draw_rect(rect).unwrap();
+    let max_chars = get_max_chars(rect.width() - 2 * PANEL_MARGIN as u32, PANEL_SCALE);
+    let line = format!("{} {}_", prompt.get_label(), prompt.get_text());
+    let skip = line.chars().count().saturating_sub(max_chars);
+    let line: String = line.chars().skip(skip).collect();
+    let x = rect.x() + PANEL_MARGIN;
+    draw_text(canvas, &line, x, rect.y() + 24, PANEL_SCALE).unwrap();
+    canvas.set_draw_color(PANEL_DIM_TEXT_COLOR);
+    let hint = "Enter confirms, Esc cancels, Ctrl+V pastes";
+    draw_text(canvas, hint, x, rect.y() + 60, PANEL_SCALE).unwrap();
+}
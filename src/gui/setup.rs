@@ -0,0 +1,140 @@
+use chess::chess::chess_piece::ChessPiece;
+use chess::chess::chess_player::ChessPlayer;
+use sdl2::keyboard::Keycode;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::{Canvas, Texture};
+use sdl2::video::Window;
+
+use super::board::{get_texture_index, HIGHLIGHT_COLOR, SQUARE_HEIGHT, SQUARE_WIDTH, WIDTH};
+use super::clock::ClockSetting;
+use super::engine::{EngineStrength, StrengthKind, MAX_STRENGTH_LEVEL};
+use super::font::{draw_text, get_text_size};
+use super::panel::{PANEL_DIM_TEXT_COLOR, PANEL_SCALE, PANEL_TEXT_COLOR};
+
+const PLAYERS: [ChessPlayer; 2] = [ChessPlayer::White, ChessPlayer::Black];
+
+// Rectangle of the setup screen showing the king of player, clicking it
+// picks the colour
+fn get_king_rect(player: ChessPlayer) -> Rect {
+    let column = match player {
+        ChessPlayer::White => 2,
+        ChessPlayer::Black => 4,
+    };
+    Rect::new(
+        column * SQUARE_WIDTH,
+        2 * SQUARE_HEIGHT,
+        2 * SQUARE_WIDTH as u32,
+        2 * SQUARE_HEIGHT as u32,
+    )
+}
+
+// Box of the strength bar of the setup screen for level, one per square of
+// the fifth row from the top
+fn get_level_rect(level: u32) -> Rect {
+    Rect::new(
+        (level as i32 - 1) * SQUARE_WIDTH + SQUARE_WIDTH / 8,
+        5 * SQUARE_HEIGHT + SQUARE_HEIGHT / 4,
+        (SQUARE_WIDTH * 3 / 4) as u32,
+        (SQUARE_HEIGHT / 2) as u32,
+    )
+}
+
+// Colour of the human, strength of the engine and the clocks picked before
+// a game
+pub struct GameSetup {
+    human_player: ChessPlayer,
+    strength_kind: StrengthKind,
+    strength_level: u32,
+    clock_setting: ClockSetting,
+}
+
+impl GameSetup {
+    pub fn new() -> Self {
+        Self {
+            human_player: ChessPlayer::White,
+            strength_kind: StrengthKind::Depth,
+            strength_level: 3,
+            clock_setting: ClockSetting::new(),
+        }
+    }
+
+    pub fn get_human_player(&self) -> ChessPlayer {
+        self.human_player
+    }
+
+    pub fn get_strength(&self) -> EngineStrength {
+        EngineStrength::new(self.strength_kind, self.strength_level)
+    }
+
+    pub fn get_clock_setting(&self) -> ClockSetting {
+        self.clock_setting
+    }
+
+    // Changes the choice bound to key, other keys are ignored
+    pub fn handle_key(&mut self, key: Keycode) {
+        match key {
+            Keycode::W => self.human_player = ChessPlayer::White,
+            Keycode::B => self.human_player = ChessPlayer::Black,
+            Keycode::Left => self.strength_level = (self.strength_level - 1).max(1),
+            Keycode::Right => {
+                self.strength_level = (self.strength_level + 1).min(MAX_STRENGTH_LEVEL)
+            }
+            Keycode::Tab => self.strength_kind = self.strength_kind.next(),
+            Keycode::Up => self.clock_setting.change_base(1),
+            Keycode::Down => self.clock_setting.change_base(-1),
+            Keycode::PageUp => self.clock_setting.change_bonus(1),
+            Keycode::PageDown => self.clock_setting.change_bonus(-1),
+            Keycode::D => self.clock_setting.toggle_bonus_kind(),
+            _ => {}
+        }
+    }
+
+    // A click on a king picks the colour, one on the bar the strength
+    pub fn handle_click(&mut self, x: i32, y: i32) {
+        for &player in PLAYERS.iter() {
+            if get_king_rect(player).contains_point((x, y)) {
+                self.human_player = player;
+            }
+        }
+        for level in 1..=MAX_STRENGTH_LEVEL {
+            if get_level_rect(level).contains_point((x, y)) {
+                self.strength_level = level;
+            }
+        }
+    }
+}
+
+// Draws the setup screen over the dimmed board
+pub fn draw_setup(canvas: &mut Canvas<Window>, textures: &[Texture], setup: &GameSetup) {
+    canvas.set_draw_color(Color::RGBA(0, 0, 0, 160));
+    canvas.fill_rect(None).unwrap();
+    for &player in PLAYERS.iter() {
+        let rect = get_king_rect(player);
+        if player == setup.human_player {
+            canvas.set_draw_color(HIGHLIGHT_COLOR);
+            canvas.fill_rect(rect).unwrap();
+        }
+        let num = get_texture_index(player, ChessPiece::King);
+        canvas.copy(&textures[num], None, rect).unwrap();
+    }
+    for level in 1..=MAX_STRENGTH_LEVEL {
+        let rect = get_level_rect(level);
+        canvas.set_draw_color(Color::RGB(0, 200, 0));
+        if level <= setup.strength_level {
+            canvas.fill_rect(rect).unwrap();
+        } else {
+            canvas.draw_rect(rect).unwrap();
+        }
+    }
+    canvas.set_draw_color(PANEL_TEXT_COLOR);
+    let text = format!("Clock: {}", setup.clock_setting);
+    let (width, _) = get_text_size(&text, 3);
+    let (x, y) = ((WIDTH - width) as i32 / 2, 6 * SQUARE_HEIGHT + 16);
+    draw_text(canvas, &text, x, y, 3).unwrap();
+    canvas.set_draw_color(PANEL_DIM_TEXT_COLOR);
+    let text = "Up/Down time, PgUp/PgDn bonus, D increment/delay";
+    let (width, _) = get_text_size(text, PANEL_SCALE);
+    let (x, y) = ((WIDTH - width) as i32 / 2, 6 * SQUARE_HEIGHT + 56);
+    draw_text(canvas, text, x, y, PANEL_SCALE).unwrap();
+}
@@ -0,0 +1,7 @@
+extern crate num;
+#[macro_use]
+extern crate num_derive;
+#[macro_use]
+extern crate impl_ops;
+
+pub mod chess;
@@ -1,30 +1,41 @@
-extern crate num;
-#[macro_use]
-extern crate num_derive;
-#[macro_use]
-extern crate impl_ops;
-
-use crate::num::ToPrimitive;
-
-mod chess;
-
-use chess::chess_piece::ChessPiece;
-use chess::chess_player::ChessPlayer;
-use chess::position::*;
+use chess::chess::chess_piece::ChessPiece;
+use chess::chess::chess_player::ChessPlayer;
+use chess::chess::game::Game;
+use chess::chess::position::Position;
 
+use sdl2::clipboard::ClipboardUtil;
 use sdl2::event::Event;
 use sdl2::image::LoadTexture;
-use sdl2::keyboard::Keycode;
+use sdl2::keyboard::{Keycode, Mod, TextInputUtil};
 use sdl2::mouse::MouseButton;
 use sdl2::pixels::Color;
-use sdl2::rect::Rect;
-use sdl2::render::{Canvas, Texture};
+use sdl2::render::{BlendMode, Canvas, Texture};
 use sdl2::video::Window;
 use std::path::Path;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 // use std::io;
 
-struct ChessDrawing {}
+mod gui;
+
+use gui::board::{draw_board, draw_thinking, get_square_at, Selection, HEIGHT, WIDTH};
+use gui::clock::ChessClock;
+use gui::editor::{PositionEditor, PALETTE_SIZE};
+use gui::engine::BackgroundEngine;
+use gui::font::wrap_text;
+use gui::game_file::{load_fen, load_pgn, save_pgn};
+use gui::move_list::MoveList;
+use gui::panel::{
+    draw_clocks, draw_move_list, draw_palette, draw_status, get_move_list_rect, get_palette_rect,
+    get_status_max_chars, PANEL_WIDTH,
+};
+use gui::promotion::{draw_promotion, PromotionChooser};
+use gui::prompt::{draw_prompt, PathPrompt, PromptAction};
+use gui::setup::{draw_setup, GameSetup};
+
+struct ChessDrawing {
+    // PGN file given on the command line, opened at the start
+    pgn_path: Option<String>,
+}
 
 const CHESS_PIECES_TEXTURES_PATHS: [&str; 12] = [
     "images/white_pawn.png",
@@ -41,26 +52,717 @@ const CHESS_PIECES_TEXTURES_PATHS: [&str; 12] = [
     "images/black_king.png",
 ];
 
-const WIDTH: u32 = 800;
-const HEIGHT: u32 = 800;
+// how long a message like Copied FEN stays in the panel
+const MESSAGE_DURATION: Duration = Duration::from_secs(5);
+
+// Before a game the human picks a colour and the strength of the engine
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Mode {
+    Setup,
+    Playing,
+    // setting up the position the game starts from
+    Editor,
+}
+
+// What the event loop does after an event
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Flow {
+    Continue,
+    // the remaining events are handled in the next frame, in the new position
+    NextFrame,
+    Quit,
+}
+
+// PGN result and its reason once the game is over. Running out of time
+// loses, but draws when the opponent has only the king left.
+fn get_game_result(game: &Game, clock: Option<&ChessClock>) -> Option<(&'static str, String)> {
+    let status = game.get_status();
+    if status.is_over() {
+        return Some((status.get_result(), status.to_string()));
+    }
+    let player = clock.and_then(|clock| clock.get_flagged())?;
+    let opponent = player.get_opponent();
+    if game
+        .get_position()
+        .get_pieces_of_player(opponent)
+        .count_ones()
+        == 1
+    {
+        return Some(("1/2-1/2", format!("{:?} ran out of time, draw", player)));
+    }
+    let result = match opponent {
+        ChessPlayer::White => "1-0",
+        ChessPlayer::Black => "0-1",
+    };
+    Some((result, format!("{:?} wins on time", opponent)))
+}
+
+// Value of the PGN Termination tag
+fn get_termination(game: &Game, clock: Option<&ChessClock>) -> &'static str {
+    if game.get_status().is_over() {
+        "normal"
+    } else if clock.and_then(|clock| clock.get_flagged()).is_some() {
+        "time forfeit"
+    } else {
+        "unterminated"
+    }
+}
+
+// Everything the window shows and the input changes
+struct GuiState {
+    mode: Mode,
+    game: Game,
+    setup: GameSetup,
+    // None in games without clocks
+    clock: Option<ChessClock>,
+    engine: BackgroundEngine,
+    // the board is seen from the side of black, at first the human's side
+    flipped: bool,
+    selection: Selection,
+    move_list: MoveList,
+    editor: PositionEditor,
+    // open while the human picks the piece a pawn promotes to
+    promotion: Option<PromotionChooser>,
+    // open while the human types the path of a PGN file
+    prompt: Option<PathPrompt>,
+    // the file of the game is offered again when saving
+    pgn_path: String,
+    // shown in the panel for a while
+    message: Option<(String, Instant)>,
+    started: Instant,
+}
+
+impl GuiState {
+    fn new(pgn_path: Option<&String>) -> Self {
+        let mut state = Self {
+            mode: Mode::Setup,
+            game: Game::default(),
+            setup: GameSetup::new(),
+            clock: None,
+            engine: BackgroundEngine::new(),
+            flipped: false,
+            selection: Selection::new(),
+            move_list: MoveList::new(),
+            editor: PositionEditor::new(Position::default()),
+            promotion: None,
+            prompt: None,
+            pgn_path: pgn_path.cloned().unwrap_or_else(|| "game.pgn".to_string()),
+            message: None,
+            started: Instant::now(),
+        };
+        match pgn_path.map(|path| load_pgn(path)) {
+            Some(Ok((game, message))) => {
+                state.message = Some((message, Instant::now()));
+                state.game = game;
+            }
+            Some(Err(err)) => state.show_message(err),
+            None => {}
+        }
+        state
+    }
+
+    // The board shows the viewed position, the game's one by default
+    fn get_board_position(&self) -> Position {
+        if self.mode == Mode::Editor {
+            *self.editor.get_position()
+        } else {
+            *self.game.get_position_at(self.move_list.get_viewed_ply())
+        }
+    }
+
+    fn get_result(&self) -> Option<(&'static str, String)> {
+        get_game_result(&self.game, self.clock.as_ref())
+    }
+
+    // The human moves only on their turn
+    fn is_human_on_move(&self) -> bool {
+        self.mode == Mode::Playing
+            && !self.move_list.is_viewing_history()
+            && self.get_board_position().get_player_on_move() == self.setup.get_human_player()
+            && !self.engine.is_thinking()
+            && self.get_result().is_none()
+    }
+
+    fn show_message(&mut self, text: String) {
+        self.message = Some((text, Instant::now()));
+    }
+
+    fn render(&self, canvas: &mut Canvas<Window>, textures: &[Texture]) {
+        let pos = self.get_board_position();
+        canvas.set_draw_color(Color::RGB(0, 0, 0));
+        canvas.clear();
+        let last_move = self
+            .move_list
+            .get_viewed_ply()
+            .checked_sub(1)
+            .filter(|_| self.mode != Mode::Editor)
+            .map(|k| self.game.get_moves()[k]);
+        draw_board(
+            canvas,
+            textures,
+            &pos,
+            self.flipped,
+            last_move,
+            &self.selection,
+        );
+
+        draw_status(canvas, &self.get_status_lines(&pos));
+        if self.mode == Mode::Editor {
+            draw_palette(canvas, textures, self.editor.get_brush());
+        } else {
+            draw_move_list(canvas, &self.move_list);
+        }
+        // the clock of the player at the bottom edge of the board is shown
+        // at the bottom of the panel
+        if let Some(clock) = self.clock.as_ref() {
+            let bottom_player = if self.flipped {
+                ChessPlayer::Black
+            } else {
+                ChessPlayer::White
+            };
+            draw_clocks(canvas, clock, bottom_player);
+        }
+
+        if let Some(chooser) = self.promotion.as_ref() {
+            draw_promotion(canvas, textures, chooser, self.flipped);
+        }
+        if self.mode == Mode::Setup {
+            draw_setup(canvas, textures, &self.setup);
+        }
+        if let Some(prompt) = self.prompt.as_ref() {
+            draw_prompt(canvas, prompt);
+        }
+        if self.engine.is_thinking() {
+            draw_thinking(canvas, self.started.elapsed());
+        }
+    }
+
+    // Lines at the top of the side panel, pos is the one on the board
+    fn get_status_lines(&self, pos: &Position) -> Vec<String> {
+        let max_chars = get_status_max_chars();
+        let mut lines = Vec::new();
+        if self.mode == Mode::Editor {
+            lines.push(format!("{:?} to move (T)", pos.get_player_on_move()));
+            // the rules know neither, so there is nothing to choose
+            lines.push("Castling, en passant: none".to_string());
+            match self.editor.validate() {
+                Ok(()) => lines.push("Valid, Enter starts".to_string()),
+                Err(err) => lines.extend(wrap_text(&err, max_chars)),
+            }
+        } else {
+            let game_pos = self.game.get_position();
+            lines.push(if let Some((_, reason)) = self.get_result() {
+                reason
+            } else if game_pos.is_in_check(game_pos.get_player_on_move()) {
+                format!("{:?} to move, check", game_pos.get_player_on_move())
+            } else {
+                format!("{:?} to move", game_pos.get_player_on_move())
+            });
+        }
+        if self.mode == Mode::Playing {
+            lines.push(if self.engine.is_thinking() {
+                "Engine is thinking...".to_string()
+            } else {
+                format!("You play {:?}", self.setup.get_human_player())
+            });
+        }
+        if self.mode != Mode::Editor && self.move_list.is_viewing_history() {
+            lines.push(format!(
+                "Viewing {}/{}, End returns",
+                self.move_list.get_viewed_ply(),
+                self.move_list.get_move_count()
+            ));
+        }
+        if let Some((text, _)) = self
+            .message
+            .as_ref()
+            .filter(|(_, at)| at.elapsed() < MESSAGE_DURATION)
+        {
+            // cut to the width of the panel
+            lines.push(text.chars().take(max_chars).collect());
+        }
+        lines
+    }
+
+    fn get_title(&self) -> String {
+        let human_player = self.setup.get_human_player();
+        let strength = self.setup.get_strength();
+        match self.mode {
+            Mode::Editor => "chess - position editor \
+                             (click puts, right click removes, T side to move, C clear, \
+                             I initial, Enter play, Esc back)"
+                .to_string(),
+            Mode::Setup => format!(
+                "chess - new game: you play {:?}, engine {}, {} \
+                 (W/B colour, Left/Right strength, Tab limit, E edit position, Enter start, \
+                 Ctrl+V/C paste/copy FEN, Ctrl+O/S open/save PGN)",
+                human_player,
+                strength,
+                self.setup.get_clock_setting()
+            ),
+            Mode::Playing => match self.get_result() {
+                Some((result, reason)) => format!(
+                    "chess - {} {} (N new game, F flip, \
+                     Ctrl+V/C paste/copy FEN, Ctrl+O/S open/save PGN)",
+                    result, reason
+                ),
+                None if self.engine.is_thinking() => {
+                    format!("chess - engine {} is thinking...", strength)
+                }
+                None => format!(
+                    "chess - you play {:?} (N new game, F flip, \
+                     Ctrl+V/C paste/copy FEN, Ctrl+O/S open/save PGN)",
+                    human_player
+                ),
+            },
+        }
+    }
+
+    // Play goes on from a game from the clipboard or a file, with fresh
+    // clocks. The message about it is shown either way.
+    fn open_game(&mut self, loaded: Result<(Game, String), String>) {
+        match loaded {
+            Ok((game, text)) => {
+                self.engine.new_game();
+                self.game = game;
+                let setting = self.setup.get_clock_setting();
+                self.clock = self.clock.as_ref().map(|_| ChessClock::new(setting));
+                self.promotion = None;
+                self.selection.clear();
+                if self.mode == Mode::Editor {
+                    self.editor.set_position(*self.game.get_position());
+                }
+                self.show_message(text);
+            }
+            Err(err) => self.show_message(err),
+        }
+    }
+
+    fn save_game(&self, path: &str) -> Result<(), String> {
+        let engine_name = format!("chess ({})", self.setup.get_strength());
+        let (white, black) = match self.setup.get_human_player() {
+            ChessPlayer::White => ("Human", engine_name.as_str()),
+            ChessPlayer::Black => (engine_name.as_str(), "Human"),
+        };
+        let result = self.get_result();
+        let result_tag = result.as_ref().map_or("*", |&(r, _)| r);
+        let termination = get_termination(&self.game, self.clock.as_ref());
+        save_pgn(path, &self.game, white, black, result_tag, termination)
+    }
+
+    fn handle_event(
+        &mut self,
+        event: Event,
+        clipboard: &ClipboardUtil,
+        text_input: &TextInputUtil,
+    ) -> Flow {
+        if self.prompt.is_some() {
+            return self.handle_prompt_event(event, clipboard, text_input);
+        }
+        if self.promotion.is_some() {
+            return self.handle_promotion_event(event);
+        }
+        match event {
+            Event::KeyDown {
+                keycode: Some(Keycode::Escape),
+                ..
+            } if self.mode == Mode::Editor => self.mode = Mode::Setup,
+            Event::Quit { .. }
+            | Event::KeyDown {
+                keycode: Some(Keycode::Escape),
+                ..
+            } => return Flow::Quit,
+            Event::KeyDown {
+                keycode: Some(key),
+                keymod,
+                ..
+            } if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) => {
+                return self.handle_shortcut(key, clipboard, text_input)
+            }
+            Event::KeyDown {
+                keycode: Some(key), ..
+            } => self.handle_key(key),
+            // a click on a move of the list shows the position after it, a
+            // click on the board returns to the game
+            Event::MouseButtonDown {
+                mouse_btn: MouseButton::Left,
+                x,
+                y,
+                ..
+            } if self.mode == Mode::Playing
+                && (x >= WIDTH as i32 || self.move_list.is_viewing_history()) =>
+            {
+                match self.move_list.get_move_at(get_move_list_rect(), x, y) {
+                    Some(k) => self.move_list.view(k + 1),
+                    None if x < WIDTH as i32 => {
+                        self.move_list.view(self.move_list.get_move_count())
+                    }
+                    None => {}
+                }
+                self.selection.clear();
+            }
+            Event::MouseButtonDown {
+                mouse_btn: MouseButton::Left,
+                x,
+                y,
+                ..
+            } if self.is_human_on_move() => return self.press_on_board(x, y),
+            Event::MouseMotion { x, y, .. } => self.selection.set_mouse_position(x, y),
+            Event::MouseButtonUp {
+                mouse_btn: MouseButton::Left,
+                x,
+                y,
+                ..
+            } if self.selection.is_dragging() => return self.release_on_board(x, y),
+            // the palette picks the brush, on the board it is used
+            Event::MouseButtonDown {
+                mouse_btn, x, y, ..
+            } if self.mode == Mode::Editor => {
+                if let Some(k) =
+                    (0..PALETTE_SIZE).find(|&k| get_palette_rect(k).contains_point((x, y)))
+                {
+                    self.editor.set_brush(k);
+                } else if let Some((i, j)) = get_square_at(x, y, self.flipped) {
+                    match mouse_btn {
+                        MouseButton::Left => self.editor.apply(i, j),
+                        MouseButton::Right => self.editor.remove(i, j),
+                        _ => {}
+                    }
+                }
+            }
+            Event::MouseButtonDown { x, y, .. } if self.mode == Mode::Setup => {
+                self.setup.handle_click(x, y)
+            }
+            _ => {}
+        }
+        Flow::Continue
+    }
+
+    // The prompt takes all input until it is closed
+    fn handle_prompt_event(
+        &mut self,
+        event: Event,
+        clipboard: &ClipboardUtil,
+        text_input: &TextInputUtil,
+    ) -> Flow {
+        let prompt = self.prompt.as_mut().unwrap();
+        let mut confirmed = false;
+        let mut closed = false;
+        match event {
+            Event::TextInput { text, .. } => prompt.insert(&text),
+            Event::KeyDown {
+                keycode: Some(Keycode::V),
+                keymod,
+                ..
+            } if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) => {
+                if let Ok(text) = clipboard.clipboard_text() {
+                    prompt.insert(&text);
+                }
+            }
+            Event::KeyDown {
+                keycode: Some(key), ..
+            } => match key {
+                Keycode::Backspace => prompt.delete_last(),
+                Keycode::Return | Keycode::KpEnter => confirmed = true,
+                Keycode::Escape => closed = true,
+                _ => {}
+            },
+            Event::Quit { .. } => return Flow::Quit,
+            _ => {}
+        }
+        if !confirmed && !closed {
+            return Flow::Continue;
+        }
+        let action = prompt.get_action();
+        let path = prompt.get_text().trim().to_string();
+        self.prompt = None;
+        text_input.stop();
+        if !confirmed {
+            return Flow::Continue;
+        }
+        let flow = match action {
+            PromptAction::OpenPgn => {
+                self.open_game(load_pgn(&path));
+                Flow::NextFrame
+            }
+            PromptAction::SavePgn => {
+                let text = match self.save_game(&path) {
+                    Ok(()) => format!("Saved {}", path),
+                    Err(err) => err,
+                };
+                self.show_message(text);
+                Flow::Continue
+            }
+        };
+        self.pgn_path = path;
+        flow
+    }
+
+    // The chooser takes all input until a piece is picked, Esc or a click
+    // outside of the choices cancels the move
+    fn handle_promotion_event(&mut self, event: Event) -> Flow {
+        let chooser = self.promotion.as_mut().unwrap();
+        let mut choice = None;
+        let mut cancelled = false;
+        match event {
+            Event::KeyDown {
+                keycode: Some(key), ..
+            } => match key {
+                Keycode::Escape => cancelled = true,
+                Keycode::Up | Keycode::Left => chooser.move_highlight(-1),
+                Keycode::Down | Keycode::Right => chooser.move_highlight(1),
+                Keycode::Return | Keycode::KpEnter | Keycode::Space => {
+                    choice = Some(chooser.get_highlighted())
+                }
+                Keycode::Q => choice = Some(ChessPiece::Queen),
+                Keycode::R => choice = Some(ChessPiece::Rook),
+                Keycode::B => choice = Some(ChessPiece::Bishop),
+                Keycode::N => choice = Some(ChessPiece::Knight),
+                _ => {}
+            },
+            Event::MouseButtonDown {
+                mouse_btn: MouseButton::Left,
+                x,
+                y,
+                ..
+            } => match get_square_at(x, y, self.flipped).and_then(|sq| chooser.get_choice_at(sq)) {
+                Some(piece) => choice = Some(piece),
+                None => cancelled = true,
+            },
+            Event::Quit { .. } => return Flow::Quit,
+            _ => {}
+        }
+        let m = chooser.get_move();
+        if cancelled {
+            self.promotion = None;
+            return Flow::Continue;
+        }
+        let Some(piece) = choice else {
+            return Flow::Continue;
+        };
+        self.promotion = None;
+        // the game may have ended on time while the human was choosing
+        if self.is_human_on_move() && self.game.make_move_promoting(m, piece) {
+            Flow::NextFrame
+        } else {
+            Flow::Continue
+        }
+    }
+
+    // FEN through the clipboard, PGN files through the prompt
+    fn handle_shortcut(
+        &mut self,
+        key: Keycode,
+        clipboard: &ClipboardUtil,
+        text_input: &TextInputUtil,
+    ) -> Flow {
+        match key {
+            Keycode::V => {
+                self.open_game(
+                    clipboard
+                        .clipboard_text()
+                        .and_then(|fen| load_fen(&fen))
+                        .map(|game| (game, "Pasted FEN".to_string())),
+                );
+                return Flow::NextFrame;
+            }
+            // the position on the board, which may be an earlier one
+            Keycode::C => {
+                let fen = if self.mode == Mode::Editor || self.move_list.is_viewing_history() {
+                    self.get_board_position().to_fen()
+                } else {
+                    self.game.get_fen()
+                };
+                let text = match clipboard.set_clipboard_text(&fen) {
+                    Ok(()) => "Copied FEN".to_string(),
+                    Err(err) => err,
+                };
+                self.show_message(text);
+            }
+            Keycode::O | Keycode::S => {
+                let action = if key == Keycode::O {
+                    PromptAction::OpenPgn
+                } else {
+                    PromptAction::SavePgn
+                };
+                self.prompt = Some(PathPrompt::new(action, &self.pgn_path));
+                text_input.start();
+                self.selection.release();
+            }
+            _ => {}
+        }
+        Flow::Continue
+    }
+
+    fn handle_key(&mut self, key: Keycode) {
+        match (self.mode, key) {
+            (Mode::Setup, Keycode::E) => {
+                self.editor = PositionEditor::new(*self.game.get_position());
+                self.mode = Mode::Editor;
+            }
+            // the game starts once colours and strength are chosen
+            (Mode::Setup, Keycode::Return) => {
+                let setting = self.setup.get_clock_setting();
+                self.clock = if setting.is_timed() {
+                    Some(ChessClock::new(setting))
+                } else {
+                    None
+                };
+                self.flipped = self.setup.get_human_player() == ChessPlayer::Black;
+                self.mode = Mode::Playing;
+            }
+            (Mode::Setup, _) => self.setup.handle_key(key),
+            (Mode::Editor, Keycode::T) => self.editor.toggle_player_on_move(),
+            (Mode::Editor, Keycode::C) => self.editor.clear(),
+            (Mode::Editor, Keycode::I) => self.editor.set_position(Position::default()),
+            (Mode::Editor, Keycode::Return) => match self.editor.validate() {
+                Ok(()) => {
+                    self.engine.new_game();
+                    self.game = Game::new(*self.editor.get_position());
+                    self.mode = Mode::Setup;
+                }
+                Err(err) => self.show_message(err),
+            },
+            (Mode::Playing, Keycode::F) => self.flipped = !self.flipped,
+            (Mode::Playing, Keycode::Left)
+            | (Mode::Playing, Keycode::Right)
+            | (Mode::Playing, Keycode::Home)
+            | (Mode::Playing, Keycode::End) => {
+                match key {
+                    Keycode::Left => self.move_list.step(-1),
+                    Keycode::Right => self.move_list.step(1),
+                    Keycode::Home => self.move_list.view(0),
+                    _ => self.move_list.view(self.move_list.get_move_count()),
+                }
+                self.selection.clear();
+            }
+            (Mode::Playing, Keycode::N) => {
+                self.engine.new_game();
+                self.game = Game::default();
+                self.clock = None;
+                self.promotion = None;
+                self.selection.clear();
+                self.mode = Mode::Setup;
+            }
+            _ => {}
+        }
+    }
+
+    // Pressing on an own piece picks it up, pressing elsewhere with a piece
+    // selected is the second click of click-click
+    fn press_on_board(&mut self, x: i32, y: i32) -> Flow {
+        let pos = self.get_board_position();
+        self.selection.set_mouse_position(x, y);
+        let square = get_square_at(x, y, self.flipped);
+        let own_piece = square.and_then(|(i, j)| pos.get_player_on_position(i, j))
+            == Some(pos.get_player_on_move());
+        if own_piece {
+            self.selection.pick(&pos, square.unwrap());
+            return Flow::Continue;
+        }
+        let played = match (self.selection.get_square(), square) {
+            (Some(from), Some(to)) => self.play_human_move(from, to),
+            _ => false,
+        };
+        self.selection.clear();
+        if played {
+            Flow::NextFrame
+        } else {
+            Flow::Continue
+        }
+    }
+
+    // Dropping on the square it came from keeps the piece selected for
+    // click-click, an invalid drop snaps it back
+    fn release_on_board(&mut self, x: i32, y: i32) -> Flow {
+        self.selection.release();
+        match (
+            self.selection.get_square(),
+            get_square_at(x, y, self.flipped),
+        ) {
+            (Some(from), Some(to)) if from != to => {
+                let played = self.play_human_move(from, to);
+                self.selection.clear();
+                if played {
+                    Flow::NextFrame
+                } else {
+                    Flow::Continue
+                }
+            }
+            _ => Flow::Continue,
+        }
+    }
+
+    // Plays the move of the human from one square to another, false if it is
+    // not valid. A valid promotion only opens the chooser.
+    fn play_human_move(&mut self, from: (usize, usize), to: (usize, usize)) -> bool {
+        let m = (from.0, from.1, to.0, to.1);
+        let pos = *self.game.get_position();
+        if pos.is_promotion(m) && pos.get_valid_moves().contains(&m) {
+            self.promotion = Some(PromotionChooser::new(m, pos.get_player_on_move()));
+            return false;
+        }
+        self.game.make_move(m)
+    }
+
+    // Plays the move of the engine once it is found, runs the clocks and
+    // starts the engine when it is on move
+    fn update_game(&mut self) {
+        if self.mode != Mode::Playing {
+            return;
+        }
+        // a move found after the flag fell does not count
+        if self
+            .clock
+            .as_ref()
+            .and_then(|clock| clock.get_flagged())
+            .is_some()
+        {
+            self.engine.cancel();
+        }
+        if let Some(Some(m)) = self.engine.poll() {
+            self.game.make_move(m);
+        }
+        // the clock of the player on move runs until the game ends
+        let result = self.get_result();
+        let pos = *self.game.get_position();
+        if let Some(clock) = self.clock.as_mut() {
+            match result {
+                Some(_) => clock.stop(),
+                None => clock.run(pos.get_player_on_move()),
+            }
+        }
+        let engine_player = self.setup.get_human_player().get_opponent();
+        let engine_on_move = pos.get_player_on_move() == engine_player;
+        if engine_on_move && !self.engine.is_thinking() && result.is_none() {
+            let time_control = self
+                .clock
+                .as_ref()
+                .map(|clock| clock.get_time_control(engine_player));
+            self.engine
+                .start(&self.game, self.setup.get_strength(), time_control);
+        }
+    }
+}
 
 impl ChessDrawing {
-    pub fn new() -> Self {
-        Self {}
+    pub fn new(pgn_path: Option<String>) -> Self {
+        Self { pgn_path }
     }
     pub fn draw(&self) {
         assert_eq!(WIDTH % 8, 0);
         assert_eq!(HEIGHT % 8, 0);
-        let mut pos: Position = Position::default();
+        let mut state = GuiState::new(self.pgn_path.as_ref());
         //let mut pos: Position = rand::random();
         let sdl_context = sdl2::init().unwrap();
         let video_subsystem = sdl_context.video().unwrap();
         let window = video_subsystem
-            .window("rust-sdl2 demo", WIDTH, HEIGHT)
+            .window("chess", WIDTH + PANEL_WIDTH, HEIGHT)
             .position_centered()
             .build()
             .unwrap();
         let mut canvas = window.into_canvas().build().unwrap();
+        canvas.set_blend_mode(BlendMode::Blend);
         let texture_creator = canvas.texture_creator();
         let chess_pieces_textures: Vec<Texture> = CHESS_PIECES_TEXTURES_PATHS
             .iter()
@@ -71,115 +773,29 @@ impl ChessDrawing {
         //canvas.clear();
         //canvas.present();
         let mut event_pump = sdl_context.event_pump().unwrap();
+        let clipboard = video_subsystem.clipboard();
+        let text_input = video_subsystem.text_input();
+        text_input.stop();
 
-        let (mut a, mut b, mut c, mut d) = (0, 0, 0, 0);
-        let mut first = true;
-        let mut up = 0;
-        let mut last_up = 0;
-        let mut valid_moves: Vec<(usize, usize, usize, usize)> = Vec::new();
+        let mut title = String::new();
         'running: loop {
-            canvas.clear();
-            canvas.set_draw_color(Color::RGB(0, 255, 255));
-            for i in 0..8 {
-                for j in 0..8 {
-                    let maybe_player = pos.get_player_on_position(i, j);
-                    if let Some(player) = maybe_player {
-                        let num = match player {
-                            ChessPlayer::White => {
-                                pos.get_piece_on_position(i, j).unwrap().to_usize().unwrap()
-                            }
-                            ChessPlayer::Black => {
-                                pos.get_piece_on_position(i, j).unwrap().to_usize().unwrap() + 6
-                            }
-                        };
-
-                        canvas
-                            .copy(
-                                &chess_pieces_textures[num],
-                                None,
-                                Rect::new(
-                                    (j as i32) * ((WIDTH / 8) as i32),
-                                    (7 - i as i32) * ((HEIGHT / 8) as i32),
-                                    WIDTH / 8,
-                                    HEIGHT / 8,
-                                ),
-                            )
-                            .unwrap();
-
-                        //valid moves
-                        canvas.set_draw_color(Color::RGB(255, 0, 0));
-                        for (_, _, to_i, to_j) in valid_moves.iter() {
-                            canvas
-                                .fill_rect(Rect::new(
-                                    (*to_j as i32) * ((WIDTH / 8) as i32),
-                                    (7 - *to_i as i32) * ((HEIGHT / 8) as i32),
-                                    WIDTH / 80,
-                                    HEIGHT / 80,
-                                ))
-                                .unwrap();
-                        }
-                        canvas.set_draw_color(Color::RGB(0, 255, 255));
-                    }
-                }
-            }
+            state.move_list.update(&state.game);
+            state.render(&mut canvas, &chess_pieces_textures);
+
             for event in event_pump.poll_iter() {
-                match event {
-                    Event::Quit { .. }
-                    | Event::KeyDown {
-                        keycode: Some(Keycode::Escape),
-                        ..
-                    } => break 'running,
-                    _ => {}
+                match state.handle_event(event, &clipboard, &text_input) {
+                    Flow::Continue => {}
+                    Flow::NextFrame => break,
+                    Flow::Quit => break 'running,
                 }
             }
 
-            let mouse_state = event_pump.mouse_state();
-            if mouse_state.left() && first && up == last_up {
-                // println!("A");
-                a = mouse_state.x() / ((WIDTH / 8) as i32);
-                b = mouse_state.y() / ((HEIGHT / 8) as i32);
-
-                let maybe_player = pos.get_player_on_position(7 - b as usize, a as usize);
-
-                if maybe_player.is_none() || (maybe_player.unwrap() != pos.get_player_on_move()) {
-                } else {
-                    valid_moves = pos
-                        .get_valid_moves()
-                        .iter()
-                        .filter(|(k, l, _, _)| *k == (7 - b as usize) && *l == (a as usize))
-                        .cloned()
-                        .collect();
-
-                    first = false;
-                    last_up += 1;
-                }
-            } else if mouse_state.left() && (!first) && up == last_up {
-                // println!("B");
-                c = mouse_state.x() / ((WIDTH / 8) as i32);
-                d = mouse_state.y() / ((HEIGHT / 8) as i32);
-                let maybe_player = pos.get_player_on_position(7 - d as usize, c as usize);
-                if maybe_player.is_some() && maybe_player.unwrap() == pos.get_player_on_move() {
-                    first = true;
-                } else {
-                    let m = pos.clone().make_move(
-                        7 - b as usize,
-                        a as usize,
-                        7 - d as usize,
-                        c as usize,
-                    );
-                    if m {
-                        println!("Good move!");
-                        pos.make_move(7 - b as usize, a as usize, 7 - d as usize, c as usize);
-                    } else {
-                        println!("Bad move!");
-                    }
-                    first = true;
-                    last_up += 1;
-                }
-            } else if (!mouse_state.left()) && last_up != up {
-                // println!("C");
-                up += 1;
+            let new_title = state.get_title();
+            if new_title != title {
+                canvas.window_mut().set_title(&new_title).unwrap();
+                title = new_title;
             }
+            state.update_game();
 
             canvas.present();
             ::std::thread::sleep(Duration::new(0, 1_000_000_000u32 / 60));
@@ -188,7 +804,7 @@ impl ChessDrawing {
 }
 
 fn main() {
-    let draw = ChessDrawing::new();
+    let draw = ChessDrawing::new(std::env::args().nth(1));
     draw.draw();
     /*for _ in 0..100 {
         let pos: Position = rand::random();
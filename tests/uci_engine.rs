@@ -0,0 +1,66 @@
+use chess::chess::position::Position;
+use chess::chess::uci_engine::{parse_info, EngineScore, SearchLimit, UciEngine};
+use std::time::{Duration, Instant};
+
+fn start_stub() -> UciEngine {
+    UciEngine::start(env!("CARGO_BIN_EXE_stub_engine"), &[]).unwrap()
+}
+
+#[test]
+fn handshake_reads_id_and_options() {
+    let engine = start_stub();
+    assert_eq!(engine.get_name(), "stub");
+    assert_eq!(engine.get_author(), "Aj0SK");
+    assert_eq!(engine.get_options(), ["Delay"]);
+}
+
+#[test]
+fn go_returns_best_move_and_info() {
+    let mut engine = start_stub();
+    engine.new_game().unwrap();
+    // 1. e4 d5, the stub takes the most material: exd5
+    let moves = [(1, 4, 3, 4), (6, 3, 4, 3)];
+    engine.set_position(&Position::default(), &moves).unwrap();
+    let result = engine
+        .go(SearchLimit::Depth(1), Duration::from_secs(10))
+        .unwrap();
+    assert_eq!(result.best_move, Some((3, 4, 4, 3)));
+    let info = result.get_last_info().unwrap();
+    assert_eq!(info.depth, Some(1));
+    assert_eq!(info.pv, vec![(3, 4, 4, 3)]);
+}
+
+#[test]
+fn set_position_rejects_invalid_moves() {
+    let mut engine = start_stub();
+    assert!(engine
+        .set_position(&Position::default(), &[(1, 4, 4, 4)])
+        .is_err());
+}
+
+#[test]
+fn go_times_out_when_engine_does_not_answer() {
+    let mut engine = start_stub();
+    engine.set_option("Delay", "3000").unwrap();
+    engine.set_position(&Position::default(), &[]).unwrap();
+    let start = Instant::now();
+    // the stub ignores stop while it sleeps, so it misses the stop timeout too
+    let result = engine.go(SearchLimit::Depth(1), Duration::from_millis(200));
+    assert!(result.is_err());
+    assert!(start.elapsed() < Duration::from_millis(2500));
+}
+
+#[test]
+fn info_fields_with_several_values_are_skipped() {
+    let pos = Position::default();
+    let line = "info depth 12 seldepth 18 multipv 1 score cp 31 lowerbound wdl 120 820 60 \
+                nodes 52013 nps 1000000 hashfull 17 tbhits 0 time 52 pv e2e4 e7e5 g1f3";
+    let info = parse_info(&pos, line).unwrap();
+    assert_eq!(info.depth, Some(12));
+    assert_eq!(info.seldepth, Some(18));
+    assert_eq!(info.multi_pv, Some(1));
+    assert_eq!(info.score, Some(EngineScore::Centipawns(31)));
+    assert_eq!(info.nodes, Some(52013));
+    assert_eq!(info.time, Some(Duration::from_millis(52)));
+    assert_eq!(info.pv, vec![(1, 4, 3, 4), (6, 4, 4, 4), (0, 6, 2, 5)]);
+}
@@ -0,0 +1,173 @@
+use chess::chess::position::Position;
+use chess::chess::uci::parse_move;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// The engine binary speaking xboard, its output is read on a separate thread
+struct XboardEngine {
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+}
+
+impl XboardEngine {
+    fn start() -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_uci"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        let mut engine = Self {
+            child,
+            stdin,
+            lines,
+        };
+        engine.send("xboard");
+        engine.send("protover 2");
+        engine.expect("feature done=1", Duration::from_secs(5));
+        engine
+    }
+
+    fn send(&mut self, command: &str) {
+        writeln!(self.stdin, "{}", command).unwrap();
+    }
+
+    // Reads lines until one starts with prefix, lines before it are returned
+    // too
+    fn expect(&mut self, prefix: &str, timeout: Duration) -> Vec<String> {
+        let deadline = Instant::now() + timeout;
+        let mut lines = Vec::new();
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let line = self
+                .lines
+                .recv_timeout(remaining)
+                .unwrap_or_else(|_| panic!("no {} in {:?}", prefix, lines));
+            let found = line.starts_with(prefix);
+            lines.push(line);
+            if found {
+                return lines;
+            }
+        }
+    }
+
+    // Waits until every command sent so far is processed
+    fn sync(&mut self) -> Vec<String> {
+        self.send("ping 1");
+        let mut lines = self.expect("pong 1", Duration::from_secs(5));
+        lines.pop();
+        lines
+    }
+}
+
+impl Drop for XboardEngine {
+    fn drop(&mut self) {
+        let _ = writeln!(self.stdin, "quit");
+        let _ = self.child.wait();
+    }
+}
+
+fn get_move(line: &str) -> &str {
+    line.strip_prefix("move ").unwrap()
+}
+
+#[test]
+fn engine_answers_user_moves_as_black() {
+    let mut engine = XboardEngine::start();
+    engine.send("new");
+    engine.send("sd 2");
+    engine.send("usermove e2e4");
+    let lines = engine.expect("move ", Duration::from_secs(10));
+    let mut pos = Position::default();
+    let m = parse_move(&pos, "e2e4").unwrap();
+    pos.make_move(m.0, m.1, m.2, m.3);
+    assert!(parse_move(&pos, get_move(lines.last().unwrap())).is_some());
+}
+
+#[test]
+fn setboard_plays_the_mate_and_claims_the_result() {
+    let mut engine = XboardEngine::start();
+    engine.send("new");
+    engine.send("force");
+    engine.send("setboard k7/8/1K6/8/8/8/8/7R w - - 0 1");
+    engine.send("sd 3");
+    engine.send("go");
+    let lines = engine.expect("move ", Duration::from_secs(10));
+    assert_eq!(get_move(lines.last().unwrap()), "h1h8");
+    let lines = engine.expect("1-0", Duration::from_secs(5));
+    assert_eq!(lines, ["1-0 {White wins by checkmate}"]);
+}
+
+#[test]
+fn setboard_keeps_the_halfmove_clock() {
+    let mut engine = XboardEngine::start();
+    engine.send("new");
+    engine.send("force");
+    // any move is the hundredth without a capture or a pawn move
+    engine.send("setboard k7/8/8/8/8/8/8/K6R w - - 99 80");
+    engine.send("sd 2");
+    engine.send("go");
+    engine.expect("move ", Duration::from_secs(10));
+    let lines = engine.expect("1/2-1/2", Duration::from_secs(5));
+    assert_eq!(lines, ["1/2-1/2 {Draw by the fifty move rule}"]);
+}
+
+#[test]
+fn level_and_st_limit_the_thinking_time() {
+    let mut engine = XboardEngine::start();
+    engine.send("new");
+    engine.send("force");
+    engine.send("st 0.2");
+    let start = Instant::now();
+    engine.send("go");
+    engine.expect("move ", Duration::from_secs(5));
+    assert!(start.elapsed() < Duration::from_secs(2));
+
+    // one second left on a clock of two minutes, the opponent has a minute
+    engine.send("new");
+    engine.send("force");
+    engine.send("level 0 2 0");
+    engine.send("time 100");
+    engine.send("otim 6000");
+    let start = Instant::now();
+    engine.send("go");
+    engine.expect("move ", Duration::from_secs(5));
+    assert!(start.elapsed() < Duration::from_secs(2));
+
+    engine.send("level 40");
+    engine.send("st -1");
+    engine.send("otim x");
+    assert_eq!(
+        engine.sync(),
+        [
+            "Error (invalid level): 40",
+            "Error (invalid time): -1",
+            "Error (invalid time): x"
+        ]
+    );
+}
+
+#[test]
+fn engine_stops_playing_after_result() {
+    let mut engine = XboardEngine::start();
+    engine.send("new");
+    engine.send("sd 1");
+    engine.send("result 1-0 {White resigns}");
+    engine.send("usermove e2e4");
+    assert!(engine.sync().is_empty());
+    thread::sleep(Duration::from_millis(300));
+    assert!(engine.sync().is_empty());
+}